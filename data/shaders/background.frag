@@ -0,0 +1,30 @@
+#version 330 core
+
+in vec2 v_uv;
+
+// Background modes, must match BACKGROUND_* in graphics.rs
+const float MODE_GRADIENT = 0.0;
+const float MODE_STRETCH = 1.0;
+const float MODE_TILE = 2.0;
+
+uniform float u_mode;
+uniform vec4 u_top;      // gradient top color or texture tint
+uniform vec4 u_bottom;   // gradient bottom color
+uniform vec4 u_area;     // texture area within the atlas
+uniform vec2 u_tiles;    // how many times the texture is repeated across the screen
+
+uniform sampler2D u_texture;
+
+out vec4 out_color;
+
+void main() {
+    if (u_mode < MODE_STRETCH - 0.5) {
+        out_color = mix(u_bottom, u_top, v_uv.y);
+    } else {
+        vec2 uv = v_uv;
+        if (u_mode > MODE_TILE - 0.5) {
+            uv = fract(uv * u_tiles);
+        }
+        out_color = u_top * texture(u_texture, mix(u_area.xy, u_area.zw, uv));
+    }
+}
@@ -0,0 +1,27 @@
+#version 330 core
+
+#include "post.glsl"
+
+uniform float u_threshold;  // brightness above which pixels start to glow
+uniform float u_intensity;
+uniform float u_radius;     // blur radius in pixels
+
+void main() {
+    vec4 color = texture(u_source, v_uv);
+    vec2 texel = u_radius / u_resolution;
+
+    // Sample bright parts of the image on a small grid around the pixel
+    vec3 glow = vec3(0);
+    float total = 0;
+    for (int x = -2; x <= 2; x++) {
+        for (int y = -2; y <= 2; y++) {
+            float weight = 1.0 / (1.0 + float(x * x + y * y));
+            vec3 sample_color = texture(u_source, v_uv + vec2(x, y) * texel).rgb;
+            float brightness = max(max(sample_color.r, sample_color.g), sample_color.b);
+            glow += sample_color * max(brightness - u_threshold, 0) * weight;
+            total += weight;
+        }
+    }
+
+    out_color = vec4(color.rgb + glow / total * u_intensity, color.a);
+}
@@ -0,0 +1,30 @@
+#version 330 core
+
+#include "post.glsl"
+
+// Lookup table laid out as a horizontal strip of u_lut_size slices, each u_lut_size pixels square.
+// Red grows to the right within a slice, green grows upwards and blue grows from slice to slice.
+uniform sampler2D u_lut;
+uniform float u_lut_size;
+uniform float u_strength;
+
+vec3 lookup(float slice, vec2 red_green) {
+    vec2 half_texel = vec2(0.5 / (u_lut_size * u_lut_size), 0.5 / u_lut_size);
+    vec2 scale = vec2(1.0 / u_lut_size, 1.0) - half_texel * 2.0;
+    vec2 uv = vec2(slice / u_lut_size, 0) + half_texel + red_green * scale;
+    return texture(u_lut, uv).rgb;
+}
+
+void main() {
+    vec4 color = texture(u_source, v_uv);
+    vec3 clamped = clamp(color.rgb, 0, 1);
+
+    // Blue is interpolated manually between two neighbouring slices
+    float blue = clamped.b * (u_lut_size - 1.0);
+    float slice = floor(blue);
+    vec3 low = lookup(slice, clamped.rg);
+    vec3 high = lookup(min(slice + 1.0, u_lut_size - 1.0), clamped.rg);
+    vec3 graded = mix(low, high, blue - slice);
+
+    out_color = vec4(mix(color.rgb, graded, u_strength), color.a);
+}
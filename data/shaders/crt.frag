@@ -0,0 +1,23 @@
+#version 330 core
+
+#include "post.glsl"
+
+uniform float u_curvature;        // how much the screen bulges, 0 is flat
+uniform float u_scanline_count;   // scanlines over the full height
+uniform float u_scanline_intensity;
+
+void main() {
+    // Barrel distortion
+    vec2 centered = v_uv * 2.0 - 1.0;
+    centered *= 1.0 + u_curvature * dot(centered, centered);
+    vec2 uv = centered * 0.5 + 0.5;
+
+    if (uv.x < 0 || uv.x > 1 || uv.y < 0 || uv.y > 1) {
+        out_color = vec4(0, 0, 0, 1);
+        return;
+    }
+
+    vec4 color = texture(u_source, uv);
+    float scanline = 0.5 + 0.5 * sin((uv.y * u_scanline_count + u_time) * 3.14159265 * 2.0);
+    out_color = vec4(color.rgb * mix(1.0, scanline, u_scanline_intensity), color.a);
+}
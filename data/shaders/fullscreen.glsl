@@ -0,0 +1,13 @@
+// Vertex stage shared by all full-screen post-processing passes
+//
+// Stretches the unit quad over the whole target.
+
+in vec2 position;
+in vec2 tex_coords;
+
+out vec2 v_uv;
+
+void main() {
+    v_uv = tex_coords;
+    gl_Position = vec4(position * 2.0, 0, 1);
+}
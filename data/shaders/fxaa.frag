@@ -0,0 +1,46 @@
+#version 330 core
+
+#include "post.glsl"
+
+// A simplified single-pass FXAA
+uniform float u_span_max;
+uniform float u_reduce_min;
+uniform float u_reduce_mul;
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+void main() {
+    vec2 texel = 1.0 / u_resolution;
+
+    float luma_nw = luma(texture(u_source, v_uv + vec2(-1, -1) * texel).rgb);
+    float luma_ne = luma(texture(u_source, v_uv + vec2(1, -1) * texel).rgb);
+    float luma_sw = luma(texture(u_source, v_uv + vec2(-1, 1) * texel).rgb);
+    float luma_se = luma(texture(u_source, v_uv + vec2(1, 1) * texel).rgb);
+    vec4 center = texture(u_source, v_uv);
+    float luma_m = luma(center.rgb);
+
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // Blur along the edge, which is perpendicular to the luma gradient
+    vec2 direction = vec2(-((luma_nw + luma_ne) - (luma_sw + luma_se)), (luma_nw + luma_sw) - (luma_ne + luma_se));
+    float reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * u_reduce_mul, u_reduce_min);
+    float scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2(-u_span_max), vec2(u_span_max)) * texel;
+
+    vec3 near = 0.5 * (
+        texture(u_source, v_uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+        texture(u_source, v_uv + direction * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 far = near * 0.5 + 0.25 * (
+        texture(u_source, v_uv - direction * 0.5).rgb +
+        texture(u_source, v_uv + direction * 0.5).rgb);
+
+    float luma_far = luma(far);
+    if (luma_far < luma_min || luma_far > luma_max) {
+        out_color = vec4(near, center.a);
+    } else {
+        out_color = vec4(far, center.a);
+    }
+}
@@ -1,5 +1,7 @@
 #version 330 core
 
+#include "transform.glsl"
+
 // Per vertex data
 in vec2 position;
 in vec2 tex_coords;
@@ -13,37 +15,32 @@ in vec4 i_color_lit;
 in vec4 i_color_unlit;
 in vec4 i_texture_lit;
 in vec4 i_texture_unlit;
+in vec4 i_uv_transform;
+in vec4 i_tint;
 
 // Uniform data
-uniform vec2 u_scale;       // camera screen-space transformations
-uniform vec2 u_translation;
+uniform mat3 u_camera;      // world to camera space, see camera_matrix() in graphics.rs
 
 out vec2 v_coords_lit;
 out vec2 v_coords_unlit;
 out vec4 v_color_lit;
 out vec4 v_color_unlit;
 out vec2 v_position;
+out vec4 v_tint;
+flat out float v_tint_mode;
 
 void main() {
-    float sinTheta = sin(i_z_theta[1]);
-    float cosTheta = cos(i_z_theta[1]);
-
-    mat2 rotation;
-    rotation[0] = vec2(cosTheta, sinTheta);
-    rotation[1] = vec2(-sinTheta, cosTheta);
-
-    vec2 pos = rotation * position;
-    pos *= i_scale;
-
-    pos += i_translation;
+    vec2 pos = transform_point(position, i_z_theta[1], i_scale) + i_translation;
     v_position = pos;
 
-    pos -= u_translation;
-    pos *= u_scale;
+    pos = world_to_camera(pos, u_camera);
 
-    v_coords_lit = i_texture_lit.xy + (i_texture_lit.zw - i_texture_lit.xy) * tex_coords;
-    v_coords_unlit = i_texture_unlit.xy + (i_texture_unlit.zw - i_texture_unlit.xy) * tex_coords;
+    vec2 uv = transform_uv(tex_coords, i_uv_transform);
+    v_coords_lit = i_texture_lit.xy + (i_texture_lit.zw - i_texture_lit.xy) * uv;
+    v_coords_unlit = i_texture_unlit.xy + (i_texture_unlit.zw - i_texture_unlit.xy) * uv;
     v_color_lit = i_color_lit;
     v_color_unlit = i_color_unlit;
+    v_tint = i_tint;
+    v_tint_mode = i_uv_transform.w;
     gl_Position = vec4(pos, i_z_theta[0], 1);
 }
\ No newline at end of file
@@ -0,0 +1,11 @@
+// Lighting around the view origin shared by all lit shaders
+
+uniform vec2 u_view_origin;
+uniform float u_view_distance;
+uniform float u_view_sharpness;
+
+// How lit a world space position is, 1 is fully lit and 0 is fully unlit
+float light_ratio(vec2 position) {
+    float dist = distance(u_view_origin, position);
+    return clamp((u_view_distance - dist) * u_view_sharpness, 0, 1);
+}
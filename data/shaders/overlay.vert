@@ -0,0 +1,19 @@
+#version 330 core
+
+// Per vertex data, positions are in pixels with y pointing down
+in vec2 position;
+in vec2 tex_coords;
+in vec4 color;
+
+uniform vec2 u_target_size;
+
+out vec2 v_tex_coords;
+out vec4 v_color;
+
+void main() {
+    vec2 normalized = position / u_target_size;
+    gl_Position = vec4(normalized.x * 2.0 - 1.0, 1.0 - normalized.y * 2.0, 0, 1);
+
+    v_tex_coords = tex_coords;
+    v_color = color;
+}
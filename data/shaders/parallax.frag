@@ -0,0 +1,35 @@
+#version 330 core
+
+in vec2 v_world;
+
+uniform vec2 u_translation;    // center of the view
+uniform vec4 u_area;           // texture area within the atlas
+uniform vec2 u_scroll_factor;
+uniform vec2 u_offset;
+uniform vec2 u_size;
+uniform vec2 u_repeat;         // 1 if the layer is tiled along the axis, 0 otherwise
+uniform vec4 u_color;
+
+uniform sampler2D u_texture;
+
+out vec4 out_color;
+
+void main() {
+    // Layers with smaller scroll factors lag behind the view center
+    vec2 layer = v_world - u_translation * (1.0 - u_scroll_factor);
+    vec2 uv = (layer - u_offset) / u_size;
+
+    if (u_repeat.x > 0.5) {
+        uv.x = fract(uv.x);
+    } else if (uv.x < 0 || uv.x > 1) {
+        discard;
+    }
+    if (u_repeat.y > 0.5) {
+        uv.y = fract(uv.y);
+    } else if (uv.y < 0 || uv.y > 1) {
+        discard;
+    }
+
+    out_color = u_color * texture(u_texture, mix(u_area.xy, u_area.zw, uv));
+    if (out_color.a == 0) discard;
+}
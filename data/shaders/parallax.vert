@@ -0,0 +1,18 @@
+#version 330 core
+
+#include "transform.glsl"
+
+// Per vertex data, the quad is stretched over the whole screen
+in vec2 position;
+in vec2 tex_coords;
+
+// Uniform data
+uniform mat3 u_camera_inverse;  // camera to world space
+
+out vec2 v_world;
+
+void main() {
+    vec2 pos = position * 2.0;
+    v_world = camera_to_world(pos, u_camera_inverse);
+    gl_Position = vec4(pos, 0, 1);
+}
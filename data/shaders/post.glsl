@@ -0,0 +1,9 @@
+// Inputs shared by all full-screen post-processing passes
+
+in vec2 v_uv;
+
+uniform sampler2D u_source;  // result of the previous pass
+uniform vec2 u_resolution;   // size of the target in pixels
+uniform float u_time;        // seconds since post-processing was created
+
+out vec4 out_color;
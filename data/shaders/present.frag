@@ -0,0 +1,8 @@
+#version 330 core
+
+#include "post.glsl"
+
+// Copy the scene into the window, filtering is set up on the sampler
+void main() {
+    out_color = texture(u_source, v_uv);
+}
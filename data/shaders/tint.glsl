@@ -0,0 +1,15 @@
+// Tint modes shared by all sprite shaders, must match TintMode in instance.rs
+
+const float TINT_MULTIPLY = 0.0;
+const float TINT_ADDITIVE = 1.0;
+const float TINT_REPLACE = 2.0;
+
+vec4 apply_tint(vec4 color, vec4 tint, float mode) {
+    if (mode < TINT_ADDITIVE - 0.5) {
+        return color * tint;
+    } else if (mode < TINT_REPLACE - 0.5) {
+        return vec4(color.rgb + tint.rgb * tint.a, color.a);
+    } else {
+        return vec4(mix(color.rgb, tint.rgb, tint.a), color.a);
+    }
+}
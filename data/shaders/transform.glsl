@@ -0,0 +1,35 @@
+// Transformations shared by all instanced shaders
+
+// Rotate a point around the origin by theta radians, then scale it along world axes
+vec2 transform_point(vec2 point, float theta, vec2 scale) {
+    float sinTheta = sin(theta);
+    float cosTheta = cos(theta);
+
+    mat2 rotation;
+    rotation[0] = vec2(cosTheta, sinTheta);
+    rotation[1] = vec2(-sinTheta, cosTheta);
+
+    return (rotation * point) * scale;
+}
+
+// Move a world space point into camera space
+vec2 world_to_camera(vec2 world, mat3 camera) {
+    return (camera * vec3(world, 1.0)).xy;
+}
+
+// Move a camera space point back into world space, camera_inverse undoes the camera matrix
+vec2 camera_to_world(vec2 camera, mat3 camera_inverse) {
+    return (camera_inverse * vec3(camera, 1.0)).xy;
+}
+
+// Flip and rotate texture coordinates within the unit square
+//
+// uv_transform holds (x scale, y scale, counter-clockwise quarter turns, unused).
+vec2 transform_uv(vec2 uv, vec4 uv_transform) {
+    vec2 centered = (uv - 0.5) * uv_transform.xy;
+    int quarter_turns = int(uv_transform.z + 0.5);
+    for (int i = 0; i < quarter_turns; i++) {
+        centered = vec2(-centered.y, centered.x);
+    }
+    return centered + 0.5;
+}
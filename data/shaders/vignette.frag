@@ -0,0 +1,14 @@
+#version 330 core
+
+#include "post.glsl"
+
+uniform float u_radius;    // distance from the center where darkening starts, 1 is the corner
+uniform float u_softness;  // width of the transition
+uniform float u_strength;
+
+void main() {
+    vec4 color = texture(u_source, v_uv);
+    float dist = length((v_uv - 0.5) * 2.0) / sqrt(2.0);
+    float vignette = smoothstep(u_radius, u_radius - u_softness, dist);
+    out_color = vec4(color.rgb * mix(1.0, vignette, u_strength), color.a);
+}
@@ -0,0 +1,247 @@
+// Benchmark mode
+//
+// Started with --bench, draws the test scene at several instance counts and batch sizes for a fixed number of frames
+// and writes frametime percentiles into a report, so batch sizes can be compared across machines.
+//
+// Options (all optional):
+//   --bench-counts=1000,10000   instance counts to draw
+//   --bench-batches=256,1024    batch sizes to try for every instance count
+//   --bench-frames=300          measured frames per run, preceded by a shorter warm-up
+//   --bench-output=bench.json   report file, written as CSV if the extension is .csv and as JSON otherwise
+
+use graphics::scene::TestScene;
+use graphics::{Graphics, TextureCollection};
+use random::Random;
+
+use glium::glutin;
+
+use std::time::{Duration, Instant};
+
+const DEFAULT_COUNTS: [u32; 4] = [1000, 10000, 50000, 100000];
+const DEFAULT_BATCHES: [usize; 5] = [64, 256, 1024, 4096, 16384];
+const DEFAULT_FRAMES: u32 = 300;
+const DEFAULT_OUTPUT: &str = "bench_report.json";
+
+/// Warm-up frames are a fraction of measured ones, they let drivers settle after buffers are recreated
+const WARMUP_DIVISOR: u32 = 10;
+
+/// Simulated time per frame in seconds, fixed so that every run does the same work
+const BENCH_STEP: f32 = 1.0 / 60.0;
+/// Every run draws the same scene
+const BENCH_SEED: u64 = 0;
+
+// Textures the test scene is drawn with
+const LIT_TEXTURE: &str = "test.png";
+const UNLIT_TEXTURE: &str = "dark.png";
+
+#[derive(Debug, Clone)]
+pub struct BenchSettings {
+	pub instance_counts: Vec<u32>,
+	pub batch_sizes: Vec<usize>,
+	pub frames: u32,
+	pub output: String,
+}
+
+/// Frametime statistics of a single instance count and batch size pair, in milliseconds
+#[derive(Serialize, Debug, Clone)]
+pub struct BenchResult {
+	pub instances: u32,
+	pub batch_size: usize,
+	pub frames: u32,
+	pub mean: f32,
+	pub p50: f32,
+	pub p90: f32,
+	pub p95: f32,
+	pub p99: f32,
+	pub max: f32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BenchReport {
+	pub renderer: String,
+	pub version: String,
+	pub results: Vec<BenchResult>,
+}
+
+impl BenchSettings {
+	/// Parse benchmark options, returns None if benchmark mode wasn't requested
+	pub fn from_args<I: Iterator<Item = String>>(args: I) -> Option<Self> {
+		let mut enabled = false;
+		let mut settings = Self {
+			instance_counts: DEFAULT_COUNTS.to_vec(),
+			batch_sizes: DEFAULT_BATCHES.to_vec(),
+			frames: DEFAULT_FRAMES,
+			output: String::from(DEFAULT_OUTPUT),
+		};
+
+		for arg in args {
+			let mut parts = arg.splitn(2, '=');
+			let (name, value) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+			match name {
+				"--bench" => enabled = true,
+				"--bench-counts" => settings.instance_counts = parse_list(name, value),
+				"--bench-batches" => settings.batch_sizes = parse_list(name, value),
+				"--bench-frames" => settings.frames = parse_list(name, value).pop().unwrap_or(DEFAULT_FRAMES),
+				"--bench-output" => settings.output = String::from(value),
+				_ => continue,
+			}
+		}
+
+		settings.batch_sizes.retain(|&size| size > 0);
+		settings.frames = settings.frames.max(1);
+
+		if enabled {
+			Some(settings)
+		} else {
+			None
+		}
+	}
+}
+
+fn parse_list<T: std::str::FromStr>(name: &str, value: &str) -> Vec<T> {
+	value
+		.split(',')
+		.filter_map(|item| match item.trim().parse() {
+			Ok(item) => Some(item),
+			Err(_) => {
+				println!("Ignoring invalid value {:?} of {}", item, name);
+				None
+			}
+		}).collect()
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[f32], percent: f32) -> f32 {
+	let rank = (percent / 100.0 * sorted.len() as f32).ceil() as usize;
+	sorted[rank.max(1).min(sorted.len()) - 1]
+}
+
+fn millis(duration: Duration) -> f32 {
+	duration.as_secs() as f32 * 1000.0 + duration.subsec_nanos() as f32 / 1000000.0
+}
+
+/// Draw every configured scene and batch size combination
+///
+/// Returns early with partial results if the window gets closed.
+pub fn run(
+	settings: &BenchSettings,
+	graphics: &mut Graphics,
+	events_loop: &mut glutin::EventsLoop,
+	texture_collection: TextureCollection,
+) -> BenchReport {
+	let mut report = BenchReport {
+		renderer: String::from(graphics.display.get_opengl_renderer_string()),
+		version: String::from(graphics.display.get_opengl_version_string()),
+		results: Vec::new(),
+	};
+	let original_batch_size = graphics.batch_size();
+	let mut texture_collection = Some(texture_collection);
+	let mut closed = false;
+
+	'counts: for &count in settings.instance_counts.iter() {
+		// Keep the 16:9 grid of the regular test scene
+		let columns = ((count as f32 * 16.0 / 9.0).sqrt().round() as u32).max(1);
+		let rows = ((count + columns - 1) / columns).max(1);
+		let mut scene = TestScene::generate(
+			columns,
+			rows,
+			texture_collection.take().unwrap(),
+			String::from(LIT_TEXTURE),
+			String::from(UNLIT_TEXTURE),
+			Random::new(BENCH_SEED),
+		);
+
+		for &batch_size in settings.batch_sizes.iter() {
+			if let Err(error) = graphics.set_batch_size(batch_size) {
+				println!("Skipping batch size {}: {}", batch_size, error);
+				continue;
+			}
+
+			let warmup = settings.frames / WARMUP_DIVISOR;
+			let mut frametimes = Vec::with_capacity(settings.frames as usize);
+			for frame in 0..warmup + settings.frames {
+				let start = Instant::now();
+				scene.update(BENCH_STEP);
+				scene.interpolate(1.0);
+				graphics.draw(&scene);
+				events_loop.poll_events(|event| {
+					if let glutin::Event::WindowEvent {
+						event: glutin::WindowEvent::CloseRequested,
+						..
+					} = event
+					{
+						closed = true;
+					}
+				});
+
+				if frame >= warmup {
+					frametimes.push(millis(Instant::now().duration_since(start)));
+				}
+				if closed {
+					break 'counts;
+				}
+			}
+
+			frametimes.sort_by(|a, b| a.partial_cmp(b).unwrap());
+			let result = BenchResult {
+				instances: columns * rows,
+				batch_size: batch_size,
+				frames: frametimes.len() as u32,
+				mean: frametimes.iter().sum::<f32>() / frametimes.len() as f32,
+				p50: percentile(&frametimes, 50.0),
+				p90: percentile(&frametimes, 90.0),
+				p95: percentile(&frametimes, 95.0),
+				p99: percentile(&frametimes, 99.0),
+				max: *frametimes.last().unwrap(),
+			};
+			println!(
+				"{:>7} instances, batch {:>6}: mean {:>8.3} ms, p50 {:>8.3} ms, p99 {:>8.3} ms",
+				result.instances, result.batch_size, result.mean, result.p50, result.p99
+			);
+			report.results.push(result);
+		}
+
+		texture_collection = Some(scene.free_texture_collection());
+	}
+
+	if let Err(error) = graphics.set_batch_size(original_batch_size) {
+		println!("Failed to restore batch size {}: {}", original_batch_size, error);
+	}
+	report
+}
+
+impl BenchReport {
+	/// Write the report as CSV if the path ends with .csv, as JSON otherwise
+	pub fn save_as<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), Box<std::error::Error>> {
+		let path = path.as_ref();
+		let is_csv = match path.extension() {
+			Some(extension) => extension == "csv",
+			None => false,
+		};
+
+		let string = if is_csv {
+			let mut string = String::from("renderer,instances,batch_size,frames,mean,p50,p90,p95,p99,max\n");
+			for result in self.results.iter() {
+				string += &format!(
+					"\"{}\",{},{},{},{},{},{},{},{},{}\n",
+					self.renderer.replace('"', "\"\""),
+					result.instances,
+					result.batch_size,
+					result.frames,
+					result.mean,
+					result.p50,
+					result.p90,
+					result.p95,
+					result.p99,
+					result.max
+				);
+			}
+			string
+		} else {
+			serde_json::to_string_pretty(self)?
+		};
+
+		std::fs::write(path, string)?;
+		Ok(())
+	}
+}
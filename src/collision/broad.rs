@@ -0,0 +1,130 @@
+// Broad phase: finding pairs of shapes that might touch
+//
+// Sort and sweep over bounding boxes. Boxes are sorted by their left edge, so each box only has to be compared
+// with the following boxes that start before it ends.
+
+use graphics::math::Rect;
+
+/// Bounding boxes of a frame's shapes, keyed by whatever identifies them
+pub struct BroadPhase<T> {
+	entries: Vec<(T, Rect)>,
+	sorted: bool,
+}
+
+impl<T: Copy> BroadPhase<T> {
+	pub fn new() -> Self {
+		Self {
+			entries: Vec::new(),
+			sorted: true,
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Forget every box, typically at the start of a frame
+	pub fn clear(&mut self) {
+		self.entries.clear();
+		self.sorted = true;
+	}
+
+	pub fn insert(&mut self, key: T, bounds: Rect) {
+		self.entries.push((key, bounds.normalized()));
+		self.sorted = false;
+	}
+
+	fn sort(&mut self) {
+		if !self.sorted {
+			self.entries.sort_by(|a, b| a.1.min_x().partial_cmp(&b.1.min_x()).unwrap_or(std::cmp::Ordering::Equal));
+			self.sorted = true;
+		}
+	}
+
+	/// Every pair of keys whose boxes overlap, each pair is reported once
+	pub fn pairs(&mut self) -> Vec<(T, T)> {
+		self.sort();
+		let mut pairs = Vec::new();
+		for (index, &(key, ref bounds)) in self.entries.iter().enumerate() {
+			for &(other_key, ref other_bounds) in self.entries[index + 1..].iter() {
+				if other_bounds.min_x() > bounds.max_x() {
+					break;
+				}
+				if bounds.intersects(other_bounds) {
+					pairs.push((key, other_key));
+				}
+			}
+		}
+		pairs
+	}
+
+	/// Keys of every box overlapping an area
+	pub fn query(&mut self, area: &Rect) -> Vec<T> {
+		self.sort();
+		let area = area.normalized();
+		self.entries
+			.iter()
+			.take_while(|entry| entry.1.min_x() <= area.max_x())
+			.filter(|entry| entry.1.intersects(&area))
+			.map(|entry| entry.0)
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use graphics::vector::Vec2;
+
+	fn rect(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Rect {
+		Rect::new(Vec2::new(min_x, min_y), Vec2::new(max_x, max_y))
+	}
+
+	fn broad_phase() -> BroadPhase<usize> {
+		let mut broad_phase = BroadPhase::new();
+		// Inserted out of order, so that sorting matters
+		broad_phase.insert(3, rect(1.5, 5.0, 3.5, 6.0));
+		broad_phase.insert(1, rect(0.5, 0.5, 2.0, 2.0));
+		broad_phase.insert(4, rect(-10.0, 0.9, 10.0, 0.95)); // Long and thin, overlaps the sweep of every other box
+		broad_phase.insert(0, rect(0.0, 0.0, 1.0, 1.0));
+		broad_phase.insert(2, rect(3.0, 0.0, 4.0, 1.0));
+		broad_phase
+	}
+
+	fn sorted_pairs(pairs: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+		let mut pairs: Vec<_> = pairs.into_iter().map(|(a, b)| (a.min(b), a.max(b))).collect();
+		pairs.sort();
+		pairs
+	}
+
+	#[test]
+	fn pairs() {
+		let mut broad_phase = broad_phase();
+		assert_eq!(sorted_pairs(broad_phase.pairs()), vec![(0, 1), (0, 4), (1, 4), (2, 4)]);
+
+		broad_phase.insert(5, rect(3.5, 5.5, 4.0, 7.0)); // Shares only an edge with box 3
+		assert_eq!(sorted_pairs(broad_phase.pairs()), vec![(0, 1), (0, 4), (1, 4), (2, 4), (3, 5)]);
+	}
+
+	#[test]
+	fn query() {
+		let mut broad_phase = broad_phase();
+		let mut keys = broad_phase.query(&rect(3.2, 5.5, 3.3, 0.5)); // Corners in either order
+		keys.sort();
+		assert_eq!(keys, vec![2, 3, 4]);
+		assert!(broad_phase.query(&rect(20.0, 20.0, 21.0, 21.0)).is_empty());
+	}
+
+	#[test]
+	fn clear() {
+		let mut broad_phase = broad_phase();
+		assert_eq!(broad_phase.len(), 5);
+		broad_phase.clear();
+		assert!(broad_phase.is_empty());
+		assert!(broad_phase.pairs().is_empty());
+	}
+}
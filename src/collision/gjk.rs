@@ -0,0 +1,145 @@
+// Distance between convex hulls with the Gilbert-Johnson-Keerthi algorithm
+//
+// Iteratively builds a simplex of the Minkowski difference A - B that is closest to the origin.
+// Each simplex vertex remembers the points of A and B it came from, so closest points are recovered at the end.
+
+use super::shape::Hull;
+
+use graphics::vector::Vec2;
+
+const MAX_ITERATIONS: usize = 32;
+const EPSILON: f32 = 1e-6;
+
+/// Closest points of two unrounded hulls
+pub(super) struct Distance {
+	pub point_a: Vec2,
+	pub point_b: Vec2,
+	pub distance: f32, // Zero if the hulls overlap or touch
+}
+
+#[derive(Clone, Copy)]
+struct SimplexVertex {
+	a: Vec2,
+	b: Vec2,
+	w: Vec2, // a - b
+	weight: f32,
+}
+
+impl SimplexVertex {
+	fn new(hull_a: &Hull, hull_b: &Hull, direction: Vec2) -> Self {
+		let a = hull_a.support(direction);
+		let b = hull_b.support(-direction);
+		SimplexVertex {
+			a: a,
+			b: b,
+			w: a - b,
+			weight: 1.0,
+		}
+	}
+}
+
+pub(super) fn distance(hull_a: &Hull, hull_b: &Hull) -> Distance {
+	let mut simplex = vec![SimplexVertex::new(hull_a, hull_b, hull_b.points[0] - hull_a.points[0])];
+
+	let mut touching = false;
+	for _ in 0..MAX_ITERATIONS {
+		let closest = solve(&mut simplex);
+		if simplex.len() == 3 || closest.length_squared() < EPSILON * EPSILON {
+			// The origin is inside or on the simplex
+			touching = true;
+			break;
+		}
+
+		let direction = -closest;
+		let vertex = SimplexVertex::new(hull_a, hull_b, direction);
+		let duplicate = simplex.iter().any(|existing| (existing.w - vertex.w).length_squared() < EPSILON * EPSILON);
+		// Stop once the new vertex doesn't get any closer to the origin
+		if duplicate || vertex.w.dot(direction) - closest.dot(direction) <= EPSILON * direction.length() {
+			break;
+		}
+		simplex.push(vertex);
+	}
+
+	let mut point_a = Vec2::zero();
+	let mut point_b = Vec2::zero();
+	for vertex in simplex.iter() {
+		point_a += vertex.a * vertex.weight;
+		point_b += vertex.b * vertex.weight;
+	}
+	let distance = if touching { 0.0 } else { point_a.distance(point_b) };
+	Distance {
+		point_a: point_a,
+		point_b: point_b,
+		distance: distance,
+	}
+}
+
+/// Reduce the simplex to the feature closest to the origin, set weights and return the closest point
+///
+/// A triangle is kept only if it contains the origin.
+fn solve(simplex: &mut Vec<SimplexVertex>) -> Vec2 {
+	match simplex.len() {
+		1 => {
+			simplex[0].weight = 1.0;
+			simplex[0].w
+		}
+		2 => solve_segment(simplex),
+		_ => {
+			let (a, b, c) = (simplex[0].w, simplex[1].w, simplex[2].w);
+			let area = (b - a).cross(c - a);
+			let inside = area != 0.0
+				&& (b - a).cross(-a) * area >= 0.0
+				&& (c - b).cross(-b) * area >= 0.0
+				&& (a - c).cross(-c) * area >= 0.0;
+			if inside {
+				// Barycentric coordinates of the origin
+				simplex[0].weight = (b.cross(c)) / area;
+				simplex[1].weight = (c.cross(a)) / area;
+				simplex[2].weight = (a.cross(b)) / area;
+				return Vec2::zero();
+			}
+
+			// Otherwise the closest point lies on one of the edges
+			let mut best: Option<(Vec<SimplexVertex>, Vec2)> = None;
+			for &(first, second) in [(0, 1), (1, 2), (2, 0)].iter() {
+				let mut edge = vec![simplex[first], simplex[second]];
+				let closest = solve_segment(&mut edge);
+				let better = match best {
+					Some((_, ref best_closest)) => closest.length_squared() < best_closest.length_squared(),
+					None => true,
+				};
+				if better {
+					best = Some((edge, closest));
+				}
+			}
+			let (edge, closest) = best.unwrap();
+			*simplex = edge;
+			closest
+		}
+	}
+}
+
+fn solve_segment(simplex: &mut Vec<SimplexVertex>) -> Vec2 {
+	let (start, end) = (simplex[0].w, simplex[1].w);
+	let edge = end - start;
+	let length_squared = edge.length_squared();
+	let t = if length_squared > 0.0 {
+		(-start).dot(edge) / length_squared
+	} else {
+		0.0
+	};
+
+	if t <= 0.0 {
+		simplex.truncate(1);
+		simplex[0].weight = 1.0;
+		start
+	} else if t >= 1.0 {
+		simplex.remove(0);
+		simplex[0].weight = 1.0;
+		end
+	} else {
+		simplex[0].weight = 1.0 - t;
+		simplex[1].weight = t;
+		start + edge * t
+	}
+}
@@ -0,0 +1,57 @@
+// 2D collision detection
+//
+// Shapes are described in object space and placed into the world by the same Transform that places sprites.
+// The broad phase finds pairs with overlapping bounding boxes, the narrow phase computes their contact manifolds.
+
+pub use self::broad::BroadPhase;
+pub use self::narrow::{collide, intersects, Manifold};
+pub use self::ray::{ray_cast, segment_cast, RayHit};
+pub use self::shape::Shape;
+
+pub mod broad;	// Sort and sweep over bounding boxes
+mod gjk;	// Distance between convex hulls
+pub mod narrow;	// Contact manifolds between pairs of shapes
+pub mod ray;	// Ray and segment casts
+pub mod shape;	// Collision shapes and their placement in the world
+
+use ecs::components::Transform;
+use ecs::{Entity, World};
+
+/// Makes an entity with a Transform collide with others
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Collider {
+	pub shape: Shape,
+}
+
+/// Two colliding entities, the manifold normal points from the first into the second
+#[derive(Clone, Debug)]
+pub struct Contact {
+	pub entities: (Entity, Entity),
+	pub manifold: Manifold,
+}
+
+/// Every pair of colliding entities in the world
+pub fn contacts(world: &mut World) -> Vec<Contact> {
+	world.register::<Collider>();
+	world.register::<Transform>();
+	let colliders = world.read::<Collider>();
+	let transforms = world.read::<Transform>();
+
+	let mut broad_phase = BroadPhase::new();
+	for (entity, collider, transform) in colliders.join(&transforms) {
+		broad_phase.insert(entity, collider.shape.bounds(transform));
+	}
+
+	let mut contacts = Vec::new();
+	for (a, b) in broad_phase.pairs() {
+		let (shape_a, transform_a) = (&colliders.get(a).unwrap().shape, transforms.get(a).unwrap());
+		let (shape_b, transform_b) = (&colliders.get(b).unwrap().shape, transforms.get(b).unwrap());
+		if let Some(manifold) = collide(shape_a, transform_a, shape_b, transform_b) {
+			contacts.push(Contact {
+				entities: (a, b),
+				manifold: manifold,
+			});
+		}
+	}
+	contacts
+}
@@ -0,0 +1,464 @@
+// Contact manifolds between pairs of shapes
+//
+// GJK finds the distance between the unrounded hulls. Where the closest features are faces, the separating axis
+// test picks the reference face and the incident edge is clipped against it, giving up to two contact points.
+// Vertex contacts and overlapping circles use the closest points directly.
+
+use super::gjk;
+use super::shape::{edge_normal, Hull, Shape};
+
+use graphics::transform::Transform;
+use graphics::vector::Vec2;
+
+/// Separation along a face normal this close to the hull distance still counts as a face contact
+const FACE_TOLERANCE: f32 = 1e-3;
+
+/// How two overlapping shapes touch
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifold {
+	pub normal: Vec2,      // Unit vector pointing from the first shape into the second
+	pub depth: f32,        // Distance to move the second shape along the normal to separate them
+	pub points: Vec<Vec2>, // One or two contact points in world space
+}
+
+impl Manifold {
+	/// The same contact seen from the second shape
+	pub fn flipped(&self) -> Self {
+		Manifold {
+			normal: -self.normal,
+			depth: self.depth,
+			points: self.points.clone(),
+		}
+	}
+}
+
+/// Contact manifold of two placed shapes, None if they don't touch
+pub fn collide(shape_a: &Shape, transform_a: &Transform, shape_b: &Shape, transform_b: &Transform) -> Option<Manifold> {
+	collide_hulls(&shape_a.hull(transform_a), &shape_b.hull(transform_b))
+}
+
+/// Do two placed shapes touch, cheaper than collide() when the manifold isn't needed
+pub fn intersects(shape_a: &Shape, transform_a: &Transform, shape_b: &Shape, transform_b: &Transform) -> bool {
+	let (hull_a, hull_b) = (shape_a.hull(transform_a), shape_b.hull(transform_b));
+	gjk::distance(&hull_a, &hull_b).distance <= hull_a.radius + hull_b.radius
+}
+
+/// Face of one hull with the smallest overlap along its normal
+struct Axis {
+	normal: Vec2, // Outward normal of the face
+	start: Vec2,
+	end: Vec2,
+	separation: f32, // Negative when the hulls overlap along the normal
+}
+
+pub(super) fn collide_hulls(hull_a: &Hull, hull_b: &Hull) -> Option<Manifold> {
+	let radius = hull_a.radius + hull_b.radius;
+	let distance = gjk::distance(hull_a, hull_b);
+	if distance.distance > radius {
+		return None;
+	}
+
+	let axis_a = best_axis(hull_a, hull_b);
+	let axis_b = best_axis(hull_b, hull_a);
+	let best = match (axis_a, axis_b) {
+		(Some(a), Some(b)) => {
+			// Prefer faces of the first hull so that results don't flicker between almost equal axes
+			if b.separation > a.separation + FACE_TOLERANCE {
+				Some((b, true))
+			} else {
+				Some((a, false))
+			}
+		}
+		(Some(a), None) => Some((a, false)),
+		(None, Some(b)) => Some((b, true)),
+		(None, None) => None,
+	};
+
+	match best {
+		Some((ref axis, flipped)) if distance.distance == 0.0 || axis.separation >= distance.distance - FACE_TOLERANCE => {
+			let (reference, incident) = if flipped { (hull_b, hull_a) } else { (hull_a, hull_b) };
+			let manifold = face_contact(axis, reference, incident);
+			Some(if flipped { manifold.flipped() } else { manifold })
+		}
+		_ => Some(point_contact(&distance, hull_a, hull_b)),
+	}
+}
+
+/// The face of reference along which incident is the least deep, None for hulls without faces
+fn best_axis(reference: &Hull, incident: &Hull) -> Option<Axis> {
+	let mut best: Option<Axis> = None;
+	for (start, end) in reference.edges() {
+		let normal = edge_normal(start, end);
+		if normal == Vec2::zero() {
+			continue;
+		}
+		let separation = (incident.support(-normal) - start).dot(normal);
+		let better = match best {
+			Some(ref best) => separation > best.separation,
+			None => true,
+		};
+		if better {
+			best = Some(Axis {
+				normal: normal,
+				start: start,
+				end: end,
+				separation: separation,
+			});
+		}
+	}
+	best
+}
+
+/// Contact between a face of the reference hull and the incident hull
+fn face_contact(axis: &Axis, reference: &Hull, incident: &Hull) -> Manifold {
+	let radius = reference.radius + incident.radius;
+
+	// The incident edge is the one facing against the reference normal the most
+	let mut incident_edge = (incident.points[0], incident.points[0]);
+	let mut lowest = std::f32::MAX;
+	for (start, end) in incident.edges() {
+		let alignment = edge_normal(start, end).dot(axis.normal);
+		if alignment < lowest {
+			lowest = alignment;
+			incident_edge = (start, end);
+		}
+	}
+
+	// Clip the incident edge to the sides of the reference face
+	let tangent = (axis.end - axis.start).normalize();
+	let mut clipped = vec![incident_edge.0, incident_edge.1];
+	clipped = clip(&clipped, -tangent, -tangent.dot(axis.start));
+	clipped = clip(&clipped, tangent, tangent.dot(axis.end));
+
+	let mut points = Vec::new();
+	let mut depth = 0.0f32;
+	for point in clipped.iter() {
+		let separation = (*point - axis.start).dot(axis.normal);
+		if separation <= radius {
+			depth = depth.max(radius - separation);
+			// Halfway between the surfaces
+			let surface = *point - axis.normal * incident.radius;
+			points.push(surface - axis.normal * ((separation - incident.radius - reference.radius) / 2.0));
+		}
+	}
+	points.dedup_by(|a, b| (*a - *b).length_squared() < 1e-12);
+
+	// Clipping can remove everything when the incident hull is a single point
+	if points.is_empty() {
+		let point = incident.support(-axis.normal);
+		let separation = (point - axis.start).dot(axis.normal);
+		depth = radius - separation;
+		points.push(point - axis.normal * ((separation + incident.radius - reference.radius) / 2.0));
+	}
+
+	Manifold {
+		normal: axis.normal,
+		depth: depth,
+		points: points,
+	}
+}
+
+/// Keep the part of a segment where dot(normal, point) <= offset
+fn clip(points: &[Vec2], normal: Vec2, offset: f32) -> Vec<Vec2> {
+	if points.len() < 2 {
+		return points.to_vec();
+	}
+	let distance_start = points[0].dot(normal) - offset;
+	let distance_end = points[1].dot(normal) - offset;
+
+	let mut clipped = Vec::new();
+	if distance_start <= 0.0 {
+		clipped.push(points[0]);
+	}
+	if distance_end <= 0.0 {
+		clipped.push(points[1]);
+	}
+	if distance_start * distance_end < 0.0 {
+		let t = distance_start / (distance_start - distance_end);
+		clipped.push(points[0] + (points[1] - points[0]) * t);
+	}
+	clipped
+}
+
+/// Contact between the closest points of two rounded hulls that are apart
+fn point_contact(distance: &gjk::Distance, hull_a: &Hull, hull_b: &Hull) -> Manifold {
+	let offset = distance.point_b - distance.point_a;
+	let normal = if distance.distance > 0.0 {
+		offset / distance.distance
+	} else {
+		// Concentric circles, any direction separates them
+		Vec2::new(0.0, 1.0)
+	};
+	let surface_a = distance.point_a + normal * hull_a.radius;
+	let surface_b = distance.point_b - normal * hull_b.radius;
+	Manifold {
+		normal: normal,
+		depth: hull_a.radius + hull_b.radius - distance.distance,
+		points: vec![(surface_a + surface_b) / 2.0],
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use graphics::angle::Angle;
+
+	const EPSILON: f32 = 1e-3;
+
+	fn close(a: f32, b: f32) -> bool {
+		(a - b).abs() < EPSILON
+	}
+
+	fn close_vec(a: Vec2, b: Vec2) -> bool {
+		(a - b).length() < EPSILON
+	}
+
+	fn at(x: f32, y: f32) -> Transform {
+		Transform::new(Vec2::new(x, y), Angle::zero(), Vec2::splat(1.0))
+	}
+
+	fn rotated(x: f32, y: f32, degrees: f32) -> Transform {
+		Transform::new(Vec2::new(x, y), Angle::from_degrees(degrees), Vec2::splat(1.0))
+	}
+
+	fn circle(radius: f32) -> Shape {
+		Shape::Circle { radius: radius }
+	}
+
+	fn aabb(half_width: f32, half_height: f32) -> Shape {
+		Shape::Aabb {
+			half_extents: Vec2::new(half_width, half_height),
+		}
+	}
+
+	fn oriented_box(half_width: f32, half_height: f32) -> Shape {
+		Shape::OrientedBox {
+			half_extents: Vec2::new(half_width, half_height),
+		}
+	}
+
+	fn capsule(half_length: f32, radius: f32) -> Shape {
+		Shape::Capsule {
+			half_length: half_length,
+			radius: radius,
+		}
+	}
+
+	fn triangle() -> Shape {
+		Shape::polygon(vec![Vec2::new(-1.0, -1.0), Vec2::new(1.0, -1.0), Vec2::new(0.0, 1.0)]).unwrap()
+	}
+
+	/// Quadrilateral with vertical sides, the left one is shorter
+	fn trapezoid() -> Shape {
+		Shape::polygon(vec![
+			Vec2::new(-1.0, -1.0),
+			Vec2::new(1.0, -1.0),
+			Vec2::new(1.0, 1.0),
+			Vec2::new(-1.0, 0.5),
+		]).unwrap()
+	}
+
+	/// Collide both ways round, checking that both find the same depth and agree with intersects()
+	///
+	/// Normals of deep overlaps may differ between the two orders, so they are only compared by the tests.
+	fn check(shape_a: &Shape, transform_a: &Transform, shape_b: &Shape, transform_b: &Transform) -> Option<Manifold> {
+		let manifold = collide(shape_a, transform_a, shape_b, transform_b);
+		let reversed = collide(shape_b, transform_b, shape_a, transform_a);
+		assert_eq!(manifold.is_some(), intersects(shape_a, transform_a, shape_b, transform_b));
+		match (&manifold, &reversed) {
+			(Some(manifold), Some(reversed)) => {
+				assert!(close(manifold.depth, reversed.depth), "{:?} {:?}", manifold, reversed);
+			}
+			(None, None) => (),
+			_ => panic!("asymmetric result {:?} {:?}", manifold, reversed),
+		}
+		manifold
+	}
+
+	/// A shape reaching exactly one unit to the left and right of its origin, with the vertical extent of its
+	/// left and right sides, None where the side is rounded
+	struct Case {
+		name: &'static str,
+		shape: Shape,
+		left: Option<(f32, f32)>,
+		right: Option<(f32, f32)>,
+	}
+
+	fn cases() -> Vec<Case> {
+		vec![
+			Case { name: "circle", shape: circle(1.0), left: None, right: None },
+			Case { name: "aabb", shape: aabb(1.0, 1.0), left: Some((-1.0, 1.0)), right: Some((-1.0, 1.0)) },
+			Case { name: "oriented box", shape: oriented_box(1.0, 0.75), left: Some((-0.75, 0.75)), right: Some((-0.75, 0.75)) },
+			Case { name: "polygon", shape: trapezoid(), left: Some((-1.0, 0.5)), right: Some((-1.0, 1.0)) },
+			Case { name: "capsule", shape: capsule(0.5, 0.5), left: None, right: None },
+		]
+	}
+
+	/// Every pair placed side by side along x: apart, touching and overlapping by a quarter
+	#[test]
+	fn every_shape_pair() {
+		for a in cases().iter() {
+			for b in cases().iter() {
+				let pair = format!("{} and {}", a.name, b.name);
+				assert!(check(&a.shape, &at(0.0, 0.0), &b.shape, &at(2.5, 0.0)).is_none(), "{} apart", pair);
+
+				for &(offset, depth) in [(2.0, 0.0), (1.75, 0.25)].iter() {
+					let manifold = match check(&a.shape, &at(0.0, 0.0), &b.shape, &at(offset, 0.0)) {
+						Some(manifold) => manifold,
+						None => panic!("{} at {} don't touch", pair, offset),
+					};
+					let reversed = collide(&b.shape, &at(offset, 0.0), &a.shape, &at(0.0, 0.0)).unwrap();
+					assert!(close_vec(manifold.normal, Vec2::new(1.0, 0.0)), "{}: {:?}", pair, manifold);
+					assert!(close_vec(reversed.normal, Vec2::new(-1.0, 0.0)), "{}: {:?}", pair, reversed);
+					assert!(close(manifold.depth, depth), "{}: {:?}", pair, manifold);
+
+					// Contact points lie halfway between the surfaces, at the ends of overlapping flat sides
+					let surface = (1.0 + offset - 1.0) / 2.0;
+					let expected = match (a.right, b.left) {
+						(Some(right), Some(left)) => vec![right.0.max(left.0), right.1.min(left.1)],
+						_ => vec![0.0],
+					};
+					let mut heights: Vec<f32> = manifold.points.iter().map(|point| point.y).collect();
+					heights.sort_by(|a, b| a.partial_cmp(b).unwrap());
+					assert_eq!(heights.len(), expected.len(), "{}: {:?}", pair, manifold);
+					for (point, height) in manifold.points.iter().zip(heights.iter()) {
+						assert!(close(point.x, surface), "{}: {:?}", pair, manifold);
+						assert!(expected.iter().any(|expected| close(*height, *expected)), "{}: {:?}", pair, manifold);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn circle_against_box_corner() {
+		let manifold = check(&circle(0.5), &at(1.3, 1.3), &aabb(1.0, 1.0), &at(0.0, 0.0)).unwrap();
+		let distance = (0.3f32 * 0.3 * 2.0).sqrt();
+		assert!(close(manifold.depth, 0.5 - distance), "{:?}", manifold);
+		assert!(close_vec(manifold.normal, Vec2::new(-1.0, -1.0).normalize()), "{:?}", manifold);
+		assert!(check(&circle(0.5), &at(1.4, 1.4), &aabb(1.0, 1.0), &at(0.0, 0.0)).is_none());
+	}
+
+	#[test]
+	fn circle_inside_box() {
+		let manifold = check(&aabb(1.0, 1.0), &at(0.0, 0.0), &circle(0.5), &at(0.8, 0.0)).unwrap();
+		assert!(close_vec(manifold.normal, Vec2::new(1.0, 0.0)), "{:?}", manifold);
+		assert!(close(manifold.depth, 0.7), "{:?}", manifold);
+	}
+
+	#[test]
+	fn concentric_circles() {
+		let manifold = check(&circle(1.0), &at(0.0, 0.0), &circle(0.5), &at(0.0, 0.0)).unwrap();
+		assert!(close(manifold.depth, 1.5));
+		assert!(close(manifold.normal.length(), 1.0));
+	}
+
+	#[test]
+	fn aabb_ignores_rotation() {
+		let manifold = check(&aabb(1.0, 1.0), &rotated(0.0, 0.0, 45.0), &circle(0.5), &at(0.0, 1.25)).unwrap();
+		assert!(close_vec(manifold.normal, Vec2::new(0.0, 1.0)), "{:?}", manifold);
+		assert!(close(manifold.depth, 0.25), "{:?}", manifold);
+		assert!(close_vec(manifold.points[0], Vec2::new(0.0, 0.875)), "{:?}", manifold);
+	}
+
+	#[test]
+	fn oriented_box_corner_on_face() {
+		let manifold = check(&oriented_box(1.0, 1.0), &at(0.0, 0.0), &oriented_box(1.0, 1.0), &rotated(0.0, 2.2, 45.0)).unwrap();
+		assert!(close_vec(manifold.normal, Vec2::new(0.0, 1.0)), "{:?}", manifold);
+		assert!(close(manifold.depth, 2.0f32.sqrt() + 1.0 - 2.2), "{:?}", manifold);
+		assert_eq!(manifold.points.len(), 1);
+		assert!(close(manifold.points[0].x, 0.0), "{:?}", manifold);
+		assert!(check(&oriented_box(1.0, 1.0), &at(0.0, 0.0), &oriented_box(1.0, 1.0), &rotated(0.0, 2.5, 45.0)).is_none());
+	}
+
+	#[test]
+	fn rotated_boxes_with_overlapping_bounds() {
+		let (long, small) = (oriented_box(2.0, 0.1), oriented_box(0.2, 0.2));
+		assert!(check(&long, &rotated(0.0, 0.0, 45.0), &small, &at(1.0, -1.0)).is_none());
+		assert!(check(&long, &rotated(0.0, 0.0, 45.0), &small, &at(0.5, 0.3)).unwrap().depth > 0.0);
+	}
+
+	#[test]
+	fn triangle_pairs() {
+		let manifold = check(&triangle(), &at(0.0, 0.0), &aabb(1.0, 0.5), &at(0.0, 1.4)).unwrap();
+		assert!(close_vec(manifold.normal, Vec2::new(0.0, 1.0)) && close(manifold.depth, 0.1), "{:?}", manifold);
+		assert!(check(&triangle(), &at(0.0, 0.0), &aabb(1.0, 0.5), &at(0.0, 1.6)).is_none());
+
+		let manifold = check(&triangle(), &at(0.0, 0.0), &triangle(), &rotated(0.0, 1.9, 180.0)).unwrap();
+		assert!(close(manifold.depth, 0.1 / 5f32.sqrt()), "{:?}", manifold);
+
+		let manifold = check(&triangle(), &at(0.0, 0.0), &capsule(1.0, 0.25), &at(0.0, -1.2)).unwrap();
+		assert!(close_vec(manifold.normal, Vec2::new(0.0, -1.0)) && close(manifold.depth, 0.05), "{:?}", manifold);
+		assert_eq!(manifold.points.len(), 2);
+	}
+
+	#[test]
+	fn polygon_winding_and_mirroring() {
+		let clockwise = Shape::polygon(vec![Vec2::new(0.0, 1.0), Vec2::new(1.0, -1.0), Vec2::new(-1.0, -1.0)]).unwrap();
+		let manifold = check(&clockwise, &at(0.0, 0.0), &circle(0.5), &at(0.0, -1.3)).unwrap();
+		assert!(close_vec(manifold.normal, Vec2::new(0.0, -1.0)) && close(manifold.depth, 0.2), "{:?}", manifold);
+
+		let mirrored = Transform::new(Vec2::zero(), Angle::zero(), Vec2::new(-1.0, 1.0));
+		let manifold = check(&triangle(), &mirrored, &circle(0.5), &at(0.0, -1.3)).unwrap();
+		assert!(close_vec(manifold.normal, Vec2::new(0.0, -1.0)) && close(manifold.depth, 0.2), "{:?}", manifold);
+	}
+
+	#[test]
+	fn capsule_pairs() {
+		let manifold = check(&capsule(1.0, 0.5), &at(0.0, 0.0), &circle(0.5), &at(0.5, 0.8)).unwrap();
+		assert!(close_vec(manifold.normal, Vec2::new(0.0, 1.0)) && close(manifold.depth, 0.2), "{:?}", manifold);
+
+		// Parallel capsules touch along a line
+		let manifold = check(&capsule(1.0, 0.5), &at(0.0, 0.0), &capsule(1.0, 0.5), &at(0.5, 0.9)).unwrap();
+		assert!(close_vec(manifold.normal, Vec2::new(0.0, 1.0)) && close(manifold.depth, 0.1), "{:?}", manifold);
+		assert_eq!(manifold.points.len(), 2);
+		assert!(manifold.points.iter().all(|point| close(point.y, 0.45)), "{:?}", manifold);
+
+		let manifold = check(&capsule(1.0, 0.1), &at(0.0, 0.0), &capsule(1.0, 0.1), &rotated(0.0, 0.0, 90.0)).unwrap();
+		assert!(manifold.depth > 0.0, "{:?}", manifold);
+
+		// Standing on a box with its rounded end
+		let manifold = check(&aabb(2.0, 0.5), &at(0.0, 0.0), &capsule(1.0, 0.25), &rotated(0.3, 1.7, 90.0)).unwrap();
+		assert!(close_vec(manifold.normal, Vec2::new(0.0, 1.0)) && close(manifold.depth, 0.05), "{:?}", manifold);
+		assert_eq!(manifold.points.len(), 1);
+		assert!(close(manifold.points[0].x, 0.3), "{:?}", manifold);
+
+		// Lying on a box with its side
+		let manifold = check(&oriented_box(2.0, 0.5), &at(0.0, 0.0), &capsule(1.0, 0.25), &at(0.0, 0.7)).unwrap();
+		assert!(close(manifold.depth, 0.05), "{:?}", manifold);
+		assert_eq!(manifold.points.len(), 2);
+	}
+
+	#[test]
+	fn scaled_capsule() {
+		let scaled = Transform::new(Vec2::zero(), Angle::zero(), Vec2::splat(2.0));
+		let manifold = check(&capsule(1.0, 0.5), &scaled, &circle(0.5), &at(0.0, 1.4)).unwrap();
+		assert!(close_vec(manifold.normal, Vec2::new(0.0, 1.0)) && close(manifold.depth, 0.1), "{:?}", manifold);
+	}
+
+	/// Moving the second shape out along the normal by the depth separates random pairs
+	#[test]
+	fn depth_separates_shapes() {
+		let shapes = [circle(0.7), aabb(0.5, 1.0), oriented_box(1.0, 0.3), triangle(), capsule(0.8, 0.3)];
+		let mut seed: u32 = 12345;
+		let mut random = || {
+			seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+			(seed >> 8) as f32 / (1u32 << 24) as f32
+		};
+		for _ in 0..2000 {
+			let shape_a = &shapes[(random() * shapes.len() as f32) as usize % shapes.len()];
+			let shape_b = &shapes[(random() * shapes.len() as f32) as usize % shapes.len()];
+			let transform_a = rotated(random() * 4.0 - 2.0, random() * 4.0 - 2.0, random() * 360.0);
+			let mut transform_b = rotated(random() * 4.0 - 2.0, random() * 4.0 - 2.0, random() * 360.0);
+
+			if let Some(manifold) = check(shape_a, &transform_a, shape_b, &transform_b) {
+				assert!(manifold.depth >= -EPSILON && !manifold.points.is_empty(), "{:?}", manifold);
+				assert!(close(manifold.normal.length(), 1.0), "{:?}", manifold);
+				transform_b.translate(manifold.normal * (manifold.depth + 0.01));
+				if let Some(moved) = collide(shape_a, &transform_a, shape_b, &transform_b) {
+					assert!(moved.depth < 0.02, "{:?} {:?}", manifold, moved);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,271 @@
+// Ray and segment casts against shapes
+
+use super::shape::{edge_normal, Hull, Shape};
+
+use graphics::transform::Transform;
+use graphics::vector::Vec2;
+
+/// First point where a ray enters a shape
+///
+/// Rays starting inside a shape hit it right away, the normal then points against the ray.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+	pub point: Vec2,
+	pub normal: Vec2,  // Surface normal at the point, pointing out of the shape
+	pub distance: f32, // Along the ray from its origin
+}
+
+/// Cast a ray from origin along direction, which doesn't have to be normalized, up to max_distance
+pub fn ray_cast(
+	shape: &Shape,
+	transform: &Transform,
+	origin: Vec2,
+	direction: Vec2,
+	max_distance: f32,
+) -> Option<RayHit> {
+	let direction = direction.normalize();
+	if direction == Vec2::zero() {
+		return None;
+	}
+	let hull = shape.hull(transform);
+	let hit = match hull.points.len() {
+		1 => cast_circle(hull.points[0], hull.radius, origin, direction),
+		2 => cast_capsule(&hull, origin, direction),
+		_ => cast_polygon(&hull, origin, direction),
+	};
+	hit.filter(|hit| hit.distance <= max_distance)
+}
+
+/// Cast along the segment from start to end, the hit distance is measured from start
+pub fn segment_cast(shape: &Shape, transform: &Transform, start: Vec2, end: Vec2) -> Option<RayHit> {
+	ray_cast(shape, transform, start, end - start, start.distance(end))
+}
+
+fn hit(origin: Vec2, direction: Vec2, distance: f32, normal: Vec2) -> RayHit {
+	RayHit {
+		point: origin + direction * distance,
+		normal: normal,
+		distance: distance,
+	}
+}
+
+fn cast_circle(center: Vec2, radius: f32, origin: Vec2, direction: Vec2) -> Option<RayHit> {
+	let offset = origin - center;
+	let c = offset.length_squared() - radius * radius;
+	if c <= 0.0 {
+		return Some(hit(origin, direction, 0.0, -direction));
+	}
+
+	// Solve |offset + direction * t| = radius, direction is normalized
+	let b = offset.dot(direction);
+	let discriminant = b * b - c;
+	if b > 0.0 || discriminant < 0.0 {
+		return None;
+	}
+	let distance = -b - discriminant.sqrt();
+	Some(hit(origin, direction, distance, (offset + direction * distance).normalize()))
+}
+
+fn cast_capsule(hull: &Hull, origin: Vec2, direction: Vec2) -> Option<RayHit> {
+	let (start, end) = (hull.points[0], hull.points[1]);
+	let axis = end - start;
+	let length_squared = axis.length_squared();
+	let t = if length_squared > 0.0 {
+		((origin - start).dot(axis) / length_squared).max(0.0).min(1.0)
+	} else {
+		0.0
+	};
+	if origin.distance(start + axis * t) <= hull.radius {
+		return Some(hit(origin, direction, 0.0, -direction));
+	}
+
+	// Closest of the two rounded ends and the two flat sides
+	let mut best = closer(
+		cast_circle(start, hull.radius, origin, direction),
+		cast_circle(end, hull.radius, origin, direction),
+	);
+	if length_squared > 0.0 {
+		for &(side_start, side_end) in [(start, end), (end, start)].iter() {
+			let normal = edge_normal(side_start, side_end);
+			let offset = normal * hull.radius;
+			best = closer(best, cast_edge(side_start + offset, side_end + offset, normal, origin, direction));
+		}
+	}
+	best
+}
+
+fn cast_polygon(hull: &Hull, origin: Vec2, direction: Vec2) -> Option<RayHit> {
+	// Clip the ray against the half-plane of every edge
+	let mut enter = 0.0f32;
+	let mut exit = std::f32::MAX;
+	let mut enter_normal = None;
+	for (start, end) in hull.edges() {
+		let normal = edge_normal(start, end);
+		let distance = (start - origin).dot(normal); // Positive while the origin is behind the edge
+		let speed = direction.dot(normal);
+		if speed == 0.0 {
+			if distance < 0.0 {
+				return None;
+			}
+			continue;
+		}
+
+		let t = distance / speed;
+		if speed < 0.0 {
+			if t > enter {
+				enter = t;
+				enter_normal = Some(normal);
+			}
+		} else if t < exit {
+			exit = t;
+		}
+		if exit < enter {
+			return None;
+		}
+	}
+
+	Some(hit(origin, direction, enter, enter_normal.unwrap_or(-direction)))
+}
+
+/// Ray against the outside of a single edge
+fn cast_edge(start: Vec2, end: Vec2, normal: Vec2, origin: Vec2, direction: Vec2) -> Option<RayHit> {
+	let speed = direction.dot(normal);
+	if speed >= 0.0 {
+		return None;
+	}
+	let distance = (start - origin).dot(normal) / speed;
+	if distance < 0.0 {
+		return None;
+	}
+	let point = origin + direction * distance;
+	let along = (point - start).dot(end - start);
+	if along < 0.0 || along > (end - start).length_squared() {
+		return None;
+	}
+	Some(hit(origin, direction, distance, normal))
+}
+
+fn closer(a: Option<RayHit>, b: Option<RayHit>) -> Option<RayHit> {
+	match (a, b) {
+		(Some(a), Some(b)) => Some(if b.distance < a.distance { b } else { a }),
+		(a, None) => a,
+		(None, b) => b,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use graphics::angle::Angle;
+
+	const EPSILON: f32 = 1e-3;
+
+	fn close(a: f32, b: f32) -> bool {
+		(a - b).abs() < EPSILON
+	}
+
+	fn close_vec(a: Vec2, b: Vec2) -> bool {
+		(a - b).length() < EPSILON
+	}
+
+	fn at(x: f32, y: f32) -> Transform {
+		Transform::new(Vec2::new(x, y), Angle::zero(), Vec2::splat(1.0))
+	}
+
+	fn box_shape(half_width: f32, half_height: f32) -> Shape {
+		Shape::Aabb {
+			half_extents: Vec2::new(half_width, half_height),
+		}
+	}
+
+	fn capsule() -> Shape {
+		Shape::Capsule {
+			half_length: 1.0,
+			radius: 0.5,
+		}
+	}
+
+	#[test]
+	fn circle_from_outside() {
+		let circle = Shape::Circle { radius: 1.0 };
+		let hit = ray_cast(&circle, &at(5.0, 0.0), Vec2::zero(), Vec2::new(2.0, 0.0), 10.0).unwrap();
+		assert!(close(hit.distance, 4.0), "{:?}", hit);
+		assert!(close_vec(hit.normal, Vec2::new(-1.0, 0.0)), "{:?}", hit);
+		assert!(close_vec(hit.point, Vec2::new(4.0, 0.0)), "{:?}", hit);
+	}
+
+	#[test]
+	fn misses() {
+		let circle = Shape::Circle { radius: 1.0 };
+		assert_eq!(ray_cast(&circle, &at(5.0, 0.0), Vec2::zero(), Vec2::new(-1.0, 0.0), 10.0), None);
+		assert_eq!(ray_cast(&circle, &at(5.0, 2.0), Vec2::zero(), Vec2::new(1.0, 0.0), 10.0), None);
+		assert_eq!(ray_cast(&box_shape(1.0, 1.0), &at(5.0, 1.5), Vec2::zero(), Vec2::new(1.0, 0.0), 10.0), None);
+		assert_eq!(ray_cast(&circle, &at(5.0, 0.0), Vec2::zero(), Vec2::zero(), 10.0), None);
+	}
+
+	#[test]
+	fn max_distance() {
+		let circle = Shape::Circle { radius: 1.0 };
+		assert_eq!(ray_cast(&circle, &at(5.0, 0.0), Vec2::zero(), Vec2::new(1.0, 0.0), 3.0), None);
+		assert!(ray_cast(&circle, &at(5.0, 0.0), Vec2::zero(), Vec2::new(1.0, 0.0), 4.0).is_some());
+	}
+
+	#[test]
+	fn origin_inside() {
+		let direction = Vec2::new(1.0, 0.0);
+		for &(ref shape, origin) in [
+			(Shape::Circle { radius: 1.0 }, Vec2::new(0.5, 0.0)),
+			(box_shape(1.0, 1.0), Vec2::new(0.5, 0.5)),
+			(capsule(), Vec2::new(0.0, 0.2)),
+		].iter()
+		{
+			let hit = ray_cast(shape, &at(0.0, 0.0), origin, direction, 10.0).unwrap();
+			assert_eq!(hit.distance, 0.0, "{:?}", shape);
+			assert!(close_vec(hit.point, origin), "{:?}", shape);
+			assert!(close_vec(hit.normal, -direction), "{:?}", shape);
+		}
+	}
+
+	#[test]
+	fn boxes() {
+		let hit = ray_cast(&box_shape(1.0, 1.0), &at(5.0, 0.5), Vec2::zero(), Vec2::new(1.0, 0.0), 10.0).unwrap();
+		assert!(close(hit.distance, 4.0), "{:?}", hit);
+		assert!(close_vec(hit.normal, Vec2::new(-1.0, 0.0)), "{:?}", hit);
+
+		let rotated = Transform::new(Vec2::new(5.0, 0.0), Angle::from_degrees(45.0), Vec2::splat(1.0));
+		let shape = Shape::OrientedBox {
+			half_extents: Vec2::splat(1.0),
+		};
+		let hit = ray_cast(&shape, &rotated, Vec2::zero(), Vec2::new(1.0, 0.0), 10.0).unwrap();
+		assert!(close(hit.distance, 5.0 - 2.0f32.sqrt()), "{:?}", hit);
+	}
+
+	#[test]
+	fn polygon() {
+		let triangle = Shape::polygon(vec![Vec2::new(-1.0, -1.0), Vec2::new(1.0, -1.0), Vec2::new(0.0, 1.0)]).unwrap();
+		let hit = ray_cast(&triangle, &at(0.0, 5.0), Vec2::zero(), Vec2::new(0.0, 1.0), 10.0).unwrap();
+		assert!(close(hit.distance, 4.0), "{:?}", hit);
+		assert!(close_vec(hit.normal, Vec2::new(0.0, -1.0)), "{:?}", hit);
+		assert_eq!(ray_cast(&triangle, &at(0.0, 5.0), Vec2::new(2.0, 0.0), Vec2::new(0.0, 1.0), 10.0), None);
+	}
+
+	#[test]
+	fn capsule_sides_and_ends() {
+		let transform = at(0.0, 5.0);
+		let side = ray_cast(&capsule(), &transform, Vec2::new(0.5, 0.0), Vec2::new(0.0, 1.0), 10.0).unwrap();
+		assert!(close(side.distance, 4.5), "{:?}", side);
+		assert!(close_vec(side.normal, Vec2::new(0.0, -1.0)), "{:?}", side);
+
+		let end = ray_cast(&capsule(), &transform, Vec2::new(-5.0, 5.0), Vec2::new(1.0, 0.0), 10.0).unwrap();
+		assert!(close(end.distance, 3.5), "{:?}", end);
+		assert!(close_vec(end.normal, Vec2::new(-1.0, 0.0)), "{:?}", end);
+	}
+
+	#[test]
+	fn segments() {
+		let shape = box_shape(1.0, 1.0);
+		let hit = segment_cast(&shape, &at(5.0, 0.0), Vec2::zero(), Vec2::new(10.0, 0.0)).unwrap();
+		assert!(close(hit.distance, 4.0), "{:?}", hit);
+		assert_eq!(segment_cast(&shape, &at(5.0, 0.0), Vec2::zero(), Vec2::new(3.0, 0.0)), None);
+	}
+}
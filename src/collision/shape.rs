@@ -0,0 +1,288 @@
+// Collision shapes and their placement in the world
+
+use graphics::math::{Rect, PI};
+use graphics::transform::Transform;
+use graphics::vector::Vec2;
+
+use serde::de::{Deserialize, Deserializer, Error};
+
+/// Fewer vertices don't enclose an area
+pub const MIN_POLYGON_VERTICES: usize = 3;
+
+/// Relative to the squared size of a polygon, smaller areas and turns count as none
+const POLYGON_TOLERANCE: f32 = 1e-6;
+
+/// Shape of an object in its own space, placed into the world by a Transform
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Shape {
+	Circle { radius: f32 },
+	Aabb { half_extents: Vec2 },               // Box that stays axis-aligned, ignores rotation
+	OrientedBox { half_extents: Vec2 },        // Box that rotates with the object
+	Polygon { vertices: PolygonVertices },     // Convex, built through Shape::polygon()
+	Capsule { half_length: f32, radius: f32 }, // Segment along the x axis with rounded ends
+}
+
+impl Shape {
+	/// Box covering the unit quad sprites are drawn with
+	pub fn unit_box() -> Self {
+		Shape::OrientedBox {
+			half_extents: Vec2::splat(0.5),
+		}
+	}
+
+	/// Convex polygon from vertices in either winding order
+	pub fn polygon(vertices: Vec<Vec2>) -> Result<Self, PolygonError> {
+		Ok(Shape::Polygon {
+			vertices: PolygonVertices::new(vertices)?,
+		})
+	}
+
+	/// Axis-aligned box around the shape placed by a transform
+	pub fn bounds(&self, transform: &Transform) -> Rect {
+		self.hull(transform).bounds()
+	}
+
+	/// The shape placed by a transform
+	///
+	/// Non-uniform scale turns circles and capsules into ellipses, which are approximated by scaling
+	/// the radius with the larger scale factor.
+	pub(super) fn hull(&self, transform: &Transform) -> Hull {
+		let radius_scale = transform.scale.x.abs().max(transform.scale.y.abs());
+		match self {
+			Shape::Circle { radius } => Hull {
+				points: vec![transform.translation],
+				radius: radius * radius_scale,
+			},
+			Shape::Aabb { half_extents } => {
+				let half_extents = *half_extents * Vec2::new(transform.scale.x.abs(), transform.scale.y.abs());
+				Hull::polygon(
+					[[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]]
+						.iter()
+						.map(|&corner| transform.translation + half_extents * Vec2::from(corner))
+						.collect(),
+				)
+			}
+			Shape::OrientedBox { half_extents } => Hull::polygon(
+				[[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]]
+					.iter()
+					.map(|&corner| transform.transform_point(*half_extents * Vec2::from(corner)))
+					.collect(),
+			),
+			Shape::Polygon { vertices } => {
+				Hull::polygon(vertices.0.iter().map(|&vertex| transform.transform_point(vertex)).collect())
+			}
+			Shape::Capsule { half_length, radius } => Hull {
+				points: vec![
+					transform.transform_point(Vec2::new(-half_length, 0.0)),
+					transform.transform_point(Vec2::new(*half_length, 0.0)),
+				],
+				radius: radius * radius_scale,
+			},
+		}
+	}
+}
+
+/// Why vertices don't describe a convex polygon
+#[derive(Clone, Debug, PartialEq)]
+pub enum PolygonError {
+	TooFewVertices(usize), // Less than MIN_POLYGON_VERTICES
+	NoArea,                // Vertices are collinear or not finite
+	NotConvex,             // Edges turn both ways or wind around more than once
+}
+
+impl std::fmt::Display for PolygonError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			PolygonError::TooFewVertices(count) => {
+				write!(f, "polygon has {} vertices, at least {} are needed", count, MIN_POLYGON_VERTICES)
+			}
+			PolygonError::NoArea => write!(f, "polygon doesn't enclose an area"),
+			PolygonError::NotConvex => write!(f, "polygon is not convex"),
+		}
+	}
+}
+
+/// Vertices of a convex polygon, only created through Shape::polygon() or by deserializing, which both check them
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PolygonVertices(Vec<Vec2>);
+
+impl PolygonVertices {
+	fn new(vertices: Vec<Vec2>) -> Result<Self, PolygonError> {
+		if vertices.len() < MIN_POLYGON_VERTICES {
+			return Err(PolygonError::TooFewVertices(vertices.len()));
+		}
+
+		let mut area = 0.0;
+		let mut turning = 0.0;
+		let (mut left_turns, mut right_turns) = (false, false);
+		let bounds = Rect::from_bounds(&vertices).size();
+		let tolerance = POLYGON_TOLERANCE * bounds.x.max(bounds.y).powi(2);
+		for (index, &vertex) in vertices.iter().enumerate() {
+			let next = vertices[(index + 1) % vertices.len()];
+			let edge = next - vertex;
+			let next_edge = vertices[(index + 2) % vertices.len()] - next;
+			area += vertex.cross(next);
+
+			let cross = edge.cross(next_edge);
+			left_turns = left_turns || cross > tolerance;
+			right_turns = right_turns || cross < -tolerance;
+			turning += cross.atan2(edge.dot(next_edge));
+		}
+
+		if !area.is_finite() || area.abs() <= tolerance {
+			return Err(PolygonError::NoArea);
+		}
+		// A convex polygon turns all the way around exactly once, always in the same direction
+		if (left_turns && right_turns) || turning.abs() > 3.0 * PI {
+			return Err(PolygonError::NotConvex);
+		}
+		Ok(PolygonVertices(vertices))
+	}
+
+	pub fn as_slice(&self) -> &[Vec2] {
+		&self.0
+	}
+}
+
+impl<'de> Deserialize<'de> for PolygonVertices {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let vertices = Vec::<Vec2>::deserialize(deserializer)?;
+		PolygonVertices::new(vertices).map_err(D::Error::custom)
+	}
+}
+
+/// A shape in world space: the convex hull of a few points, rounded by a radius
+///
+/// A single point is a circle, two points are a capsule and more points are a polygon in counter-clockwise order.
+/// Polygons are never rounded.
+#[derive(Clone, Debug)]
+pub(super) struct Hull {
+	pub points: Vec<Vec2>,
+	pub radius: f32,
+}
+
+impl Hull {
+	/// Polygon from vertices of either winding, transforms that mirror the shape reverse the order
+	fn polygon(mut points: Vec<Vec2>) -> Self {
+		let mut area = 0.0;
+		for (index, point) in points.iter().enumerate() {
+			area += point.cross(points[(index + 1) % points.len()]);
+		}
+		if area < 0.0 {
+			points.reverse();
+		}
+		Hull {
+			points: points,
+			radius: 0.0,
+		}
+	}
+
+	/// Point of the unrounded hull furthest along a direction
+	pub fn support(&self, direction: Vec2) -> Vec2 {
+		let mut best = self.points[0];
+		for point in self.points.iter().skip(1) {
+			if point.dot(direction) > best.dot(direction) {
+				best = *point;
+			}
+		}
+		best
+	}
+
+	/// Edges as start and end points, a segment has both of its sides as edges and a point has none
+	pub fn edges<'a>(&'a self) -> impl Iterator<Item = (Vec2, Vec2)> + 'a {
+		let count = if self.points.len() > 1 { self.points.len() } else { 0 };
+		(0..count).map(move |index| (self.points[index], self.points[(index + 1) % self.points.len()]))
+	}
+
+	pub fn bounds(&self) -> Rect {
+		let mut min = self.points[0];
+		let mut max = self.points[0];
+		for point in self.points.iter() {
+			min = min.min(*point);
+			max = max.max(*point);
+		}
+		Rect::new(min, max).expand(self.radius)
+	}
+}
+
+/// Outward normal of a counter-clockwise edge
+pub(super) fn edge_normal(start: Vec2, end: Vec2) -> Vec2 {
+	let edge = end - start;
+	Vec2::new(edge.y, -edge.x).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use graphics::angle::Angle;
+	use serde_yaml;
+
+	fn points(coordinates: &[[f32; 2]]) -> Vec<Vec2> {
+		coordinates.iter().map(|&point| Vec2::from(point)).collect()
+	}
+
+	#[test]
+	fn polygon_validation() {
+		assert_eq!(Shape::polygon(vec![]), Err(PolygonError::TooFewVertices(0)));
+		assert_eq!(Shape::polygon(points(&[[0.0, 0.0], [1.0, 0.0]])), Err(PolygonError::TooFewVertices(2)));
+		assert_eq!(Shape::polygon(points(&[[0.0, 0.0], [1.0, 1.0], [2.0, 2.0]])), Err(PolygonError::NoArea));
+		assert_eq!(Shape::polygon(points(&[[0.0, 0.0], [0.0, 0.0], [0.0, 0.0]])), Err(PolygonError::NoArea));
+		assert_eq!(
+			Shape::polygon(points(&[[0.0, 0.0], [1.0, 0.0], [std::f32::INFINITY, 1.0]])),
+			Err(PolygonError::NoArea)
+		);
+
+		// Dent in the top edge
+		let dented = points(&[[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [1.0, 1.0], [0.0, 2.0]]);
+		assert_eq!(Shape::polygon(dented), Err(PolygonError::NotConvex));
+		// Every corner turns the same way, but the outline goes around twice
+		let pentagram = (0..5)
+			.map(|index| Vec2::new(0.0, 1.0).rotate(Angle::from_degrees(144.0 * index as f32)))
+			.collect();
+		assert_eq!(Shape::polygon(pentagram), Err(PolygonError::NotConvex));
+
+		assert!(Shape::polygon(points(&[[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]])).is_ok());
+		assert!(Shape::polygon(points(&[[0.0, 0.0], [0.0, 1.0], [1.0, 0.0]])).is_ok(), "clockwise");
+		assert!(Shape::polygon(points(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [1.0, 1.0]])).is_ok(), "collinear edge");
+		assert!(Shape::polygon(points(&[[0.0, 0.0], [0.001, 0.0], [0.0, 0.001]])).is_ok(), "small");
+	}
+
+	#[test]
+	fn polygon_deserialization() {
+		for yaml in [
+			"polygon: {vertices: []}",
+			"polygon: {vertices: [[0, 0], [1, 0]]}",
+			"polygon: {vertices: [[0, 0], [1, 1], [2, 2]]}",
+			"polygon: {vertices: [[0, 0], [2, 0], [2, 2], [1, 1], [0, 2]]}",
+		].iter()
+		{
+			assert!(serde_yaml::from_str::<Shape>(yaml).is_err(), "{}", yaml);
+		}
+
+		let triangle = Shape::polygon(points(&[[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]])).unwrap();
+		assert_eq!(serde_yaml::from_str::<Shape>("polygon: {vertices: [[0, 0], [1, 0], [0, 1]]}").unwrap(), triangle);
+		let written = serde_yaml::to_string(&triangle).unwrap();
+		assert_eq!(serde_yaml::from_str::<Shape>(&written).unwrap(), triangle);
+	}
+
+	#[test]
+	fn bounds() {
+		let rotated = Transform::new(Vec2::zero(), Angle::from_degrees(45.0), Vec2::splat(1.0));
+		let oriented = Shape::OrientedBox {
+			half_extents: Vec2::splat(1.0),
+		};
+		assert!((oriented.bounds(&rotated).max().x - 2.0f32.sqrt()).abs() < 1e-5);
+		let aabb = Shape::Aabb {
+			half_extents: Vec2::splat(1.0),
+		};
+		assert_eq!(aabb.bounds(&rotated), Rect::new(Vec2::splat(-1.0), Vec2::splat(1.0)));
+
+		let capsule = Shape::Capsule {
+			half_length: 1.0,
+			radius: 0.5,
+		};
+		let transform = Transform::new(Vec2::splat(1.0), Angle::zero(), Vec2::splat(1.0));
+		assert_eq!(capsule.bounds(&transform), Rect::new(Vec2::new(-0.5, 0.5), Vec2::new(2.5, 1.5)));
+	}
+}
@@ -1,5 +1,13 @@
 // Configuration file save-and-loading utility.
 
+use graphics::post_processing::PostPassConfig;
+use graphics::viewport::RenderMode;
+
+use std::collections::BTreeMap;
+
+/// Font used when the configuration doesn't name one
+pub const DEFAULT_FONT: &str = "arimo.ttf";
+
 // A single structure that is able to hold all necessary configurations
 // All variables have default, so that user can easily reset single setting by just deleting them
 #[derive(Serialize, Deserialize, Debug)]
@@ -12,36 +20,109 @@ pub struct Configuration {
 
 	#[serde(default)]
 	pub font: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub seed: Option<u64>, // Seed of all simulation randomness, a new one is picked every run if not set
+	#[serde(default = "default_simulation_rate")]
+	pub simulation_rate: u32, // Simulation steps per second, independent of the frame rate
 	#[serde(default = "default_batch_size")]
 	pub batch_size: usize,
+	#[serde(default = "default_auto_batch_size")]
+	pub auto_batch_size: bool, // Should batch size be measured on startup whenever the renderer changes
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub calibrated_renderer: Option<String>, // OpenGL renderer the batch size was measured on
+
+	#[serde(default = "default_render_mode")]
+	pub render_mode: RenderMode,
+	#[serde(default = "default_virtual_resolution")]
+	pub virtual_resolution: (u32, u32), // Scene resolution used in pixel perfect render mode
+	#[serde(default = "default_resolution_scale")]
+	pub resolution_scale: f32, // Scene resolution relative to the window in native render mode
 
 	#[serde(default, skip_serializing_if = "Option::is_none")]
 	pub window_position: Option<(f64, f64)>,
 	#[serde(default, skip_serializing_if = "Option::is_none")]
 	pub window_size: Option<(f64, f64)>,
 
+	#[serde(default = "default_compress_saves")]
+	pub compress_saves: bool, // Should save games be gzip compressed
+
 	#[serde(default)]
 	pub debug_mode: bool,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub profiler_csv: Option<String>, // File every frame's profiler statistics are written to on exit
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub trace_budget_ms: Option<f32>, // Frames taking longer than this many milliseconds automatically export a trace
+
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub shader_defines: BTreeMap<String, String>, // Extra preprocessor definitions for all shaders, used as feature toggles
+
+	#[serde(default = "default_post_passes")]
+	pub post_passes: Vec<PostPassConfig>, // Full-screen passes run over the scene in order, each names a material
 
 	#[serde(skip)]
 	pub changed: bool,
 }
 
+fn default_simulation_rate() -> u32 {
+	60
+}
+
 fn default_batch_size() -> usize {
 	1024
 }
 
+fn default_auto_batch_size() -> bool {
+	true
+}
+
+fn default_compress_saves() -> bool {
+	true
+}
+
+fn default_render_mode() -> RenderMode {
+	RenderMode::Native
+}
+
+fn default_virtual_resolution() -> (u32, u32) {
+	(320, 180)
+}
+
+fn default_resolution_scale() -> f32 {
+	1.0
+}
+
+fn default_post_passes() -> Vec<PostPassConfig> {
+	["bloom", "color_grading", "fxaa", "vignette", "crt"]
+		.iter()
+		.map(|material| PostPassConfig {
+			material: String::from(*material),
+			enabled: false,
+		}).collect()
+}
+
 impl Default for Configuration {
 	// Create a new defaulted Configuration
 	fn default() -> Self {
 		Self {
 			fullscreen: false,
 			vsync: true,
-			font: String::from("arimo.ttf"),
+			font: String::from(DEFAULT_FONT),
+			seed: None,
+			simulation_rate: default_simulation_rate(),
 			batch_size: default_batch_size(),
+			auto_batch_size: default_auto_batch_size(),
+			calibrated_renderer: None,
+			render_mode: default_render_mode(),
+			virtual_resolution: default_virtual_resolution(),
+			resolution_scale: default_resolution_scale(),
 			window_position: None,
 			window_size: None,
+			compress_saves: default_compress_saves(),
 			debug_mode: false,
+			profiler_csv: None,
+			trace_budget_ms: None,
+			shader_defines: BTreeMap::new(),
+			post_passes: default_post_passes(),
 			changed: true,
 		}
 	}
@@ -87,6 +168,15 @@ impl Configuration {
 		self.debug_mode = enabled;
 	}
 
+	/// Store a measured batch size along with the renderer it was measured on
+	pub fn set_calibrated_batch_size(&mut self, batch_size: usize, renderer: String) {
+		self.changed = self.changed
+			|| self.batch_size != batch_size
+			|| self.calibrated_renderer.as_ref() != Some(&renderer);
+		self.batch_size = batch_size;
+		self.calibrated_renderer = Some(renderer);
+	}
+
 	pub fn set_window_position(&mut self, window_position: (f64, f64)) {
 		self.changed = self.changed || self.window_position != Some(window_position);
 		self.window_position = Some(window_position);
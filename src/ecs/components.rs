@@ -0,0 +1,61 @@
+// Components shared by most game objects
+
+pub use graphics::transform::Transform;
+
+use graphics::instance::{Instance, SpriteEffects};
+use graphics::material::Material;
+use graphics::texture::Texture;
+use graphics::vector::Vec2;
+
+use super::Entity;
+
+/// How an entity is drawn, combined with its Transform into an Instance
+#[derive(Clone, Debug)]
+pub struct Sprite {
+	pub texture_lit: Texture,
+	pub texture_unlit: Texture,
+	pub color_lit: [f32; 4],
+	pub color_unlit: [f32; 4],
+	pub material: Material,
+	pub effects: SpriteEffects,
+}
+
+impl Sprite {
+	/// A sprite drawn with the default material and no effects
+	pub fn new(texture_lit: Texture, texture_unlit: Texture, color_lit: [f32; 4], color_unlit: [f32; 4]) -> Self {
+		Self {
+			texture_lit: texture_lit,
+			texture_unlit: texture_unlit,
+			color_lit: color_lit,
+			color_unlit: color_unlit,
+			material: Material::default(),
+			effects: SpriteEffects::default(),
+		}
+	}
+
+	pub fn instance(&self, transform: &Transform) -> Instance {
+		Instance {
+			transform: transform.clone(),
+			color_lit: self.color_lit,
+			color_unlit: self.color_unlit,
+			texture_lit: self.texture_lit,
+			texture_unlit: self.texture_unlit,
+			material: self.material,
+			effects: self.effects,
+		}
+	}
+}
+
+/// Attaches an entity to another, its Transform is kept relative to the parent by HierarchySystem
+#[derive(Clone, Debug)]
+pub struct Parent {
+	pub entity: Entity,
+	pub local: Transform, // Relative to the parent
+}
+
+/// Movement per second
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Velocity {
+	pub linear: Vec2,  // world units
+	pub angular: f32, // radians
+}
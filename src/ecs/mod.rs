@@ -0,0 +1,44 @@
+// Entity-component system for game objects
+//
+// Entities are generational ids, every component type is kept in its own sparse set.
+// Systems borrow the storages they need from the World, reading and writing different types at once.
+
+pub use self::storage::SparseSet;
+pub use self::world::{Entity, EntityBuilder, World};
+
+pub mod components;	// Components shared by most game objects
+pub mod storage;	// Sparse set storage of a single component type
+pub mod systems;	// Systems that drive common components and extract them for rendering
+pub mod world;		// Entities and component storages
+
+/// Logic that runs over the components of a World
+pub trait System {
+	/// Advance by delta seconds
+	fn run(&mut self, world: &mut World, delta: f32);
+}
+
+/// Systems that run one after another in a fixed order
+pub struct Schedule {
+	systems: Vec<Box<System>>,
+}
+
+impl Schedule {
+	pub fn new() -> Self {
+		Self { systems: Vec::new() }
+	}
+
+	pub fn with<T: System + 'static>(mut self, system: T) -> Self {
+		self.add(system);
+		self
+	}
+
+	pub fn add<T: System + 'static>(&mut self, system: T) {
+		self.systems.push(Box::new(system));
+	}
+
+	pub fn run(&mut self, world: &mut World, delta: f32) {
+		for system in self.systems.iter_mut() {
+			system.run(world, delta);
+		}
+	}
+}
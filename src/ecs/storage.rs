@@ -0,0 +1,131 @@
+// Sparse set storage
+//
+// Components are packed densely for fast iteration, a sparse array maps entity indices into the dense array.
+
+use super::world::Entity;
+
+const EMPTY: u32 = std::u32::MAX;
+
+/// Components of a single type, indexed by entity
+#[derive(Debug, Clone)]
+pub struct SparseSet<T> {
+	sparse: Vec<u32>,      // entity index to dense index, EMPTY if the entity has no component
+	entities: Vec<Entity>, // owner of every dense component
+	components: Vec<T>,
+}
+
+impl<T> Default for SparseSet<T> {
+	fn default() -> Self {
+		Self {
+			sparse: Vec::new(),
+			entities: Vec::new(),
+			components: Vec::new(),
+		}
+	}
+}
+
+impl<T> SparseSet<T> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn len(&self) -> usize {
+		self.components.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.components.is_empty()
+	}
+
+	fn dense_index(&self, entity: Entity) -> Option<usize> {
+		let index = *self.sparse.get(entity.index() as usize)?;
+		if index != EMPTY && self.entities[index as usize] == entity {
+			Some(index as usize)
+		} else {
+			None
+		}
+	}
+
+	pub fn contains(&self, entity: Entity) -> bool {
+		self.dense_index(entity).is_some()
+	}
+
+	/// Add a component to an entity, returns the replaced component if it already had one
+	pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+		let slot = entity.index() as usize;
+		if slot >= self.sparse.len() {
+			self.sparse.resize(slot + 1, EMPTY);
+		}
+
+		let index = self.sparse[slot];
+		if index != EMPTY {
+			// The slot might still be owned by a dead entity with an older generation
+			let index = index as usize;
+			self.entities[index] = entity;
+			return Some(std::mem::replace(&mut self.components[index], component));
+		}
+
+		self.sparse[slot] = self.components.len() as u32;
+		self.entities.push(entity);
+		self.components.push(component);
+		None
+	}
+
+	/// Remove the component of an entity, the last component takes its place
+	pub fn remove(&mut self, entity: Entity) -> Option<T> {
+		let index = self.dense_index(entity)?;
+
+		self.sparse[entity.index() as usize] = EMPTY;
+		let last = *self.entities.last().unwrap();
+		if last != entity {
+			self.sparse[last.index() as usize] = index as u32;
+		}
+		self.entities.swap_remove(index);
+		Some(self.components.swap_remove(index))
+	}
+
+	pub fn get(&self, entity: Entity) -> Option<&T> {
+		let index = self.dense_index(entity)?;
+		Some(&self.components[index])
+	}
+
+	pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+		let index = self.dense_index(entity)?;
+		Some(&mut self.components[index])
+	}
+
+	/// Entities that have this component, in storage order
+	pub fn entities(&self) -> &[Entity] {
+		&self.entities
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+		self.entities.iter().cloned().zip(self.components.iter())
+	}
+
+	pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+		self.entities.iter().cloned().zip(self.components.iter_mut())
+	}
+
+	/// Entities that have both components, in the order of the smaller storage
+	pub fn join<'a, U>(&'a self, other: &'a SparseSet<U>) -> Box<Iterator<Item = (Entity, &'a T, &'a U)> + 'a> {
+		if self.len() <= other.len() {
+			Box::new(
+				self.iter()
+					.filter_map(move |(entity, component)| other.get(entity).map(|other| (entity, component, other))),
+			)
+		} else {
+			Box::new(
+				other
+					.iter()
+					.filter_map(move |(entity, other)| self.get(entity).map(|component| (entity, component, other))),
+			)
+		}
+	}
+
+	/// Entities that have both components, with mutable access to components of this storage
+	pub fn join_mut<'a, U>(&'a mut self, other: &'a SparseSet<U>) -> impl Iterator<Item = (Entity, &'a mut T, &'a U)> {
+		self.iter_mut()
+			.filter_map(move |(entity, component)| other.get(entity).map(|other| (entity, component, other)))
+	}
+}
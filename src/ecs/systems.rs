@@ -0,0 +1,150 @@
+// Systems that drive common components and extract them for rendering
+
+use super::components::{Parent, Sprite, Transform, Velocity};
+use super::{Entity, SparseSet, System, World};
+
+use graphics::angle::Angle;
+use graphics::instance::Instance;
+use graphics::scene_graph::{NodeId, SceneGraph};
+
+use std::collections::{HashMap as Map, HashSet as Set};
+
+/// Moves and rotates entities by their Velocity
+pub struct MovementSystem;
+
+impl System for MovementSystem {
+	fn run(&mut self, world: &mut World, delta: f32) {
+		let mut transforms = world.write::<Transform>();
+		let velocities = world.read::<Velocity>();
+
+		for (_, transform, velocity) in transforms.join_mut(&velocities) {
+			transform.translate(velocity.linear * delta);
+			transform.rotate(Angle::from_radians(velocity.angular * delta));
+		}
+	}
+}
+
+/// Places children relative to their parents and despawns children whose parent is gone
+///
+/// The hierarchy is mirrored into a SceneGraph, so only children whose ancestors changed get recomposed.
+/// Velocity of a child moves it relative to its parent. Run after everything that moves parents.
+pub struct HierarchySystem {
+	graph: SceneGraph,
+	nodes: Map<Entity, NodeId>,
+}
+
+impl HierarchySystem {
+	pub fn new() -> Self {
+		Self {
+			graph: SceneGraph::new(),
+			nodes: Map::new(),
+		}
+	}
+
+	/// Bring the node of an entity and its ancestors up to date with the world
+	///
+	/// None if one of the ancestors is dead or the topmost one has no Transform.
+	fn sync(
+		&mut self,
+		world: &World,
+		parents: &SparseSet<Parent>,
+		transforms: &SparseSet<Transform>,
+		visited: &mut Set<Entity>,
+		entity: Entity,
+	) -> Option<NodeId> {
+		if !visited.insert(entity) {
+			return self.nodes.get(&entity).cloned().filter(|node| self.graph.contains(*node));
+		}
+
+		let (local, parent_node) = match parents.get(entity) {
+			Some(parent) if world.is_alive(parent.entity) => (
+				parent.local.clone(),
+				Some(self.sync(world, parents, transforms, visited, parent.entity)?),
+			),
+			Some(_) => return None,
+			None => (transforms.get(entity)?.clone(), None),
+		};
+
+		match self.nodes.get(&entity).cloned().filter(|node| self.graph.contains(*node)) {
+			Some(node) => {
+				if self.graph.parent(node) != parent_node {
+					self.graph.set_parent(node, parent_node);
+				}
+				// Unchanged nodes stay clean, keeping the cached world transforms of their children
+				if *self.graph.local(node) != local {
+					self.graph.set_local(node, local);
+				}
+				Some(node)
+			}
+			None => {
+				let node = self.graph.add(local, parent_node);
+				self.nodes.insert(entity, node);
+				Some(node)
+			}
+		}
+	}
+}
+
+impl System for HierarchySystem {
+	fn run(&mut self, world: &mut World, delta: f32) {
+		world.register::<Parent>();
+		world.register::<Velocity>();
+
+		{
+			let mut parents = world.write::<Parent>();
+			let velocities = world.read::<Velocity>();
+			for (_, parent, velocity) in parents.join_mut(&velocities) {
+				parent.local.translate(velocity.linear * delta);
+				parent.local.rotate(Angle::from_radians(velocity.angular * delta));
+			}
+		}
+
+		let mut resolved = Vec::new();
+		let mut orphans = Vec::new();
+		{
+			let parents = world.read::<Parent>();
+			let transforms = world.read::<Transform>();
+			let mut visited = Set::new();
+			for entity in parents.entities() {
+				match self.sync(world, &parents, &transforms, &mut visited, *entity) {
+					Some(node) => resolved.push((*entity, node)),
+					None => orphans.push(*entity),
+				}
+			}
+
+			// Entities that were despawned or detached since the last run
+			let stale: Vec<Entity> = self.nodes.keys().filter(|entity| !visited.contains(*entity)).cloned().collect();
+			for entity in stale {
+				let node = self.nodes.remove(&entity).unwrap();
+				if self.graph.contains(node) {
+					self.graph.remove(node);
+				}
+			}
+		}
+
+		{
+			let mut transforms = world.write::<Transform>();
+			for (entity, node) in resolved {
+				if let Some(current) = transforms.get_mut(entity) {
+					*current = self.graph.world(node).clone();
+				}
+			}
+		}
+		for entity in orphans {
+			world.despawn(entity);
+		}
+	}
+}
+
+/// Collect every entity with a Transform and a Sprite into instances that can be drawn, along with the entity
+pub fn extract_instances(world: &World, instances: &mut Vec<(Entity, Instance)>) {
+	let sprites = world.read::<Sprite>();
+	let transforms = world.read::<Transform>();
+
+	instances.clear();
+	instances.extend(
+		sprites
+			.iter()
+			.filter_map(|(entity, sprite)| transforms.get(entity).map(|transform| (entity, sprite.instance(transform)))),
+	);
+}
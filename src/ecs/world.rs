@@ -0,0 +1,197 @@
+// Entities and their component storages
+
+use super::storage::SparseSet;
+
+use std::any::{Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap as Map;
+
+/// A game object, components are attached to it through the World
+///
+/// Indices of despawned entities are reused with a new generation, so stale handles never alias new entities.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Entity {
+	index: u32,
+	generation: u32,
+}
+
+impl Entity {
+	pub fn index(&self) -> u32 {
+		self.index
+	}
+
+	pub fn generation(&self) -> u32 {
+		self.generation
+	}
+}
+
+/// Type-erased component storage
+trait Storage {
+	fn as_any(&self) -> &Any;
+	fn remove_entity(&self, entity: Entity);
+}
+
+impl<T: 'static> Storage for RefCell<SparseSet<T>> {
+	fn as_any(&self) -> &Any {
+		self
+	}
+
+	fn remove_entity(&self, entity: Entity) {
+		self.borrow_mut().remove(entity);
+	}
+}
+
+/// All entities and components of a scene
+pub struct World {
+	generations: Vec<u32>, // current generation of every entity index
+	alive: Vec<bool>,
+	free: Vec<u32>, // indices of despawned entities available for reuse
+	storages: Map<TypeId, Box<Storage>>,
+}
+
+impl World {
+	pub fn new() -> Self {
+		Self {
+			generations: Vec::new(),
+			alive: Vec::new(),
+			free: Vec::new(),
+			storages: Map::new(),
+		}
+	}
+
+	/// Create an entity without components
+	pub fn spawn(&mut self) -> Entity {
+		match self.free.pop() {
+			Some(index) => {
+				self.alive[index as usize] = true;
+				Entity {
+					index: index,
+					generation: self.generations[index as usize],
+				}
+			}
+			None => {
+				self.generations.push(0);
+				self.alive.push(true);
+				Entity {
+					index: (self.generations.len() - 1) as u32,
+					generation: 0,
+				}
+			}
+		}
+	}
+
+	/// Create an entity and add components to it in a chain
+	pub fn build(&mut self) -> EntityBuilder {
+		let entity = self.spawn();
+		EntityBuilder {
+			world: self,
+			entity: entity,
+		}
+	}
+
+	/// Remove an entity with all of its components, returns false if it was already dead
+	pub fn despawn(&mut self, entity: Entity) -> bool {
+		if !self.is_alive(entity) {
+			return false;
+		}
+
+		for storage in self.storages.values() {
+			storage.remove_entity(entity);
+		}
+
+		let index = entity.index as usize;
+		self.alive[index] = false;
+		self.generations[index] = self.generations[index].wrapping_add(1);
+		self.free.push(entity.index);
+		true
+	}
+
+	pub fn is_alive(&self, entity: Entity) -> bool {
+		let index = entity.index as usize;
+		index < self.alive.len() && self.alive[index] && self.generations[index] == entity.generation
+	}
+
+	/// All living entities
+	pub fn entities<'a>(&'a self) -> impl Iterator<Item = Entity> + 'a {
+		self.alive
+			.iter()
+			.zip(self.generations.iter())
+			.enumerate()
+			.filter(|(_, (alive, _))| **alive)
+			.map(|(index, (_, generation))| Entity {
+				index: index as u32,
+				generation: *generation,
+			})
+	}
+
+	/// Create the storage of a component type, so that systems can borrow it before any component is added
+	pub fn register<T: 'static>(&mut self) {
+		self.storages
+			.entry(TypeId::of::<T>())
+			.or_insert_with(|| Box::new(RefCell::new(SparseSet::<T>::new())));
+	}
+
+	fn storage<T: 'static>(&self) -> &RefCell<SparseSet<T>> {
+		match self.storages.get(&TypeId::of::<T>()) {
+			Some(storage) => storage.as_any().downcast_ref().unwrap(),
+			None => panic!("component storage of {} is not registered!", std::any::type_name::<T>()),
+		}
+	}
+
+	/// Borrow all components of a type
+	///
+	/// Panics if the type was never registered or the storage is borrowed mutably.
+	pub fn read<T: 'static>(&self) -> Ref<SparseSet<T>> {
+		self.storage::<T>().borrow()
+	}
+
+	/// Mutably borrow all components of a type
+	///
+	/// Panics if the type was never registered or the storage is already borrowed.
+	pub fn write<T: 'static>(&self) -> RefMut<SparseSet<T>> {
+		self.storage::<T>().borrow_mut()
+	}
+
+	/// Add a component to a living entity, replacing the previous one of the same type
+	pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) {
+		assert!(self.is_alive(entity), "inserting a component into a dead entity!");
+		self.register::<T>();
+		self.write::<T>().insert(entity, component);
+	}
+
+	pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+		if !self.storages.contains_key(&TypeId::of::<T>()) {
+			return None;
+		}
+		self.write::<T>().remove(entity)
+	}
+
+	/// Copy of a single component
+	pub fn get<T: Clone + 'static>(&self, entity: Entity) -> Option<T> {
+		if !self.storages.contains_key(&TypeId::of::<T>()) {
+			return None;
+		}
+		self.read::<T>().get(entity).cloned()
+	}
+
+	pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+		self.storages.contains_key(&TypeId::of::<T>()) && self.read::<T>().contains(entity)
+	}
+}
+
+/// Adds components to a freshly spawned entity
+pub struct EntityBuilder<'a> {
+	world: &'a mut World,
+	entity: Entity,
+}
+
+impl<'a> EntityBuilder<'a> {
+	pub fn with<T: 'static>(self, component: T) -> Self {
+		self.world.insert(self.entity, component);
+		self
+	}
+
+	pub fn entity(self) -> Entity {
+		self.entity
+	}
+}
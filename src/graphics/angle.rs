@@ -0,0 +1,236 @@
+// Angles that know how to wrap around
+//
+// Stored in radians. Arithmetic doesn't normalize on its own, normalized() brings an angle into [0, 2π)
+// and distance_to() gives the shortest signed arc between two angles.
+
+use super::math::{Lerp, MAX_ROTATION, PI};
+
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+/// An angle in radians, serialized as a plain number
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Angle(f32);
+
+impl Angle {
+	pub fn zero() -> Self {
+		Angle(0.0)
+	}
+
+	pub fn from_radians(radians: f32) -> Self {
+		Angle(radians)
+	}
+
+	pub fn from_degrees(degrees: f32) -> Self {
+		Angle(degrees.to_radians())
+	}
+
+	pub fn as_radians(self) -> f32 {
+		self.0
+	}
+
+	pub fn as_degrees(self) -> f32 {
+		self.0.to_degrees()
+	}
+
+	/// The same direction within [0, 2π)
+	pub fn normalized(self) -> Self {
+		let wrapped = self.0 - (self.0 / MAX_ROTATION).floor() * MAX_ROTATION;
+		// Tiny negative angles round up to a full turn
+		if wrapped >= MAX_ROTATION {
+			Angle(0.0)
+		} else {
+			Angle(wrapped)
+		}
+	}
+
+	/// The same direction within [-π, π)
+	pub fn normalized_signed(self) -> Self {
+		let normalized = self.normalized().0;
+		if normalized >= PI {
+			Angle(normalized - MAX_ROTATION)
+		} else {
+			Angle(normalized)
+		}
+	}
+
+	/// Shortest signed arc from this angle to the other one, within [-π, π)
+	pub fn distance_to(self, other: Angle) -> Angle {
+		(other - self).normalized_signed()
+	}
+
+	pub fn sin(self) -> f32 {
+		self.0.sin()
+	}
+
+	pub fn cos(self) -> f32 {
+		self.0.cos()
+	}
+
+	pub fn sin_cos(self) -> (f32, f32) {
+		self.0.sin_cos()
+	}
+}
+
+/// Interpolates along the shortest arc, the result is not normalized
+impl Lerp for Angle {
+	fn lerp(a: &Self, b: &Self, t: f32) -> Self {
+		*a + a.distance_to(*b) * t
+	}
+}
+
+impl Add for Angle {
+	type Output = Angle;
+
+	fn add(self, other: Angle) -> Angle {
+		Angle(self.0 + other.0)
+	}
+}
+
+impl Sub for Angle {
+	type Output = Angle;
+
+	fn sub(self, other: Angle) -> Angle {
+		Angle(self.0 - other.0)
+	}
+}
+
+impl Neg for Angle {
+	type Output = Angle;
+
+	fn neg(self) -> Angle {
+		Angle(-self.0)
+	}
+}
+
+impl Mul<f32> for Angle {
+	type Output = Angle;
+
+	fn mul(self, factor: f32) -> Angle {
+		Angle(self.0 * factor)
+	}
+}
+
+impl Div<f32> for Angle {
+	type Output = Angle;
+
+	fn div(self, divisor: f32) -> Angle {
+		Angle(self.0 / divisor)
+	}
+}
+
+impl AddAssign for Angle {
+	fn add_assign(&mut self, other: Angle) {
+		self.0 += other.0;
+	}
+}
+
+impl SubAssign for Angle {
+	fn sub_assign(&mut self, other: Angle) {
+		self.0 -= other.0;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use graphics::math::{lerp_angle, short_angle_distance};
+
+	const EPSILON: f32 = 1e-5;
+
+	fn close(a: f32, b: f32) -> bool {
+		(a - b).abs() < EPSILON
+	}
+
+	fn same_direction(a: Angle, b: Angle) -> bool {
+		close(a.distance_to(b).as_radians(), 0.0)
+	}
+
+	#[test]
+	fn normalized_wraps_into_a_full_turn() {
+		assert!(close(Angle::from_radians(1.0).normalized().as_radians(), 1.0));
+		assert!(close(Angle::from_radians(-1.0).normalized().as_radians(), MAX_ROTATION - 1.0));
+		assert!(close(Angle::from_radians(-PI / 2.0).normalized().as_radians(), PI * 1.5));
+		assert!(close(Angle::from_radians(MAX_ROTATION + 1.0).normalized().as_radians(), 1.0));
+		assert!(close(Angle::from_radians(-3.0 * MAX_ROTATION - 1.0).normalized().as_radians(), MAX_ROTATION - 1.0));
+	}
+
+	#[test]
+	fn normalized_multiples_of_a_full_turn_are_zero() {
+		for turns in -3..4 {
+			let normalized = Angle::from_radians(turns as f32 * MAX_ROTATION).normalized().as_radians();
+			assert!((0.0..MAX_ROTATION).contains(&normalized));
+			assert!(normalized < EPSILON || MAX_ROTATION - normalized < 1e-3, "{} turns", turns);
+		}
+		assert_eq!(Angle::from_radians(MAX_ROTATION).normalized(), Angle::zero());
+	}
+
+	#[test]
+	fn normalized_tiny_negative_stays_below_a_full_turn() {
+		// -1e-8 + 2π rounds to exactly 2π in f32
+		let normalized = Angle::from_radians(-1e-8).normalized().as_radians();
+		assert!((0.0..MAX_ROTATION).contains(&normalized));
+		assert_eq!(normalized, 0.0);
+	}
+
+	#[test]
+	fn normalized_signed_is_within_half_turns() {
+		assert!(close(Angle::from_radians(-1.0).normalized_signed().as_radians(), -1.0));
+		assert!(close(Angle::from_radians(MAX_ROTATION - 1.0).normalized_signed().as_radians(), -1.0));
+		assert!(close(Angle::from_radians(-MAX_ROTATION + 1.0).normalized_signed().as_radians(), 1.0));
+		// The boundary belongs to the negative side
+		assert!(close(Angle::from_radians(PI).normalized_signed().as_radians(), -PI));
+		assert!(close(Angle::from_radians(-PI).normalized_signed().as_radians(), -PI));
+		assert!(close(Angle::from_radians(PI - 1e-3).normalized_signed().as_radians(), PI - 1e-3));
+	}
+
+	#[test]
+	fn distance_to_takes_the_shortest_arc() {
+		let distance = |a: f32, b: f32| Angle::from_radians(a).distance_to(Angle::from_radians(b)).as_radians();
+		assert!(close(distance(0.0, 1.0), 1.0));
+		assert!(close(distance(1.0, 0.0), -1.0));
+		assert!(close(distance(0.1, MAX_ROTATION - 0.1), -0.2));
+		assert!(close(distance(MAX_ROTATION - 0.1, 0.1), 0.2));
+		assert!(close(distance(-10.0, 10.0), 20.0 - 3.0 * MAX_ROTATION));
+		assert!(close(distance(0.0, PI), -PI));
+		assert!(close(distance(0.0, -PI), -PI));
+		assert!(close(distance(0.0, PI - 1e-3), PI - 1e-3));
+		assert!(close(distance(0.0, -PI + 1e-3), -PI + 1e-3));
+	}
+
+	#[test]
+	fn lerp_crosses_zero_the_short_way() {
+		let a = Angle::from_radians(MAX_ROTATION - 0.2);
+		let b = Angle::from_radians(0.2);
+		assert!(same_direction(Angle::lerp(&a, &b, 0.0), a));
+		assert!(same_direction(Angle::lerp(&a, &b, 0.5), Angle::zero()));
+		assert!(same_direction(Angle::lerp(&a, &b, 0.75), Angle::from_radians(0.1)));
+		assert!(same_direction(Angle::lerp(&b, &a, 0.75), Angle::from_radians(-0.1)));
+		assert!(same_direction(Angle::lerp(&a, &b, 1.0), b));
+	}
+
+	#[test]
+	fn degrees_round_trip() {
+		for &degrees in [0.0, 45.0, -90.0, 180.0, 359.0, 720.0].iter() {
+			assert!((Angle::from_degrees(degrees).as_degrees() - degrees).abs() < 1e-3);
+		}
+		assert!(close(Angle::from_degrees(180.0).as_radians(), PI));
+		assert!(close(Angle::from_degrees(-90.0).as_radians(), -PI / 2.0));
+		assert!(close(Angle::from_radians(PI / 2.0).as_degrees(), 90.0));
+	}
+
+	#[test]
+	fn short_angle_distance_handles_negative_deltas() {
+		assert!(close(short_angle_distance(0.5, 0.0), -0.5));
+		assert!(close(short_angle_distance(3.0, -3.0), MAX_ROTATION - 6.0));
+		assert!(close(short_angle_distance(-3.0, 3.0), 6.0 - MAX_ROTATION));
+		assert!(close(short_angle_distance(6.0, 0.5), 0.5 + MAX_ROTATION - 6.0));
+		assert!(close(short_angle_distance(0.0, -PI), -PI));
+	}
+
+	#[test]
+	fn lerp_angle_takes_the_shortest_arc() {
+		assert!(close(lerp_angle(6.0, 0.5, 0.5), 6.0 + (0.5 + MAX_ROTATION - 6.0) / 2.0));
+		assert!(close(lerp_angle(0.5, 6.0, 0.5), 0.5 - (0.5 + MAX_ROTATION - 6.0) / 2.0));
+		assert!(close(lerp_angle(-3.0, 3.0, 1.0), -3.0 + 6.0 - MAX_ROTATION));
+	}
+}
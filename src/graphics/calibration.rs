@@ -0,0 +1,108 @@
+// Automatic batch size selection
+//
+// The fastest batch size depends on the driver, so a grid of instances is drawn offscreen
+// with several candidate sizes and the one with the lowest median draw time wins.
+
+use super::angle::Angle;
+use super::instance::Instance;
+use super::math::Rect;
+use super::scene::Scene;
+use super::texture::GLTexture;
+use super::transform::Transform;
+use super::vector::Vec2;
+
+use std::time::Duration;
+
+/// Batch sizes that are tried
+pub const CANDIDATES: [usize; 7] = [128, 256, 512, 1024, 2048, 4096, 8192];
+
+/// Side of the instance grid, large enough for batch sizes to matter
+const GRID_SIDE: u32 = 128;
+/// Draws per candidate that are not measured
+const WARMUP_ROUNDS: u32 = 3;
+/// Measured draws per candidate
+const MEASURED_ROUNDS: u32 = 15;
+
+/// Resolution of the offscreen target used for calibration
+pub const TARGET_SIZE: (u32, u32) = (512, 512);
+
+/// Outcome of a calibration
+#[derive(Debug, Clone)]
+pub struct Calibration {
+	pub batch_size: usize,
+	pub timings: Vec<(usize, Duration)>, // median draw time of every candidate
+}
+
+/// A grid of copies of an instance from another scene, drawn with that scene's texture
+pub struct CalibrationScene<'a> {
+	objects: Vec<Instance>,
+	texture: &'a GLTexture,
+}
+
+impl<'a> CalibrationScene<'a> {
+	/// Returns None if the scene has no objects to copy
+	pub fn from_scene<T: Scene>(scene: &'a T) -> Option<Self> {
+		let template = scene.object_instances().first()?;
+
+		let mut objects = Vec::with_capacity((GRID_SIDE * GRID_SIDE) as usize);
+		for x in 0..GRID_SIDE {
+			for y in 0..GRID_SIDE {
+				let mut object = template.clone();
+				object.transform = Transform::new(Vec2::new(x as f32 + 0.5, y as f32 + 0.5), Angle::from_radians((x + y) as f32 * 0.1), Vec2::splat(1.0));
+				objects.push(object);
+			}
+		}
+
+		Some(Self {
+			objects: objects,
+			texture: scene.texture(),
+		})
+	}
+}
+
+impl<'a> Scene for CalibrationScene<'a> {
+	fn object_instances(&self) -> &[Instance] {
+		&self.objects
+	}
+
+	fn view_rect(&self) -> Rect {
+		Rect::new(Vec2::zero(), Vec2::splat(GRID_SIDE as f32))
+	}
+
+	fn texture(&self) -> &GLTexture {
+		self.texture
+	}
+}
+
+/// Measure every candidate with a function that draws once with the given batch size
+///
+/// The function returns None if it can't draw with a batch size, that candidate is skipped.
+/// Returns None if no candidate could be measured.
+pub fn find_fastest<F: FnMut(usize) -> Option<Duration>>(mut draw: F) -> Option<Calibration> {
+	let mut timings = Vec::with_capacity(CANDIDATES.len());
+
+	'candidates: for &batch_size in CANDIDATES.iter() {
+		for _ in 0..WARMUP_ROUNDS {
+			if draw(batch_size).is_none() {
+				continue 'candidates;
+			}
+		}
+
+		let mut rounds = Vec::with_capacity(MEASURED_ROUNDS as usize);
+		for _ in 0..MEASURED_ROUNDS {
+			match draw(batch_size) {
+				Some(duration) => rounds.push(duration),
+				None => continue 'candidates,
+			}
+		}
+		rounds.sort();
+		timings.push((batch_size, rounds[rounds.len() / 2]));
+	}
+
+	let batch_size = timings.iter().min_by_key(|timing| timing.1).map(|timing| timing.0)?;
+
+	Some(Calibration {
+		batch_size: batch_size,
+		timings: timings,
+	})
+}
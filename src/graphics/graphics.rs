@@ -1,125 +1,226 @@
 // Graphical context handling
 
-use config::Configuration;
-use SHADER_PREFIX;
-
-use super::instance::PerInstance;
-use super::scene::Scene;
-use super::{FRAGMENT_SHADER_EXTENSHION, INSTANCED_SHADER, VERTEX_SHADER_EXTENSHION};
-
+use config::{Configuration, DEFAULT_FONT};
+use profiler;
+
+use super::calibration::{self, Calibration, CalibrationScene};
+use super::instance::{Instance, PerInstance};
+use super::material::{Material, MaterialCollection, MaterialError};
+use super::math::Rect;
+use super::overlay::Overlay;
+use super::post_processing::PostProcessing;
+use super::scene::{Background, BackgroundFit, Scene};
+use super::shader::ShaderDefines;
+use super::text::{GlyphAtlas, TextError};
+use super::vector::{Mat3, Vec2};
+use super::viewport::{RenderSettings, Viewport};
+
+use glium::framebuffer::{DepthRenderBuffer, SimpleFrameBuffer};
 use glium::index::BufferCreationError as IndexBufferCreationError;
-use glium::program::ProgramCreationError;
+use glium::texture::{DepthFormat, RawImage2d, Texture2d};
 use glium::vertex::BufferCreationError as VertexBufferCreationError;
-use glium::{Display, IndexBuffer, Program, Surface, VertexBuffer};
+use glium::{Display, IndexBuffer, Surface, VertexBuffer};
+
+use image::RgbaImage;
+
+use std::time::Instant;
+
+/// Material used to draw gradient and textured backgrounds
+const BACKGROUND_MATERIAL: &str = "background";
+/// Material used to draw parallax layers
+const PARALLAX_MATERIAL: &str = "parallax";
 
-use std::io::Error as IoError;
-use std::path::Path;
+/// Pixel height of the overlay font
+const OVERLAY_FONT_SIZE: f32 = 16.0;
+
+// Background modes, must match background.frag
+const BACKGROUND_GRADIENT: f32 = 0.0;
+const BACKGROUND_STRETCH: f32 = 1.0;
+const BACKGROUND_TILE: f32 = 2.0;
 
 // Graphical context
 pub struct Graphics {
 	pub display: Display, // takes ownership of the glium display object
-	program: Program, // shaders used to draw objects
+	materials: MaterialCollection, // shaders and draw parameters used to draw objects
+
+	renderer: SceneRenderer,
+	post_processing: PostProcessing,
+	render_settings: RenderSettings,
+	overlay: Overlay, // drawn on top of the presented frame
+}
 
-	quad_vertices: VertexBuffer<Vertex>,
-	quad_indices: IndexBuffer<u16>,
+/// Unit quad every instance and full-screen pass is drawn with
+pub struct Quad {
+	pub vertices: VertexBuffer<Vertex>,
+	pub indices: IndexBuffer<u16>,
+}
 
-	instance_buffer: VertexBuffer<PerInstance>,
-	batch_size: usize,
+/// Buffers and materials used to draw a scene into a surface
+struct SceneRenderer {
+	quad: Quad,
+	instance_buffer: VertexBuffer<PerInstance>, // instances are uploaded in batches of buffer size
+	background_material: Material,
+	parallax_material: Material,
 }
 
 impl Graphics {
 	pub fn new(display: Display, config: &Configuration) -> Result<Self, GraphicsCreationError> {
-		let program = load_program(&display, &String::from(INSTANCED_SHADER))?;
-
-		let (verts, indcs) = generate_quad(&display)?;
+		// Shaders are only hot reloaded in debug mode
+		let mut materials = MaterialCollection::new(&display, ShaderDefines::from_config(config), config.debug_mode)?;
+		let post_processing = PostProcessing::new(&display, &mut materials, config)?;
+		let atlas = load_overlay_font(&display, &config.font)?;
+		let overlay = Overlay::new(&display, &mut materials, atlas)?;
+
+		let renderer = SceneRenderer {
+			quad: generate_quad(&display)?,
+			instance_buffer: generate_instance_buffer(&display, config.batch_size)?,
+			background_material: materials.load(&display, BACKGROUND_MATERIAL)?,
+			parallax_material: materials.load(&display, PARALLAX_MATERIAL)?,
+		};
 
-		let batch_size = config.batch_size;
-		let instances = generate_instance_buffer(&display, batch_size)?;
+		// Scene targets are drawn with a viewport covering them, so they can't be larger than the GPU's viewport limit
+		let (max_width, max_height) = display.get_capabilities().max_viewport_dims;
+		let render_settings = RenderSettings::from_config(config, (max_width.max(1) as u32, max_height.max(1) as u32));
 
 		Ok(Graphics {
 			display: display,
-			program: program,
-			quad_vertices: verts,
-			quad_indices: indcs,
-			instance_buffer: instances,
-			batch_size: batch_size,
+			materials: materials,
+			renderer: renderer,
+			post_processing: post_processing,
+			render_settings: render_settings,
+			overlay: overlay,
 		})
 	}
 
-	pub fn draw<T: Scene>(&mut self, scene: &T) {
-		let params = glium::DrawParameters {
-			depth: glium::Depth {
-				test: glium::DepthTest::IfLess,
-				write: true,
-				..Default::default()
-			},
-			blend: glium::Blend::alpha_blending(),
-			..Default::default()
-		};
+	/// Load a material by name, returns the existing handle if it was already loaded
+	pub fn load_material(&mut self, id: &str) -> Result<Material, MaterialError> {
+		self.materials.load(&self.display, id)
+	}
 
-		// Preserve aspect ratio of the world-space
-		let mut width_to_height = 1.0f32;
-		if let Some(size) = self.display.gl_window().get_inner_size() {
-			width_to_height = size.width as f32 / size.height as f32;
-		}
+	pub fn material(&self, id: &str) -> Option<Material> {
+		self.materials.get(id)
+	}
 
-		let view_rect = scene.view_rect();
+	/// Name a material was loaded with
+	pub fn material_id(&self, material: Material) -> Option<&str> {
+		self.materials.id(material)
+	}
 
-		let scale = if scene.preserve_ratio() {
-			// Calculate the necessary scaling
-			let mut scaling = 2.0 / view_rect.width();
-			let y_scaling = 2.0 / width_to_height / view_rect.height();
+	/// Recompile shaders whose files were modified since the last call
+	///
+	/// Only works in debug mode. On failure the old program is kept and the error is printed.
+	pub fn reload_changed_shaders(&mut self) {
+		self.materials.reload_changed_shaders(&self.display);
+	}
+
+	/// Change scale of the scene resolution relative to the window, takes effect on the next frame
+	///
+	/// Only used in native render mode, can be lowered dynamically to improve performance.
+	pub fn set_resolution_scale(&mut self, scale: f32) {
+		self.render_settings.resolution_scale = scale;
+	}
+
+	/// Change how many instances are uploaded and drawn at once, recreates the instance buffer
+	pub fn set_batch_size(&mut self, size: usize) -> Result<(), VertexBufferCreationError> {
+		self.renderer.instance_buffer = generate_instance_buffer(&self.display, size)?;
+		Ok(())
+	}
+
+	pub fn batch_size(&self) -> usize {
+		self.renderer.instance_buffer.len()
+	}
 
-			if scaling > y_scaling {
-				scaling = y_scaling;
+	/// Find the fastest batch size by drawing copies of the scene's first object offscreen, and switch to it
+	///
+	/// Candidates whose instance buffer can't be allocated are skipped.
+	/// Returns None and keeps the current batch size if the scene has no objects or no candidate could be tried.
+	pub fn calibrate_batch_size<T: Scene>(&mut self, scene: &T) -> Option<Calibration> {
+		let calibration_scene = CalibrationScene::from_scene(scene)?;
+		let (width, height) = calibration::TARGET_SIZE;
+		let color = Texture2d::empty(&self.display, width, height).ok()?;
+		let depth = DepthRenderBuffer::new(&self.display, DepthFormat::I24, width, height).ok()?;
+		let original_batch_size = self.batch_size();
+
+		let display = &self.display;
+		let renderer = &mut self.renderer;
+		let materials = &self.materials;
+		let result = calibration::find_fastest(|batch_size| {
+			if renderer.instance_buffer.len() != batch_size {
+				renderer.instance_buffer = generate_instance_buffer(display, batch_size).ok()?;
 			}
 
-			[scaling, width_to_height * scaling]
-		} else {
-			[2.0 / view_rect.width(), 2.0 / view_rect.height()]
-		};
+			let mut target = SimpleFrameBuffer::with_depth_buffer(display, &color, &depth)
+				.expect("failed to create calibration framebuffer!");
+			let start = Instant::now();
+			renderer.draw(&mut target, &calibration_scene, materials, true);
+			// Wait for the GPU, otherwise only command submission would be measured
+			display.finish();
+			Some(Instant::now().duration_since(start))
+		});
+
+		match result {
+			Some(result) => {
+				self.set_batch_size(result.batch_size).ok()?;
+				Some(result)
+			}
+			None => {
+				self.set_batch_size(original_batch_size).ok()?;
+				None
+			}
+		}
+	}
 
-		let uniforms = uniform! {
-			u_scale: scale,
-			u_translation: view_rect.center(),
+	/// Draw scenes into an offscreen image of the given size, without post-processing or overlay
+	pub fn capture(&mut self, scenes: &[&Scene], (width, height): (u32, u32)) -> Option<RgbaImage> {
+		let color = Texture2d::empty(&self.display, width, height).ok()?;
+		let depth = DepthRenderBuffer::new(&self.display, DepthFormat::I24, width, height).ok()?;
+		{
+			let mut target = SimpleFrameBuffer::with_depth_buffer(&self.display, &color, &depth).ok()?;
+			self.renderer.draw_all(&mut target, scenes, &self.materials);
+		}
 
-			u_view_origin: scene.view_origin(),
-			u_view_distance: scene.view_distance(),
-			u_view_sharpness: scene.view_sharpness(),
+		let pixels: RawImage2d<u8> = color.read();
+		let image = RgbaImage::from_raw(pixels.width, pixels.height, pixels.data.into_owned())?;
+		// OpenGL rows start at the bottom
+		Some(image::imageops::flip_vertical(&image))
+	}
 
-			u_texture: scene.texture(),
-		};
+	/// Current mapping between the scene and the window
+	pub fn viewport(&self) -> Viewport {
+		Viewport::new(&self.render_settings, self.display.get_framebuffer_dimensions())
+	}
+
+	/// Overlay drawn on top of the next frame, it is cleared after every draw
+	pub fn overlay(&mut self) -> &mut Overlay {
+		&mut self.overlay
+	}
+
+	pub fn draw<T: Scene>(&mut self, scene: &T) {
+		self.draw_scenes(&[scene as &Scene]);
+	}
+
+	/// Draw scenes on top of each other, only the first one draws its background
+	pub fn draw_scenes(&mut self, scenes: &[&Scene]) {
+		profiler::max_counter(
+			profiler::COUNTER_INSTANCE_BUFFER_SIZE,
+			self.renderer.instance_buffer.get_size() as u64,
+		);
 
 		let mut target = self.display.draw();
-		target.clear_color_and_depth((0.0, 0.0, 1.0, 1.0), 1.0);
+		let viewport = Viewport::new(&self.render_settings, target.get_dimensions());
 
-		// Pass 0: objects
-		{
-			for chunk in scene.object_instances().chunks(self.batch_size) {
-				{
-					let mut mapping = self.instance_buffer.map();
-					for (object, instance) in chunk.iter().zip(mapping.iter_mut()) {
-						*instance = object.clone().into();
-					}
-				}
-				target
-					.draw(
-						(
-							&self.quad_vertices,
-							self.instance_buffer
-								.slice(..chunk.len())
-								.unwrap()
-								.per_instance()
-								.unwrap(),
-						),
-						&self.quad_indices,
-						&self.program,
-						&uniforms,
-						&params,
-					).unwrap();
+		if self.post_processing.is_enabled() || !viewport.is_native() {
+			{
+				let mut scene_target = self.post_processing.scene_target(&self.display, viewport.render_size);
+				self.renderer.draw_all(&mut scene_target, scenes, &self.materials);
 			}
+			self.post_processing
+				.present(&self.display, &mut target, &self.materials, &self.renderer.quad, &viewport);
+		} else {
+			self.renderer.draw_all(&mut target, scenes, &self.materials);
 		}
 
+		self.overlay.draw(&self.display, &mut target, &self.materials);
 		target.finish().unwrap();
 	}
 
@@ -127,34 +228,22 @@ impl Graphics {
 		self.display.gl_window()
 	}
 
-	pub fn screen_to_world<T: Scene>(&self, normalized_screen: super::math::Point, scene: &T) -> super::math::Point {
-		let mut width_to_height = 1.0f32;
-		if let Some(size) = self.display.gl_window().get_inner_size() {
-			width_to_height = size.width as f32 / size.height as f32;
-		}
-		let view_rect = scene.view_rect();
-		let scale = if scene.preserve_ratio() {
-			// Calculate the necessary scaling
-			let mut scaling = 1.0 / view_rect.width();
-			let y_scaling = 1.0 / width_to_height / view_rect.height();
-
-			if scaling > y_scaling {
-				scaling = y_scaling;
-			}
+	/// Convert normalized window coordinates (y pointing down) into world coordinates
+	pub fn screen_to_world<T: Scene + ?Sized>(&self, normalized_screen: Vec2, scene: &T) -> Vec2 {
+		let viewport = self.viewport();
+		let normalized_screen = viewport.window_to_scene(normalized_screen);
+		let width_to_height = viewport.render_size.0 as f32 / viewport.render_size.1 as f32;
 
-			[scaling, width_to_height * scaling]
-		} else {
-			[1.0 / view_rect.width(), 1.0 / view_rect.height()]
-		};
-
-		[normalized_screen[0] / scale[0] + view_rect.min_x(), (1.0 - normalized_screen[1]) / scale[1] + view_rect.min_y()]
+		let camera = Vec2::new(normalized_screen.x * 2.0 - 1.0, 1.0 - normalized_screen.y * 2.0);
+		let camera_inverse = camera_matrix(scene, width_to_height).inverse().unwrap_or_default();
+		camera_inverse.transform_point(camera)
 	}
 }
 
 #[derive(Debug)]
 pub enum GraphicsCreationError {
-	Io(IoError),                   // Something went wrong trying to load shader files
-	Program(ProgramCreationError), // Something went wrong trying to compile shaders
+	Material(MaterialError), // Something went wrong trying to load the default material or its shaders
+	Text(TextError), // Something went wrong trying to rasterize the overlay font
 	VertexBuffer(VertexBufferCreationError), // Something went wrong trying to generate vertices for the quad
 	IndexBuffer(IndexBufferCreationError), // Something went wrong trying to generate indices for the quad
 }
@@ -162,12 +251,12 @@ pub enum GraphicsCreationError {
 impl std::fmt::Display for GraphicsCreationError {
 	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 		match self {
-			GraphicsCreationError::Io(error) => {
-				write!(f, "(IO)");
+			GraphicsCreationError::Material(error) => {
+				write!(f, "(Material)");
 				error.fmt(f)
 			}
-			GraphicsCreationError::Program(error) => {
-				write!(f, "(Program)");
+			GraphicsCreationError::Text(error) => {
+				write!(f, "(Text)");
 				error.fmt(f)
 			}
 			GraphicsCreationError::VertexBuffer(error) => {
@@ -189,23 +278,23 @@ impl std::error::Error for GraphicsCreationError {
 
 	fn cause(&self) -> Option<&std::error::Error> {
 		match self {
-			GraphicsCreationError::Io(error) => Some(error),
-			GraphicsCreationError::Program(error) => Some(error),
+			GraphicsCreationError::Material(error) => Some(error),
+			GraphicsCreationError::Text(error) => Some(error),
 			GraphicsCreationError::VertexBuffer(error) => Some(error),
 			GraphicsCreationError::IndexBuffer(error) => Some(error),
 		}
 	}
 }
 
-impl From<IoError> for GraphicsCreationError {
-	fn from(error: IoError) -> Self {
-		GraphicsCreationError::Io(error)
+impl From<MaterialError> for GraphicsCreationError {
+	fn from(error: MaterialError) -> Self {
+		GraphicsCreationError::Material(error)
 	}
 }
 
-impl From<ProgramCreationError> for GraphicsCreationError {
-	fn from(error: ProgramCreationError) -> Self {
-		GraphicsCreationError::Program(error)
+impl From<TextError> for GraphicsCreationError {
+	fn from(error: TextError) -> Self {
+		GraphicsCreationError::Text(error)
 	}
 }
 
@@ -228,31 +317,226 @@ pub struct Vertex {
 }
 implement_vertex!(Vertex, position, tex_coords);
 
-fn load_program<F>(facade: &F, shader_name: &str) -> Result<Program, GraphicsCreationError>
-where
-	F: glium::backend::Facade,
-{
-	let mut path = String::from(SHADER_PREFIX);
-	path.push_str(shader_name);
-	path.push_str(VERTEX_SHADER_EXTENSHION);
-	let path = Path::new(&path);
+impl SceneRenderer {
+	/// Clear the surface with the scene background
+	fn draw_background<S: Surface, T: Scene + ?Sized>(&self, surface: &mut S, scene: &T, materials: &MaterialCollection) {
+		let (width, height) = surface.get_dimensions();
+
+		// Mode, top color, bottom color, texture area and tile count
+		let (mode, top, bottom, area, tiles) = match scene.background() {
+			Background::Color(color) => {
+				surface.clear_color_and_depth((color[0], color[1], color[2], color[3]), 1.0);
+				return;
+			}
+			Background::VerticalGradient { top, bottom } => (BACKGROUND_GRADIENT, top, bottom, [0.0; 4], [1.0, 1.0]),
+			Background::Texture { texture, fit, color } => match fit {
+				BackgroundFit::Stretch => (BACKGROUND_STRETCH, color, color, texture.area.get_vec4(), [1.0, 1.0]),
+				BackgroundFit::Tile { tile_size } => (
+					BACKGROUND_TILE,
+					color,
+					color,
+					texture.area.get_vec4(),
+					[width as f32 / tile_size.x, height as f32 / tile_size.y],
+				),
+			},
+		};
+
+		// The full-screen quad covers all of the color buffer
+		surface.clear_depth(1.0);
+
+		let background_uniforms = uniform! {
+			u_mode: mode,
+			u_top: top,
+			u_bottom: bottom,
+			u_area: area,
+			u_tiles: tiles,
+			u_texture: scene.texture(),
+		};
+		let uniforms = materials.uniforms(self.background_material, &background_uniforms);
+		surface
+			.draw(
+				&self.quad.vertices,
+				&self.quad.indices,
+				materials.program(self.background_material),
+				&uniforms,
+				&materials.draw_parameters(self.background_material),
+			).unwrap();
+		profiler::add_counter(profiler::COUNTER_DRAW_CALLS, 1);
+	}
+
+	/// Draw scenes on top of each other, the first one clears the surface with its background
+	fn draw_all<S: Surface>(&mut self, surface: &mut S, scenes: &[&Scene], materials: &MaterialCollection) {
+		for (index, scene) in scenes.iter().enumerate() {
+			self.draw(surface, *scene, materials, index == 0);
+		}
+	}
 
-	let vertex_shader = std::fs::read_to_string(path)?;
+	/// Draw background layers and all objects of the scene into a surface
+	///
+	/// Without background only depth is cleared, so the scene is drawn over what is already in the surface.
+	fn draw<S: Surface, T: Scene + ?Sized>(
+		&mut self,
+		surface: &mut S,
+		scene: &T,
+		materials: &MaterialCollection,
+		background: bool,
+	) {
+		let (width, height) = surface.get_dimensions();
+		let view_rect = scene.view_rect();
+		let camera = camera_matrix(scene, width as f32 / height as f32);
+		let camera_inverse = camera.inverse().unwrap_or_default();
 
-	let mut path = String::from(SHADER_PREFIX);
-	path.push_str(shader_name);
-	path.push_str(FRAGMENT_SHADER_EXTENSHION);
-	let path = Path::new(&path);
+		let shared_uniforms = uniform! {
+			u_camera: camera,
 
-	let fragment_shader = std::fs::read_to_string(path)?;
+			u_view_origin: scene.view_origin(),
+			u_view_distance: scene.view_distance(),
+			u_view_sharpness: scene.view_sharpness(),
+
+			u_texture: scene.texture(),
+		};
 
-	let program = Program::from_source(facade, &vertex_shader, &fragment_shader, None)?;
-	Ok(program)
+		if background {
+			self.draw_background(surface, scene, materials);
+		} else {
+			surface.clear_depth(1.0);
+		}
+
+		// Pass 0: parallax layers, back to front and unaffected by lighting
+		{
+			let program = materials.program(self.parallax_material);
+			let params = materials.draw_parameters(self.parallax_material);
+
+			for layer in scene.parallax_layers() {
+				let layer_uniforms = uniform! {
+					u_camera_inverse: camera_inverse,
+					u_translation: view_rect.center(),
+					u_texture: scene.texture(),
+
+					u_area: layer.texture.area.get_vec4(),
+					u_scroll_factor: layer.scroll_factor,
+					u_offset: layer.offset,
+					u_size: layer.size,
+					u_repeat: [
+						if layer.repeat[0] { 1.0f32 } else { 0.0 },
+						if layer.repeat[1] { 1.0f32 } else { 0.0 },
+					],
+					u_color: layer.color,
+				};
+				let uniforms = materials.uniforms(self.parallax_material, &layer_uniforms);
+				surface
+					.draw(&self.quad.vertices, &self.quad.indices, program, &uniforms, &params)
+					.unwrap();
+				profiler::add_counter(profiler::COUNTER_DRAW_CALLS, 1);
+			}
+		}
+
+		// Objects entirely outside of the visible area are skipped
+		let visible_area = Rect::new(
+			camera_inverse.transform_point(Vec2::new(-1.0, -1.0)),
+			camera_inverse.transform_point(Vec2::new(1.0, 1.0)),
+		);
+		let mut culled = 0;
+
+		// Pass 1: objects, batched by material
+		for material in materials.handles() {
+			let program = materials.program(material);
+			let params = materials.draw_parameters(material);
+			let uniforms = materials.uniforms(material, &shared_uniforms);
+
+			let mut objects = scene
+				.object_instances()
+				.iter()
+				.filter(|object| object.material == material)
+				.filter(|object| {
+					let visible = is_visible(object, &visible_area);
+					if !visible {
+						culled += 1;
+					}
+					visible
+				}).peekable();
+
+			while objects.peek().is_some() {
+				let mut count = 0;
+				{
+					let _scope = profiler::scope(profiler::SCOPE_UPLOAD);
+					let mut mapping = self.instance_buffer.map();
+					for (instance, object) in mapping.iter_mut().zip(&mut objects) {
+						*instance = object.clone().into();
+						count += 1;
+					}
+				}
+				profiler::add_counter(profiler::COUNTER_DRAW_CALLS, 1);
+				profiler::add_counter(profiler::COUNTER_INSTANCES_SUBMITTED, count as u64);
+				profiler::max_counter(
+					profiler::COUNTER_INSTANCE_BUFFER_USED,
+					(count * std::mem::size_of::<PerInstance>()) as u64,
+				);
+				surface
+					.draw(
+						(
+							&self.quad.vertices,
+							self.instance_buffer
+								.slice(..count)
+								.unwrap()
+								.per_instance()
+								.unwrap(),
+						),
+						&self.quad.indices,
+						program,
+						&uniforms,
+						&params,
+					).unwrap();
+			}
+		}
+
+		profiler::add_counter(profiler::COUNTER_INSTANCES_CULLED, culled);
+	}
 }
 
-fn generate_quad<F>(
-	facade: &F,
-) -> Result<(VertexBuffer<Vertex>, IndexBuffer<u16>), GraphicsCreationError>
+/// Transformation from world space into camera space, the view rect is centered and fills [-1, 1]
+///
+/// With a preserved ratio the view rect is fit into the surface and more of the world is visible along one axis.
+fn camera_matrix<T: Scene + ?Sized>(scene: &T, width_to_height: f32) -> Mat3 {
+	let view_rect = scene.view_rect();
+	let scale = if scene.preserve_ratio() {
+		// Calculate the necessary scaling
+		let mut scaling = 2.0 / view_rect.width();
+		let y_scaling = 2.0 / width_to_height / view_rect.height();
+
+		if scaling > y_scaling {
+			scaling = y_scaling;
+		}
+
+		Vec2::new(scaling, width_to_height * scaling)
+	} else {
+		Vec2::new(2.0 / view_rect.width(), 2.0 / view_rect.height())
+	};
+
+	Mat3::scale(scale) * Mat3::translation(-view_rect.center())
+}
+
+/// Does the bounding circle of an object overlap an area
+fn is_visible(object: &Instance, area: &Rect) -> bool {
+	let position = object.transform.translation;
+	// The quad spans [-0.5, 0.5] before scaling
+	let radius = 0.5 * object.transform.scale.length();
+
+	Rect::new(position, position).expand(radius).intersects(area)
+}
+
+/// Rasterize the configured font, or the default font if none is configured or it can't be loaded
+fn load_overlay_font(display: &Display, font: &str) -> Result<GlyphAtlas, TextError> {
+	if !font.is_empty() && font != DEFAULT_FONT {
+		match GlyphAtlas::new(display, font, OVERLAY_FONT_SIZE) {
+			Ok(atlas) => return Ok(atlas),
+			Err(error) => println!("Failed to load font {}, using {} instead: {}", font, DEFAULT_FONT, error),
+		}
+	}
+	GlyphAtlas::new(display, DEFAULT_FONT, OVERLAY_FONT_SIZE)
+}
+
+fn generate_quad<F>(facade: &F) -> Result<Quad, GraphicsCreationError>
 where
 	F: glium::backend::Facade,
 {
@@ -283,7 +567,10 @@ where
 		&[0, 1, 2, 1, 2, 3],
 	)?;
 
-	Ok((vertex_buffer, index_buffer))
+	Ok(Quad {
+		vertices: vertex_buffer,
+		indices: index_buffer,
+	})
 }
 
 fn generate_instance_buffer<F: glium::backend::Facade>(
@@ -1,5 +1,6 @@
 // An instance of a renderable object
 
+use super::material::Material;
 use super::math::Lerp;
 use super::texture::Texture;
 use super::transform::Transform;
@@ -11,6 +12,85 @@ pub struct Instance {
 	pub color_unlit: [f32; 4],
 	pub texture_lit: Texture,
 	pub texture_unlit: Texture,
+	pub material: Material,
+	pub effects: SpriteEffects,
+}
+
+/// Rotation of texture coordinates in counter-clockwise quarter turns
+///
+/// Unlike Transform rotation this only affects the texture, used for rotated atlas packing and tile variants.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UvRotation {
+	None,
+	Quarter,
+	Half,
+	ThreeQuarters,
+}
+
+/// How the tint color is applied on top of the textured color
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TintMode {
+	Multiply, // Multiply the color by tint
+	Additive, // Add tint color weighted by its alpha, used for flashes
+	Replace,  // Blend towards tint color by its alpha keeping the original alpha, used for hit feedback
+}
+
+/// Per-instance texture coordinate and color effects
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpriteEffects {
+	pub flip_x: bool,
+	pub flip_y: bool,
+	pub uv_rotation: UvRotation,
+	pub tint: [f32; 4],
+	pub tint_mode: TintMode,
+}
+
+impl Default for SpriteEffects {
+	fn default() -> Self {
+		Self {
+			flip_x: false,
+			flip_y: false,
+			uv_rotation: UvRotation::None,
+			tint: [1.0, 1.0, 1.0, 1.0],
+			tint_mode: TintMode::Multiply,
+		}
+	}
+}
+
+impl SpriteEffects {
+	/// Encode into a vector of (x scale, y scale, quarter turns, tint mode) for the shader
+	fn uv_transform(&self) -> [f32; 4] {
+		let quarter_turns = match self.uv_rotation {
+			UvRotation::None => 0.0,
+			UvRotation::Quarter => 1.0,
+			UvRotation::Half => 2.0,
+			UvRotation::ThreeQuarters => 3.0,
+		};
+		let tint_mode = match self.tint_mode {
+			TintMode::Multiply => 0.0,
+			TintMode::Additive => 1.0,
+			TintMode::Replace => 2.0,
+		};
+		[
+			if self.flip_x { -1.0 } else { 1.0 },
+			if self.flip_y { -1.0 } else { 1.0 },
+			quarter_turns,
+			tint_mode,
+		]
+	}
+}
+
+impl Lerp for SpriteEffects {
+	fn lerp(a: &Self, b: &Self, t: f32) -> Self {
+		let discrete = if t <= 0.5 { a } else { b };
+		Self {
+			tint: Lerp::lerp(&a.tint, &b.tint, t),
+			..*discrete
+		}
+	}
 }
 
 impl Instance {
@@ -27,6 +107,8 @@ impl Instance {
 			color_unlit,
 			texture_lit: texture_lit.clone(),
 			texture_unlit: texture_unlit.clone(),
+			material: Material::default(),
+			effects: SpriteEffects::default(),
 		}
 	}
 
@@ -37,6 +119,8 @@ impl Instance {
 			color_unlit: color,
 			texture_lit: texture.clone(),
 			texture_unlit: texture.clone(),
+			material: Material::default(),
+			effects: SpriteEffects::default(),
 		}
 	}
 }
@@ -45,12 +129,15 @@ impl Lerp for Instance {
 	fn lerp(a: &Self, b: &Self, t: f32) -> Self {
 		let texture_lit;
 		let texture_unlit;
+		let material;
 		if t <= 0.5 {
 			texture_lit = a.texture_lit.clone();
 			texture_unlit = a.texture_unlit.clone();
+			material = a.material;
 		} else {
 			texture_lit = b.texture_lit.clone();
 			texture_unlit = b.texture_unlit.clone();
+			material = b.material;
 		}
 		Self {
 			transform: Lerp::lerp(&a.transform, &b.transform, t),
@@ -58,6 +145,8 @@ impl Lerp for Instance {
 			color_unlit: Lerp::lerp(&a.color_unlit, &b.color_unlit, t),
 			texture_lit,
 			texture_unlit,
+			material,
+			effects: Lerp::lerp(&a.effects, &b.effects, t),
 		}
 	}
 }
@@ -72,8 +161,10 @@ pub struct PerInstance {
 	pub i_color_unlit: [f32; 4],	// The color of the object when not within vision range
 	pub i_texture_lit: [f32; 4],	// Offsets to texture used when in vision range
 	pub i_texture_unlit: [f32; 4],	// Offsets to texture used when not within vision range
+	pub i_uv_transform: [f32; 4],	// Texture coordinate flips, quarter turns and tint mode
+	pub i_tint: [f32; 4],			// Tint color applied according to tint mode
 }
-implement_vertex!(PerInstance, i_translation, i_z_theta, i_scale, i_color_lit, i_color_unlit, i_texture_lit, i_texture_unlit);
+implement_vertex!(PerInstance, i_translation, i_z_theta, i_scale, i_color_lit, i_color_unlit, i_texture_lit, i_texture_unlit, i_uv_transform, i_tint);
 
 impl Default for PerInstance {
 	fn default() -> Self {
@@ -85,6 +176,8 @@ impl Default for PerInstance {
 			i_color_unlit: [0.5, 0.5, 0.5, 1.0],
 			i_texture_lit: [0.0, 0.0, 0.0, 0.0],
 			i_texture_unlit: [0.0, 0.0, 0.0, 0.0],
+			i_uv_transform: [1.0, 1.0, 0.0, 0.0],
+			i_tint: [1.0, 1.0, 1.0, 1.0],
 		}
 	}
 }
@@ -92,13 +185,15 @@ impl Default for PerInstance {
 impl From<Instance> for PerInstance {
 	fn from(instance: Instance) -> Self {
 		Self {
-			i_translation: instance.transform.translation,
-			i_z_theta: [0.5, instance.transform.rotation],
-			i_scale: instance.transform.scale,
+			i_translation: instance.transform.translation.into(),
+			i_z_theta: [0.5, instance.transform.rotation.as_radians()],
+			i_scale: instance.transform.scale.into(),
 			i_color_lit: instance.color_lit,
 			i_color_unlit: instance.color_unlit,
 			i_texture_lit: instance.texture_lit.area.get_vec4(),
 			i_texture_unlit: instance.texture_unlit.area.get_vec4(),
+			i_uv_transform: instance.effects.uv_transform(),
+			i_tint: instance.effects.tint,
 		}
 	}
 }
@@ -0,0 +1,429 @@
+// Materials describe how a batch of instances gets drawn
+//
+// A material names a shader pair, blending and depth behaviour and a set of typed custom uniforms.
+// They are loaded from YAML files and referenced by instances through lightweight handles.
+
+use MATERIAL_PREFIX;
+use TEXTURE_PREFIX;
+
+use super::shader::{load_program, ShaderDefines, ShaderError, ShaderWatcher};
+
+use glium::texture::{RawImage2d, Texture2d, TextureCreationError};
+use glium::uniforms::{AsUniformValue, UniformValue, Uniforms};
+use glium::Program;
+
+use image::ImageError;
+
+use std::collections::HashMap as Map;
+use std::io::Error as IoError;
+use std::path::Path;
+
+pub type MaterialID = String;
+
+pub const DEFAULT_MATERIAL: &str = "default";
+pub const MATERIAL_EXTENSION: &str = ".yml";
+
+/// Handle to a loaded material
+///
+/// The default handle refers to DEFAULT_MATERIAL, which is always loaded first.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Material {
+	index: usize,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+	None,     // Overwrite the target
+	Alpha,    // Ordinary transparency
+	Additive, // Add color on top of the target, useful for glows
+	Multiply, // Multiply target color, useful for shadows
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DepthTest {
+	Always,
+	Never,
+	Less,
+	LessOrEqual,
+	Greater,
+	GreaterOrEqual,
+	Equal,
+	NotEqual,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+pub struct DepthMode {
+	#[serde(default = "default_depth_test")]
+	pub test: DepthTest,
+	#[serde(default = "default_depth_write")]
+	pub write: bool,
+}
+
+fn default_blend_mode() -> BlendMode {
+	BlendMode::Alpha
+}
+
+fn default_depth_test() -> DepthTest {
+	DepthTest::Less
+}
+
+fn default_depth_write() -> bool {
+	true
+}
+
+impl Default for DepthMode {
+	fn default() -> Self {
+		Self {
+			test: default_depth_test(),
+			write: default_depth_write(),
+		}
+	}
+}
+
+/// Value of a custom uniform as written in a material file
+///
+/// Numbers become floats, lists of 2 to 4 numbers become vectors and strings name a texture file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum UniformDescription {
+	Float(f32),
+	Vec2([f32; 2]),
+	Vec3([f32; 3]),
+	Vec4([f32; 4]),
+	Texture(String),
+}
+
+/// Contents of a material file
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MaterialDescription {
+	pub shader: String, // Name of the vertex-fragment shader pair
+	#[serde(default = "default_blend_mode")]
+	pub blend: BlendMode,
+	#[serde(default)]
+	pub depth: DepthMode,
+	#[serde(default)]
+	pub uniforms: Map<String, UniformDescription>,
+}
+
+#[derive(Debug)]
+pub enum MaterialError {
+	Io(IoError),                   // Something went wrong trying to read a material file
+	Yaml(serde_yaml::Error),       // Material file is not a valid description
+	Shader(ShaderError),           // Something went wrong trying to load the shader pair
+	Image(ImageError),             // Something went wrong trying to load a texture uniform
+	Texture(TextureCreationError), // Failed to upload a texture uniform to the GPU
+}
+
+impl std::fmt::Display for MaterialError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			MaterialError::Io(error) => write!(f, "(IO) {}", error),
+			MaterialError::Yaml(error) => write!(f, "(Yaml) {}", error),
+			MaterialError::Shader(error) => write!(f, "(Shader) {}", error),
+			MaterialError::Image(error) => write!(f, "(Image) {}", error),
+			MaterialError::Texture(error) => write!(f, "(Texture) {}", error),
+		}
+	}
+}
+
+impl std::error::Error for MaterialError {
+	fn description(&self) -> &str {
+		"Failed to load a material."
+	}
+
+	fn cause(&self) -> Option<&std::error::Error> {
+		match self {
+			MaterialError::Io(error) => Some(error),
+			MaterialError::Yaml(error) => Some(error),
+			MaterialError::Shader(error) => Some(error),
+			MaterialError::Image(error) => Some(error),
+			MaterialError::Texture(error) => Some(error),
+		}
+	}
+}
+
+impl From<IoError> for MaterialError {
+	fn from(error: IoError) -> Self {
+		MaterialError::Io(error)
+	}
+}
+
+impl From<serde_yaml::Error> for MaterialError {
+	fn from(error: serde_yaml::Error) -> Self {
+		MaterialError::Yaml(error)
+	}
+}
+
+impl From<ShaderError> for MaterialError {
+	fn from(error: ShaderError) -> Self {
+		MaterialError::Shader(error)
+	}
+}
+
+impl From<ImageError> for MaterialError {
+	fn from(error: ImageError) -> Self {
+		// Don't nest IoErrors, unwrap one here
+		if let ImageError::IoError(error) = error {
+			MaterialError::Io(error)
+		} else {
+			MaterialError::Image(error)
+		}
+	}
+}
+
+impl From<TextureCreationError> for MaterialError {
+	fn from(error: TextureCreationError) -> Self {
+		MaterialError::Texture(error)
+	}
+}
+
+/// Custom uniform value ready to be used for drawing
+enum MaterialUniform {
+	Float(f32),
+	Vec2([f32; 2]),
+	Vec3([f32; 3]),
+	Vec4([f32; 4]),
+	Texture(Texture2d),
+}
+
+impl AsUniformValue for MaterialUniform {
+	fn as_uniform_value(&self) -> UniformValue {
+		match self {
+			MaterialUniform::Float(value) => UniformValue::Float(*value),
+			MaterialUniform::Vec2(value) => UniformValue::Vec2(*value),
+			MaterialUniform::Vec3(value) => UniformValue::Vec3(*value),
+			MaterialUniform::Vec4(value) => UniformValue::Vec4(*value),
+			MaterialUniform::Texture(texture) => UniformValue::Texture2d(texture, None),
+		}
+	}
+}
+
+/// A loaded material
+struct MaterialData {
+	shader: String,
+	blend: BlendMode,
+	depth: DepthMode,
+	uniforms: Vec<(String, MaterialUniform)>,
+}
+
+impl MaterialData {
+	fn draw_parameters(&self) -> glium::DrawParameters<'static> {
+		use glium::{Blend, BlendingFunction, LinearBlendingFactor};
+
+		let blend = match self.blend {
+			BlendMode::None => Blend::default(),
+			BlendMode::Alpha => Blend::alpha_blending(),
+			BlendMode::Additive => {
+				let function = BlendingFunction::Addition {
+					source: LinearBlendingFactor::SourceAlpha,
+					destination: LinearBlendingFactor::One,
+				};
+				Blend {
+					color: function,
+					alpha: function,
+					constant_value: (0.0, 0.0, 0.0, 0.0),
+				}
+			}
+			BlendMode::Multiply => {
+				let function = BlendingFunction::Addition {
+					source: LinearBlendingFactor::DestinationColor,
+					destination: LinearBlendingFactor::Zero,
+				};
+				Blend {
+					color: function,
+					alpha: function,
+					constant_value: (0.0, 0.0, 0.0, 0.0),
+				}
+			}
+		};
+
+		let test = match self.depth.test {
+			DepthTest::Always => glium::DepthTest::Overwrite,
+			DepthTest::Never => glium::DepthTest::Ignore,
+			DepthTest::Less => glium::DepthTest::IfLess,
+			DepthTest::LessOrEqual => glium::DepthTest::IfLessOrEqual,
+			DepthTest::Greater => glium::DepthTest::IfMore,
+			DepthTest::GreaterOrEqual => glium::DepthTest::IfMoreOrEqual,
+			DepthTest::Equal => glium::DepthTest::IfEqual,
+			DepthTest::NotEqual => glium::DepthTest::IfNotEqual,
+		};
+
+		glium::DrawParameters {
+			depth: glium::Depth {
+				test: test,
+				write: self.depth.write,
+				..Default::default()
+			},
+			blend: blend,
+			..Default::default()
+		}
+	}
+}
+
+/// Uniforms shared by all materials extended with custom uniforms of a single material
+pub struct MaterialUniforms<'a, U: 'a + Uniforms> {
+	base: &'a U,
+	material: &'a MaterialData,
+}
+
+impl<'b, U: Uniforms> Uniforms for MaterialUniforms<'b, U> {
+	fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut output: F) {
+		self.base.visit_values(&mut output);
+		for (name, value) in &self.material.uniforms {
+			output(name, value.as_uniform_value());
+		}
+	}
+}
+
+/// All loaded materials and the shader programs they use
+pub struct MaterialCollection {
+	ids: Map<MaterialID, Material>,
+	materials: Vec<MaterialData>,
+	programs: Map<String, Program>,
+	shader_defines: ShaderDefines,         // definitions every program is compiled with
+	shader_watcher: Option<ShaderWatcher>, // only present when hot reloading is enabled
+}
+
+impl MaterialCollection {
+	/// Create a collection with DEFAULT_MATERIAL loaded
+	pub fn new<F>(facade: &F, shader_defines: ShaderDefines, watch_shaders: bool) -> Result<Self, MaterialError>
+	where
+		F: glium::backend::Facade,
+	{
+		let mut collection = Self {
+			ids: Map::new(),
+			materials: Vec::new(),
+			programs: Map::new(),
+			shader_defines: shader_defines,
+			shader_watcher: if watch_shaders {
+				Some(ShaderWatcher::new())
+			} else {
+				None
+			},
+		};
+		collection.load(facade, DEFAULT_MATERIAL)?;
+
+		Ok(collection)
+	}
+
+	/// Load a material from MATERIAL_PREFIX, does nothing if it is already loaded
+	pub fn load<F>(&mut self, facade: &F, id: &str) -> Result<Material, MaterialError>
+	where
+		F: glium::backend::Facade,
+	{
+		if let Some(material) = self.get(id) {
+			return Ok(material);
+		}
+
+		let path = String::from(MATERIAL_PREFIX) + id + MATERIAL_EXTENSION;
+		let file = std::fs::File::open(Path::new(&path))?;
+		let description: MaterialDescription = serde_yaml::from_reader(file)?;
+
+		if !self.programs.contains_key(&description.shader) {
+			let (program, files) = load_program(facade, &description.shader, &self.shader_defines)?;
+			if let Some(ref mut watcher) = self.shader_watcher {
+				watcher.watch(&description.shader, files);
+			}
+			self.programs.insert(description.shader.clone(), program);
+		}
+
+		let mut uniforms = Vec::with_capacity(description.uniforms.len());
+		for (name, value) in description.uniforms {
+			let value = match value {
+				UniformDescription::Float(value) => MaterialUniform::Float(value),
+				UniformDescription::Vec2(value) => MaterialUniform::Vec2(value),
+				UniformDescription::Vec3(value) => MaterialUniform::Vec3(value),
+				UniformDescription::Vec4(value) => MaterialUniform::Vec4(value),
+				UniformDescription::Texture(name) => MaterialUniform::Texture(load_texture(facade, &name)?),
+			};
+			uniforms.push((name, value));
+		}
+
+		let material = Material {
+			index: self.materials.len(),
+		};
+		self.materials.push(MaterialData {
+			shader: description.shader,
+			blend: description.blend,
+			depth: description.depth,
+			uniforms: uniforms,
+		});
+		self.ids.insert(String::from(id), material);
+
+		Ok(material)
+	}
+
+	pub fn get(&self, id: &str) -> Option<Material> {
+		self.ids.get(id).cloned()
+	}
+
+	/// Name a material was loaded with
+	pub fn id(&self, material: Material) -> Option<&str> {
+		self.ids.iter().find(|(_, other)| **other == material).map(|(id, _)| id.as_str())
+	}
+
+	/// Iterate over handles of all loaded materials in load order
+	pub fn handles(&self) -> impl Iterator<Item = Material> {
+		(0..self.materials.len()).map(|index| Material { index })
+	}
+
+	pub fn program(&self, material: Material) -> &Program {
+		&self.programs[&self.materials[material.index].shader]
+	}
+
+	pub fn draw_parameters(&self, material: Material) -> glium::DrawParameters<'static> {
+		self.materials[material.index].draw_parameters()
+	}
+
+	/// Extend shared uniforms with custom uniforms of a given material
+	pub fn uniforms<'a, U: Uniforms>(&'a self, material: Material, base: &'a U) -> MaterialUniforms<'a, U> {
+		MaterialUniforms {
+			base: base,
+			material: &self.materials[material.index],
+		}
+	}
+
+	/// Recompile shaders whose files were modified since the last call
+	///
+	/// Only works if shader watching was enabled. On failure the old program is kept and the error is printed.
+	pub fn reload_changed_shaders<F>(&mut self, facade: &F)
+	where
+		F: glium::backend::Facade,
+	{
+		let changed = match self.shader_watcher {
+			Some(ref mut watcher) => watcher.poll(),
+			None => return,
+		};
+
+		for name in changed {
+			match load_program(facade, &name, &self.shader_defines) {
+				Ok((program, files)) => {
+					self.programs.insert(name.clone(), program);
+					if let Some(ref mut watcher) = self.shader_watcher {
+						watcher.watch(&name, files);
+					}
+					println!("Reloaded shader {:?}", name);
+				}
+				Err(error) => {
+					println!("Failed to reload shader {:?}, keeping the old one:", name);
+					println!("{}", error);
+				}
+			}
+		}
+	}
+}
+
+/// Load a standalone texture to be used as a custom uniform
+fn load_texture<F>(facade: &F, name: &str) -> Result<Texture2d, MaterialError>
+where
+	F: glium::backend::Facade,
+{
+	let path = String::from(TEXTURE_PREFIX) + name;
+	let image = image::open(Path::new(&path))?.to_rgba();
+	let dimensions = image.dimensions();
+	let image = RawImage2d::from_raw_rgba_reversed(&image.into_raw(), dimensions);
+
+	Ok(Texture2d::new(facade, image)?)
+}
@@ -1,33 +1,38 @@
 // Helper functions used in graphics module
+//
+// Positions, sizes and rectangles are Vec2 based. Colors, texture areas and vertex attributes stay plain arrays,
+// as they are handed to shaders as is.
 pub use std::f32::consts::PI;
 
+use super::angle::Angle;
+use super::vector::Vec2;
+
 pub const MAX_ROTATION: f32 = PI * 2.0;
 
-pub type Point = [f32; 2];
-pub type Bounds = Vec<Point>;
+pub type Bounds = Vec<Vec2>;
 
 /// Linearly interpolateable
 pub trait Lerp {
 	fn lerp(a: &Self, b: &Self, t: f32) -> Self;
 }
 
-/// Can be converted to a set of Points
+/// Can be converted to a set of points
 pub trait Boundable {
 	fn bounds(&self) -> Bounds;
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Rect {
-	min: Point,
-	max: Point,
+	min: Vec2,
+	max: Vec2,
 }
 
+/// Shortest signed arc in radians from a to b, within [-π, π), see Angle::distance_to()
 pub fn short_angle_distance(a: f32, b: f32) -> f32 {
-	let delta = (b - a) % MAX_ROTATION;
-
-	(2.0 * delta) % MAX_ROTATION - delta
+	Angle::from_radians(a).distance_to(Angle::from_radians(b)).as_radians()
 }
 
+/// Interpolate radians along the shortest arc
 pub fn lerp_angle(a: f32, b: f32, t: f32) -> f32 {
 	a + short_angle_distance(a, b) * t
 }
@@ -35,30 +40,19 @@ pub fn lerp_angle(a: f32, b: f32, t: f32) -> f32 {
 impl Rect {
 	/// Create a new rectangle from two points, no checking is done to make sure min is actually less than max
 	/// 
-	/// To generate a rectangle that contains two arbitrary points use bounds()
-	pub fn new(min: Point, max: Point) -> Self {
+	/// To generate a rectangle that contains two arbitrary points use from_bounds() or normalized()
+	pub fn new(min: Vec2, max: Vec2) -> Self {
 		Self {min: min, max: max}
 	}
 
 	/// Create a smallest rectangle that includes given points
 	pub fn from_bounds(points: &Bounds) -> Self {
-		let mut min = [std::f32::MAX, std::f32::MAX];
-		let mut max = [std::f32::MIN, std::f32::MIN];
+		let mut min = Vec2::splat(std::f32::MAX);
+		let mut max = Vec2::splat(std::f32::MIN);
 
 		for point in points {
-			if min[0] > point[0] {
-				min[0] = point[0];
-			}
-			if min[1] > point[1] {
-				min[1] = point[1];
-			}
-
-			if max[0] < point[0] {
-				max[0] = point[0];
-			}
-			if max[1] < point[1] {
-				max[1] = point[1];
-			}
+			min = min.min(*point);
+			max = max.max(*point);
 		}
 
 		Self {min: min, max: max}
@@ -66,67 +60,134 @@ impl Rect {
 
 	/// Non-consuming transformation into [f32; 4]
 	pub fn get_vec4(&self) -> [f32; 4] {
-		[self.min[0], self.min[1], self.max[0], self.max[1]]
+		[self.min.x, self.min.y, self.max.x, self.max.y]
 	}
 
-	pub fn grow_to_fit(&mut self, point: &Point) {
-		if self.min[0] > point[0] {
-			self.min[0] = point[0];
-		}
-		if self.min[1] > point[1] {
-			self.min[1] = point[1];
-		}
-
-		if self.max[0] < point[0] {
-			self.max[0] = point[0];
-		}
-		if self.max[1] < point[1] {
-			self.max[1] = point[1];
-		}
+	pub fn grow_to_fit(&mut self, point: Vec2) {
+		self.min = self.min.min(point);
+		self.max = self.max.max(point);
 	}
 
-	pub fn min(&self) -> [f32; 2] {
+	pub fn min(&self) -> Vec2 {
 		self.min
 	}
 
 	pub fn min_x(&self) -> f32 {
-		self.min[0]
+		self.min.x
 	}
 
 	pub fn min_y(&self) -> f32 {
-		self.min[1]
+		self.min.y
 	}
 
-	pub fn max(&self) -> [f32; 2] {
+	pub fn max(&self) -> Vec2 {
 		self.max
 	}
 
 	pub fn max_x(&self) -> f32 {
-		self.max[0]
+		self.max.x
 	}
 
 	pub fn max_y(&self) -> f32 {
-		self.max[1]
+		self.max.y
 	}
 
 	pub fn width(&self) -> f32 {
-		self.max[0] - self.min[0]
+		self.max.x - self.min.x
 	}
 
 	pub fn height(&self) -> f32 {
-		self.max[1] - self.min[1]
+		self.max.y - self.min.y
 	}
 
-	pub fn center(&self) -> Point {
-		[
-			(self.min[0] + self.max[0]) / 2.0,
-			(self.min[1] + self.max[1]) / 2.0,
-		]
+	pub fn center(&self) -> Vec2 {
+		(self.min + self.max) / 2.0
+	}
+
+	/// Returns a vector where x = width and y = height
+	pub fn size(&self) -> Vec2 {
+		self.max - self.min
+	}
+
+	/// Width divided by height
+	pub fn aspect_ratio(&self) -> f32 {
+		self.width() / self.height()
+	}
+
+	/// The same rectangle with min and max swapped along axes where min is greater than max
+	pub fn normalized(&self) -> Self {
+		Self {min: self.min.min(self.max), max: self.min.max(self.max)}
+	}
+
+	/// Is the point inside the rectangle or on its edge
+	pub fn contains_point(&self, point: Vec2) -> bool {
+		point.x >= self.min.x && point.x <= self.max.x && point.y >= self.min.y && point.y <= self.max.y
+	}
+
+	/// Is the other rectangle entirely inside this one
+	pub fn contains(&self, other: &Rect) -> bool {
+		self.contains_point(other.min) && self.contains_point(other.max)
+	}
+
+	/// Do the rectangles overlap, touching edges count
+	pub fn intersects(&self, other: &Rect) -> bool {
+		self.min.x <= other.max.x
+			&& self.max.x >= other.min.x
+			&& self.min.y <= other.max.y
+			&& self.max.y >= other.min.y
+	}
+
+	/// The area covered by both rectangles, None if they don't overlap
+	pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+		if !self.intersects(other) {
+			return None;
+		}
+		Some(Self {min: self.min.max(other.min), max: self.max.min(other.max)})
+	}
+
+	/// Smallest rectangle containing both rectangles
+	pub fn union(&self, other: &Rect) -> Rect {
+		Self {min: self.min.min(other.min), max: self.max.max(other.max)}
 	}
 
-	/// Returns a point where x = width and y = height
-	pub fn size(&self) -> Point {
-		[self.max[0] - self.min[0], self.max[1] - self.min[1]]
+	/// Move every edge outwards by a margin
+	pub fn expand(&self, margin: f32) -> Rect {
+		let margin = Vec2::splat(margin);
+		Self {min: self.min - margin, max: self.max + margin}
+	}
+
+	/// Move every edge inwards by a margin, a rectangle smaller than twice the margin collapses into its center
+	pub fn shrink(&self, margin: f32) -> Rect {
+		let center = self.center();
+		let margin = Vec2::splat(margin);
+		Self {min: (self.min + margin).min(center), max: (self.max - margin).max(center)}
+	}
+
+	pub fn translate(&self, delta: Vec2) -> Rect {
+		Self {min: self.min + delta, max: self.max + delta}
+	}
+
+	/// Largest rectangle with the given width to height ratio that fits inside, centered
+	pub fn aspect_fit(&self, aspect_ratio: f32) -> Rect {
+		if self.aspect_ratio() > aspect_ratio {
+			self.with_size_around_center(Vec2::new(self.height() * aspect_ratio, self.height()))
+		} else {
+			self.with_size_around_center(Vec2::new(self.width(), self.width() / aspect_ratio))
+		}
+	}
+
+	/// Smallest rectangle with the given width to height ratio that covers this one, centered
+	pub fn aspect_fill(&self, aspect_ratio: f32) -> Rect {
+		if self.aspect_ratio() > aspect_ratio {
+			self.with_size_around_center(Vec2::new(self.width(), self.width() / aspect_ratio))
+		} else {
+			self.with_size_around_center(Vec2::new(self.height() * aspect_ratio, self.height()))
+		}
+	}
+
+	fn with_size_around_center(&self, size: Vec2) -> Rect {
+		let half_size = size / 2.0;
+		Self {min: self.center() - half_size, max: self.center() + half_size}
 	}
 }
 
@@ -180,4 +241,69 @@ impl Lerp for [f32; 4] {
 			a[3] * mt + b[3] * t,
 		]
 	}
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rect(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Rect {
+		Rect::new(Vec2::new(min_x, min_y), Vec2::new(max_x, max_y))
+	}
+
+	#[test]
+	fn normalized() {
+		let expected = rect(0.0, 0.0, 4.0, 2.0);
+		assert_eq!(rect(4.0, 0.0, 0.0, 2.0).normalized(), expected);
+		assert_eq!(rect(0.0, 2.0, 4.0, 0.0).normalized(), expected);
+		assert_eq!(rect(4.0, 2.0, 0.0, 0.0).normalized(), expected);
+		assert_eq!(expected.normalized(), expected);
+	}
+
+	#[test]
+	fn intersection() {
+		let a = rect(0.0, 0.0, 4.0, 2.0);
+		assert_eq!(a.intersection(&rect(2.0, 1.0, 5.0, 5.0)), Some(rect(2.0, 1.0, 4.0, 2.0)));
+		assert_eq!(a.intersection(&rect(1.0, 0.5, 2.0, 1.0)), Some(rect(1.0, 0.5, 2.0, 1.0)));
+		assert_eq!(a.intersection(&rect(5.0, 1.0, 6.0, 5.0)), None);
+		assert_eq!(a.intersection(&rect(1.0, 3.0, 2.0, 4.0)), None);
+
+		// Touching edges give an empty rectangle along them
+		let touching = a.intersection(&rect(4.0, 1.0, 6.0, 5.0)).unwrap();
+		assert_eq!(touching, rect(4.0, 1.0, 4.0, 2.0));
+		assert_eq!(touching.width(), 0.0);
+	}
+
+	#[test]
+	fn shrink() {
+		let a = rect(0.0, 0.0, 4.0, 2.0);
+		assert_eq!(a.shrink(0.5), rect(0.5, 0.5, 3.5, 1.5));
+		// Collapses along y first, then along both axes
+		assert_eq!(a.shrink(1.5), rect(1.5, 1.0, 2.5, 1.0));
+		assert_eq!(a.shrink(5.0), rect(2.0, 1.0, 2.0, 1.0));
+		assert_eq!(a.expand(1.0).shrink(1.0), a);
+	}
+
+	#[test]
+	fn aspect_fit_and_fill() {
+		let wide = rect(0.0, 0.0, 4.0, 2.0);
+		assert_eq!(wide.aspect_fit(1.0), rect(1.0, 0.0, 3.0, 2.0));
+		assert_eq!(wide.aspect_fill(1.0), rect(0.0, -1.0, 4.0, 3.0));
+		assert_eq!(wide.aspect_fit(4.0), rect(0.0, 0.5, 4.0, 1.5));
+		assert_eq!(wide.aspect_fill(4.0), rect(-2.0, 0.0, 6.0, 2.0));
+		assert_eq!(wide.aspect_fit(2.0), wide);
+		assert_eq!(wide.aspect_fill(2.0), wide);
+
+		let tall = rect(0.0, 0.0, 2.0, 4.0);
+		assert_eq!(tall.aspect_fit(1.0), rect(0.0, 1.0, 2.0, 3.0));
+		assert_eq!(tall.aspect_fill(1.0), rect(-1.0, 0.0, 3.0, 4.0));
+		assert_eq!(tall.aspect_fit(0.25), rect(0.5, 0.0, 1.5, 4.0));
+		assert_eq!(tall.aspect_fill(0.25), rect(0.0, -2.0, 2.0, 6.0));
+	}
+
+	#[test]
+	fn union_and_from_bounds() {
+		let a = rect(0.0, 0.0, 4.0, 2.0);
+		assert_eq!(a.union(&rect(-1.0, 1.0, 1.0, 3.0)), rect(-1.0, 0.0, 4.0, 3.0));
+		assert_eq!(Rect::from_bounds(&vec![Vec2::new(1.0, 5.0), Vec2::new(-2.0, 3.0)]), rect(-2.0, 3.0, 1.0, 5.0));
+	}
+}
@@ -1,15 +1,25 @@
 // Module that encopases all accelerated graphical presentation.
 
 pub use self::graphics::Graphics;
+pub use self::material::Material;
 pub use self::texture::TextureCollection;
 
 pub mod graphics;	// Graphical context, core module
 pub mod math;		// Helper functions
+pub mod angle;		// Angle newtype with wrapping and shortest-arc interpolation
+pub mod vector;		// Vec2 and affine Mat3 shared by CPU and shader math
 pub mod texture;	// Smart texture wrapping above glium to allow instancing with different textures
 pub mod instance;	// A drawable object instance
 pub mod transform;	// Transformation of a drawable instance
+pub mod scene_graph;	// Parent-child hierarchy of transforms
 pub mod scene;		// A renderable scene
+pub mod shader;		// Shader loading and hot reloading
+pub mod material;	// Named shader programs with draw parameters and custom uniforms
+pub mod post_processing;	// Full-screen passes over the rendered scene
+pub mod viewport;	// Scene resolution and its placement in the window
+pub mod text;		// Font rasterization into a glyph atlas
+pub mod overlay;	// Screen-space rectangles and text drawn over the frame
+pub mod calibration;	// Picking the fastest batch size for the current driver
 
-pub const INSTANCED_SHADER: &str = "instanced";
 pub const VERTEX_SHADER_EXTENSHION: &str = ".vert";
 pub const FRAGMENT_SHADER_EXTENSHION: &str = ".frag";
\ No newline at end of file
@@ -0,0 +1,128 @@
+// Screen-space debug overlay
+//
+// Rectangles and text are collected during the frame in window pixels (y pointing down)
+// and drawn on top of everything after the scene has been presented.
+
+use super::material::{Material, MaterialCollection, MaterialError};
+use super::math::Rect;
+use super::text::GlyphAtlas;
+use super::vector::Vec2;
+
+use glium::index::{NoIndices, PrimitiveType};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+use glium::{Surface, VertexBuffer};
+
+/// Material used to draw the overlay
+const OVERLAY_MATERIAL: &str = "overlay";
+
+#[derive(Copy, Clone)]
+pub struct OverlayVertex {
+	position: [f32; 2],
+	tex_coords: [f32; 2],
+	color: [f32; 4],
+}
+implement_vertex!(OverlayVertex, position, tex_coords, color);
+
+pub struct Overlay {
+	atlas: GlyphAtlas,
+	material: Material,
+	vertices: Vec<OverlayVertex>, // two triangles per quad, cleared every frame
+}
+
+impl Overlay {
+	pub fn new<F>(facade: &F, materials: &mut MaterialCollection, atlas: GlyphAtlas) -> Result<Self, MaterialError>
+	where
+		F: glium::backend::Facade,
+	{
+		Ok(Self {
+			atlas: atlas,
+			material: materials.load(facade, OVERLAY_MATERIAL)?,
+			vertices: Vec::new(),
+		})
+	}
+
+	pub fn line_height(&self) -> f32 {
+		self.atlas.line_height
+	}
+
+	pub fn text_width(&self, text: &str) -> f32 {
+		self.atlas.text_width(text)
+	}
+
+	/// Add a solid rectangle, position is its top-left corner
+	pub fn rect(&mut self, position: Vec2, size: Vec2, color: [f32; 4]) {
+		let area = self.atlas.solid_area();
+		self.quad(position, size, area, color);
+	}
+
+	/// Add a single line of text, position is its top-left corner
+	///
+	/// Returns the width of the text in pixels.
+	pub fn text(&mut self, position: Vec2, text: &str, color: [f32; 4]) -> f32 {
+		let baseline = position.y + self.atlas.ascent.round();
+		let mut pen = position.x.round();
+
+		for character in text.chars() {
+			let glyph = match self.atlas.glyph(character) {
+				Some(glyph) => *glyph,
+				None => continue,
+			};
+			if glyph.size.x > 0.0 {
+				let corner = Vec2::new(pen, baseline) + glyph.offset;
+				self.quad(corner, glyph.size, glyph.area, color);
+			}
+			pen += glyph.advance;
+		}
+
+		pen - position.x
+	}
+
+	fn quad(&mut self, position: Vec2, size: Vec2, area: Rect, color: [f32; 4]) {
+		let vertex = |x: f32, y: f32, u: f32, v: f32| OverlayVertex {
+			position: (position + Vec2::new(x, y) * size).into(),
+			tex_coords: [area.min_x() + u * area.width(), area.min_y() + v * area.height()],
+			color: color,
+		};
+		let corners = [
+			vertex(0.0, 0.0, 0.0, 0.0),
+			vertex(0.0, 1.0, 0.0, 1.0),
+			vertex(1.0, 0.0, 1.0, 0.0),
+			vertex(1.0, 1.0, 1.0, 1.0),
+		];
+		self.vertices
+			.extend([0, 1, 2, 1, 3, 2].iter().map(|&index| corners[index]));
+	}
+
+	/// Draw everything added since the last call into a surface and clear the overlay
+	pub fn draw<F, S>(&mut self, facade: &F, surface: &mut S, materials: &MaterialCollection)
+	where
+		F: glium::backend::Facade,
+		S: Surface,
+	{
+		if self.vertices.is_empty() {
+			return;
+		}
+
+		let (width, height) = surface.get_dimensions();
+		let vertices = VertexBuffer::new(facade, &self.vertices).expect("failed to upload overlay vertices!");
+		self.vertices.clear();
+
+		let overlay_uniforms = uniform! {
+			u_target_size: [width as f32, height as f32],
+			u_texture: self.atlas
+				.texture
+				.sampled()
+				.magnify_filter(MagnifySamplerFilter::Nearest)
+				.minify_filter(MinifySamplerFilter::Nearest),
+		};
+		let uniforms = materials.uniforms(self.material, &overlay_uniforms);
+		surface
+			.draw(
+				&vertices,
+				NoIndices(PrimitiveType::TrianglesList),
+				materials.program(self.material),
+				&uniforms,
+				&materials.draw_parameters(self.material),
+			).unwrap();
+	}
+}
@@ -0,0 +1,189 @@
+// Post-processing of the rendered scene
+//
+// The scene is rendered into an offscreen target, then a chain of full-screen passes is run over it.
+// Every pass is a material. If the scene resolution matches the window the last pass draws straight into it,
+// otherwise the result is upscaled into the window by a separate present pass.
+
+use config::Configuration;
+use profiler;
+
+use super::graphics::Quad;
+use super::material::{Material, MaterialCollection, MaterialError};
+use super::viewport::Viewport;
+
+use glium::framebuffer::{DepthRenderBuffer, SimpleFrameBuffer};
+use glium::texture::{DepthFormat, MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
+use glium::Surface;
+
+use std::time::Instant;
+
+/// Format of offscreen targets, floating point to keep bright values for bloom
+const TARGET_FORMAT: UncompressedFloatFormat = UncompressedFloatFormat::F16F16F16F16;
+
+/// Material used to upscale the result into the window
+const PRESENT_MATERIAL: &str = "present";
+
+/// A single post-processing pass as written in the configuration
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PostPassConfig {
+	pub material: String,
+	#[serde(default)]
+	pub enabled: bool,
+}
+
+/// Textures the scene and intermediate passes are rendered into
+struct RenderTargets {
+	dimensions: (u32, u32),
+	scene: Texture2d,
+	depth: DepthRenderBuffer,
+	intermediate: [Texture2d; 2], // passes ping-pong between these
+}
+
+impl RenderTargets {
+	fn new<F>(facade: &F, dimensions: (u32, u32)) -> Self
+	where
+		F: glium::backend::Facade,
+	{
+		let (width, height) = dimensions;
+		let texture = || {
+			Texture2d::empty_with_format(facade, TARGET_FORMAT, MipmapsOption::NoMipmap, width, height)
+				.expect("failed to create post-processing target!")
+		};
+
+		Self {
+			dimensions: dimensions,
+			scene: texture(),
+			depth: DepthRenderBuffer::new(facade, DepthFormat::I24, width, height)
+				.expect("failed to create post-processing depth buffer!"),
+			intermediate: [texture(), texture()],
+		}
+	}
+}
+
+/// A configurable chain of full-screen passes
+pub struct PostProcessing {
+	passes: Vec<Material>,
+	present: Material,
+	targets: Option<RenderTargets>, // created lazily and recreated when the window is resized
+	start_time: Instant,
+}
+
+impl PostProcessing {
+	/// Load materials of all passes enabled in the configuration
+	pub fn new<F>(facade: &F, materials: &mut MaterialCollection, config: &Configuration) -> Result<Self, MaterialError>
+	where
+		F: glium::backend::Facade,
+	{
+		let mut passes = Vec::new();
+		for pass in config.post_passes.iter().filter(|pass| pass.enabled) {
+			passes.push(materials.load(facade, &pass.material)?);
+		}
+
+		Ok(Self {
+			passes: passes,
+			present: materials.load(facade, PRESENT_MATERIAL)?,
+			targets: None,
+			start_time: Instant::now(),
+		})
+	}
+
+	pub fn is_enabled(&self) -> bool {
+		!self.passes.is_empty()
+	}
+
+	/// Get a framebuffer the scene should be rendered into
+	pub fn scene_target<F>(&mut self, facade: &F, dimensions: (u32, u32)) -> SimpleFrameBuffer
+	where
+		F: glium::backend::Facade,
+	{
+		let outdated = match self.targets {
+			Some(ref targets) => targets.dimensions != dimensions,
+			None => true,
+		};
+		if outdated {
+			self.targets = Some(RenderTargets::new(facade, dimensions));
+		}
+
+		let targets = self.targets.as_ref().unwrap();
+		SimpleFrameBuffer::with_depth_buffer(facade, &targets.scene, &targets.depth)
+			.expect("failed to create scene framebuffer!")
+	}
+
+	/// Run all passes over the rendered scene and present the result into the window surface
+	pub fn present<F, S>(
+		&self,
+		facade: &F,
+		surface: &mut S,
+		materials: &MaterialCollection,
+		quad: &Quad,
+		viewport: &Viewport,
+	) where
+		F: glium::backend::Facade,
+		S: Surface,
+	{
+		let targets = match self.targets {
+			Some(ref targets) => targets,
+			None => return,
+		};
+
+		let elapsed = Instant::now().duration_since(self.start_time);
+		let time = elapsed.as_secs() as f32 + elapsed.subsec_micros() as f32 / 1000000.0;
+		let resolution = [targets.dimensions.0 as f32, targets.dimensions.1 as f32];
+		let direct = viewport.is_native();
+
+		let mut source = &targets.scene;
+		for (index, pass) in self.passes.iter().enumerate() {
+			let shared_uniforms = uniform! {
+				u_source: source.sampled().wrap_function(SamplerWrapFunction::Clamp),
+				u_resolution: resolution,
+				u_time: time,
+			};
+			let uniforms = materials.uniforms(*pass, &shared_uniforms);
+			let program = materials.program(*pass);
+			let params = materials.draw_parameters(*pass);
+
+			profiler::add_counter(profiler::COUNTER_DRAW_CALLS, 1);
+			if direct && index + 1 == self.passes.len() {
+				surface
+					.draw(&quad.vertices, &quad.indices, program, &uniforms, &params)
+					.unwrap();
+				return;
+			}
+
+			let destination = &targets.intermediate[index % 2];
+			let mut framebuffer = SimpleFrameBuffer::new(facade, destination)
+				.expect("failed to create post-processing framebuffer!");
+			framebuffer
+				.draw(&quad.vertices, &quad.indices, program, &uniforms, &params)
+				.unwrap();
+			source = destination;
+		}
+
+		let (magnify, minify) = if viewport.nearest_filtering {
+			(MagnifySamplerFilter::Nearest, MinifySamplerFilter::Nearest)
+		} else {
+			(MagnifySamplerFilter::Linear, MinifySamplerFilter::Linear)
+		};
+		let shared_uniforms = uniform! {
+			u_source: source
+				.sampled()
+				.wrap_function(SamplerWrapFunction::Clamp)
+				.magnify_filter(magnify)
+				.minify_filter(minify),
+			u_resolution: [viewport.present_rect.width as f32, viewport.present_rect.height as f32],
+			u_time: time,
+		};
+		let uniforms = materials.uniforms(self.present, &shared_uniforms);
+		let mut params = materials.draw_parameters(self.present);
+		params.viewport = Some(viewport.present_rect);
+
+		profiler::add_counter(profiler::COUNTER_DRAW_CALLS, 1);
+
+		// Clear letterboxing bars
+		surface.clear_color(0.0, 0.0, 0.0, 1.0);
+		surface
+			.draw(&quad.vertices, &quad.indices, materials.program(self.present), &uniforms, &params)
+			.unwrap();
+	}
+}
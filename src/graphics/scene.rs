@@ -1,7 +1,16 @@
+use super::angle::Angle;
 use super::instance::Instance;
-use super::math::{Rect, MAX_ROTATION, PI, Point};
+use super::math::{Rect, MAX_ROTATION, PI};
 use super::transform::Transform;
-use super::texture::{TextureCollection, GLTexture, TextureID};
+use super::texture::{TextureCollection, GLTexture, Texture, TextureID};
+use super::vector::Vec2;
+
+use ecs::components::{Sprite, Velocity};
+use ecs::systems::{self, MovementSystem};
+use ecs::{Entity, Schedule, World};
+use input::WheelAction;
+use random::{Random, Stream};
+use scene_stack::{GameScene, Transition};
 
 use rand::Rng;
 
@@ -16,8 +25,8 @@ pub trait Scene {
 		true
 	}
 	/// Origin of the 'looker' object
-	fn view_origin(&self) -> Point {
-		[0.0, 0.0]
+	fn view_origin(&self) -> Vec2 {
+		Vec2::zero()
 	}
 	/// Visibility distance from view origin
 	fn view_distance(&self) -> f32 {
@@ -27,37 +36,102 @@ pub trait Scene {
 		1.0
 	}
 
+	/// What is drawn behind parallax layers and objects
+	fn background(&self) -> Background {
+		Background::default()
+	}
+
+	/// Background layers drawn behind all objects, from the farthest to the nearest
+	fn parallax_layers(&self) -> &[ParallaxLayer] {
+		&[]
+	}
+
 	/// Reference to texture used for drawing instances
 	fn texture(&self) -> &GLTexture;
 }
 
-use std::time::Instant;
+/// How a background texture covers the screen
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackgroundFit {
+	Stretch,
+	Tile { tile_size: Vec2 }, // Size of a single tile in pixels
+}
+
+/// The backmost part of the scene, fixed to the screen
+#[derive(Clone, Debug)]
+pub enum Background {
+	Color([f32; 4]),
+	VerticalGradient { top: [f32; 4], bottom: [f32; 4] },
+	Texture { texture: Texture, fit: BackgroundFit, color: [f32; 4] },
+}
+
+impl Default for Background {
+	fn default() -> Self {
+		Background::Color([0.0, 0.0, 0.0, 1.0])
+	}
+}
+
+/// A background layer that scrolls slower than the world to give a sense of depth
+#[derive(Clone, Debug)]
+pub struct ParallaxLayer {
+	pub texture: Texture,
+	/// How much the layer moves with the view, 0 is fixed to the screen and 1 moves together with the world
+	pub scroll_factor: Vec2,
+	/// Position of the texture origin in layer space
+	pub offset: Vec2,
+	/// Size of the texture in world units
+	pub size: Vec2,
+	/// Should the texture be tiled along X and Y
+	pub repeat: [bool; 2],
+	pub color: [f32; 4],
+}
+
+impl ParallaxLayer {
+	/// Create a layer tiled in both directions
+	pub fn tiled(texture: Texture, scroll_factor: Vec2, size: Vec2) -> Self {
+		Self {
+			texture,
+			scroll_factor,
+			offset: Vec2::zero(),
+			size,
+			repeat: [true, true],
+			color: [1.0, 1.0, 1.0, 1.0],
+		}
+	}
+}
+
+use timestep;
 
 /// A basic temporary implementation of a Scene trait for testing purposes
-#[derive(Debug)]
 pub struct TestScene {
-	pub objects: Vec<Instance>,
+	pub world: World,
+	schedule: Schedule,
 	pub view_rect: Rect,
 
-	pub rotation_speeds: Vec<f32>,
-	pub view_origin: Point,
+	pub view_origin: Vec2,
 	pub view_distance: f32,
-	last_update: Instant,
+	objects: Vec<(Entity, Instance)>, // extracted from the world after the last update
+	previous_objects: Vec<(Entity, Instance)>, // state before the last update
+	interpolated_objects: Vec<Instance>, // what is drawn, between the previous and the current state
 
 	pub texture_collection: TextureCollection,
 	pub sharpness: f32,
+	pub background: Background,
+	pub parallax_layers: Vec<ParallaxLayer>,
+	pub random: Random,
 }
 
 impl Scene for TestScene {
 	fn object_instances(&self) -> &[Instance] {
-		&self.objects
+		&self.interpolated_objects
 	}
 
 	fn view_rect(&self) -> Rect {
 		self.view_rect.clone()
 	}
 
-	fn view_origin(&self) -> Point {
+	fn view_origin(&self) -> Vec2 {
 		self.view_origin
 	}
 
@@ -72,61 +146,109 @@ impl Scene for TestScene {
 	fn view_sharpness(&self) -> f32 {
 		self.sharpness
 	}
+
+	fn background(&self) -> Background {
+		self.background.clone()
+	}
+
+	fn parallax_layers(&self) -> &[ParallaxLayer] {
+		&self.parallax_layers
+	}
 }
 
 impl TestScene {
-	pub fn generate(columns: u32, rows: u32, texture_collection: TextureCollection, lit_texture: TextureID, unlit_texture: TextureID) -> TestScene {
-		let mut rng = rand::thread_rng();
-		let mut objects = Vec::with_capacity((columns * rows) as usize);
-		let mut rotations = Vec::with_capacity((columns * rows) as usize);
+	pub fn generate(columns: u32, rows: u32, texture_collection: TextureCollection, lit_texture: TextureID, unlit_texture: TextureID, mut random: Random) -> TestScene {
+		let mut world = World::new();
 
 		let lit_texture = texture_collection.get(&lit_texture).unwrap();
 		let unlit_texture = texture_collection.get(&unlit_texture).unwrap();
 
 		for x in 0..columns {
 			for y in 0..rows {
-				objects.push(Instance {
-					transform: Transform::new(
-						[x as f32 + 0.5, y as f32 + 0.5],
-						rng.gen_range(0.0, MAX_ROTATION),
-						[1.0, 1.0],
-					),
-					color_lit: [rng.gen(), rng.gen(), rng.gen(), 1.0],
-					color_unlit: [rng.gen_range(0.0, 0.5), rng.gen_range(0.0, 0.5), rng.gen_range(0.0, 0.5), rng.gen()],
-					texture_lit: lit_texture,
-					texture_unlit: unlit_texture,
-				});
-				rotations.push(rng.gen_range(-PI, PI));
+				let rng = random.stream(Stream::Worldgen);
+				world
+					.build()
+					.with(Transform::new(
+						Vec2::new(x as f32 + 0.5, y as f32 + 0.5),
+						Angle::from_radians(rng.gen_range(0.0, MAX_ROTATION)),
+						Vec2::splat(1.0),
+					)).with(Sprite::new(
+						lit_texture,
+						unlit_texture,
+						[rng.gen(), rng.gen(), rng.gen(), 1.0],
+						[rng.gen_range(0.0, 0.5), rng.gen_range(0.0, 0.5), rng.gen_range(0.0, 0.5), rng.gen()],
+					)).with(Velocity {
+						linear: Vec2::zero(),
+						angular: rng.gen_range(-PI, PI),
+					});
 			}
 		}
 
+		let mut objects = Vec::with_capacity((columns * rows) as usize);
+		systems::extract_instances(&world, &mut objects);
+
 		TestScene {
+			world: world,
+			schedule: Schedule::new().with(MovementSystem),
+			previous_objects: objects.clone(),
+			interpolated_objects: objects.iter().map(|&(_, ref instance)| instance.clone()).collect(),
 			objects: objects,
 			view_rect: Rect::new(
-				[0.0, 0.0],
-				[columns as f32, rows as f32],
+				Vec2::zero(),
+				Vec2::new(columns as f32, rows as f32),
 			),
-			rotation_speeds: rotations,
-			last_update: Instant::now(),
 			view_distance: ((columns * rows) as f32).powf(1.0 / 4.0),
-			view_origin: [0.0, 0.0],
+			view_origin: Vec2::zero(),
 
 			texture_collection: texture_collection,
 			sharpness: 1.0,
+			background: Background::Color([0.0, 0.0, 1.0, 1.0]),
+			parallax_layers: Vec::new(),
+			random: random,
 		}
 	}
 
-	pub fn update(&mut self) {
-		let now = Instant::now();
-		let delta = now.duration_since(self.last_update).subsec_micros() as f32 / 1000000.0;
+	/// Advance the simulation by delta seconds
+	pub fn update(&mut self, delta: f32) {
+		std::mem::swap(&mut self.previous_objects, &mut self.objects);
+		self.schedule.run(&mut self.world, delta);
+		systems::extract_instances(&self.world, &mut self.objects);
+	}
 
-		for (src, dest) in self.rotation_speeds.iter().zip(self.objects.iter_mut()) {
-			dest.transform.rotate(delta * src);
-		}
-		self.last_update = now;
+	/// Prepare objects for drawing, alpha is the position between the previous and the current state
+	pub fn interpolate(&mut self, alpha: f32) {
+		timestep::interpolate(&self.previous_objects, &self.objects, alpha, &mut self.interpolated_objects);
 	}
 
 	pub fn free_texture_collection(self) -> TextureCollection {
 		self.texture_collection
 	}
 }
+
+impl GameScene for TestScene {
+	fn as_scene(&self) -> &Scene {
+		self
+	}
+
+	fn update(&mut self, delta: f32) -> Transition {
+		TestScene::update(self, delta);
+		Transition::none()
+	}
+
+	fn interpolate(&mut self, alpha: f32) {
+		TestScene::interpolate(self, alpha);
+	}
+
+	fn handle_wheel_action(&mut self, action: WheelAction, delta: f32) {
+		match action {
+			WheelAction::None => (),
+			WheelAction::ChangeViewSize => self.view_distance *= 1.0 + delta / 8.0,
+			WheelAction::ChangeViewSharpness => self.sharpness *= 1.0 + delta / 8.0,
+			WheelAction::ChangeSceneSize => (),
+		};
+	}
+
+	fn handle_cursor(&mut self, position: Vec2) {
+		self.view_origin = position;
+	}
+}
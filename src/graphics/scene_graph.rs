@@ -0,0 +1,341 @@
+// Hierarchy of transforms
+//
+// Every node has a transform relative to its parent. World transforms are computed lazily and cached,
+// changing a node marks it and all of its descendants dirty so only those get recomputed.
+// Composition follows Transform::compose(), which is exact only for uniformly scaled children.
+
+use super::vector::Vec2;
+use super::transform::Transform;
+
+/// Handle of a node, stale handles of removed nodes are detected through generations
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct NodeId {
+	index: usize,
+	generation: u32,
+}
+
+struct Node {
+	local: Transform,
+	world: Transform, // valid only when not dirty
+	dirty: bool,      // if set, all descendants are dirty too
+	parent: Option<NodeId>,
+	children: Vec<NodeId>,
+	generation: u32,
+	alive: bool,
+}
+
+pub struct SceneGraph {
+	nodes: Vec<Node>,
+	free: Vec<usize>,
+}
+
+impl SceneGraph {
+	pub fn new() -> Self {
+		Self {
+			nodes: Vec::new(),
+			free: Vec::new(),
+		}
+	}
+
+	pub fn contains(&self, id: NodeId) -> bool {
+		id.index < self.nodes.len() && self.nodes[id.index].alive && self.nodes[id.index].generation == id.generation
+	}
+
+	fn node(&self, id: NodeId) -> &Node {
+		assert!(self.contains(id), "scene graph node {:?} doesn't exist!", id);
+		&self.nodes[id.index]
+	}
+
+	fn node_mut(&mut self, id: NodeId) -> &mut Node {
+		assert!(self.contains(id), "scene graph node {:?} doesn't exist!", id);
+		&mut self.nodes[id.index]
+	}
+
+	/// Add a node with a transform relative to its parent, or to the world if it has none
+	pub fn add(&mut self, local: Transform, parent: Option<NodeId>) -> NodeId {
+		if let Some(parent) = parent {
+			assert!(self.contains(parent), "scene graph parent {:?} doesn't exist!", parent);
+		}
+
+		let node = Node {
+			world: local.clone(),
+			local: local,
+			dirty: true,
+			parent: parent,
+			children: Vec::new(),
+			generation: 0,
+			alive: true,
+		};
+		let id = match self.free.pop() {
+			Some(index) => {
+				let generation = self.nodes[index].generation;
+				self.nodes[index] = Node {
+					generation: generation,
+					..node
+				};
+				NodeId {
+					index: index,
+					generation: generation,
+				}
+			}
+			None => {
+				self.nodes.push(node);
+				NodeId {
+					index: self.nodes.len() - 1,
+					generation: 0,
+				}
+			}
+		};
+
+		if let Some(parent) = parent {
+			self.node_mut(parent).children.push(id);
+		}
+		id
+	}
+
+	/// Remove a node together with all of its descendants
+	pub fn remove(&mut self, id: NodeId) {
+		if let Some(parent) = self.node(id).parent {
+			self.node_mut(parent).children.retain(|child| *child != id);
+		}
+
+		let mut pending = vec![id];
+		while let Some(id) = pending.pop() {
+			let node = &mut self.nodes[id.index];
+			pending.extend(node.children.drain(..));
+			node.alive = false;
+			node.generation = node.generation.wrapping_add(1);
+			self.free.push(id.index);
+		}
+	}
+
+	pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+		self.node(id).parent
+	}
+
+	pub fn children(&self, id: NodeId) -> &[NodeId] {
+		&self.node(id).children
+	}
+
+	/// Is ancestor somewhere above the node
+	pub fn is_ancestor(&self, ancestor: NodeId, id: NodeId) -> bool {
+		let mut current = self.node(id).parent;
+		while let Some(parent) = current {
+			if parent == ancestor {
+				return true;
+			}
+			current = self.node(parent).parent;
+		}
+		false
+	}
+
+	/// Move a node under another parent, keeping its local transform
+	///
+	/// Panics if the new parent is the node itself or one of its descendants.
+	pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) {
+		if let Some(parent) = parent {
+			assert!(
+				parent != id && !self.is_ancestor(id, parent),
+				"scene graph node can't become a child of its own descendant!"
+			);
+		}
+
+		if let Some(old_parent) = self.node(id).parent {
+			self.node_mut(old_parent).children.retain(|child| *child != id);
+		}
+		if let Some(parent) = parent {
+			self.node_mut(parent).children.push(id);
+		}
+		self.node_mut(id).parent = parent;
+		self.mark_dirty(id);
+	}
+
+	/// Transform relative to the parent
+	pub fn local(&self, id: NodeId) -> &Transform {
+		&self.node(id).local
+	}
+
+	pub fn set_local(&mut self, id: NodeId, local: Transform) {
+		self.node_mut(id).local = local;
+		self.mark_dirty(id);
+	}
+
+	/// Change the local transform in place
+	pub fn update_local<F: FnOnce(&mut Transform)>(&mut self, id: NodeId, function: F) {
+		function(&mut self.node_mut(id).local);
+		self.mark_dirty(id);
+	}
+
+	fn mark_dirty(&mut self, id: NodeId) {
+		let mut pending = vec![id];
+		while let Some(id) = pending.pop() {
+			let node = &mut self.nodes[id.index];
+			// Descendants of a dirty node are already dirty
+			if !node.dirty {
+				node.dirty = true;
+				pending.extend(node.children.iter().cloned());
+			}
+		}
+	}
+
+	/// Transform relative to the world, recomputed if the node or one of its ancestors changed
+	pub fn world(&mut self, id: NodeId) -> &Transform {
+		if self.node(id).dirty {
+			// Walk up to the topmost dirty ancestor, then resolve back down
+			let mut chain = vec![id];
+			let mut current = self.node(id).parent;
+			while let Some(parent) = current {
+				if !self.nodes[parent.index].dirty {
+					break;
+				}
+				chain.push(parent);
+				current = self.nodes[parent.index].parent;
+			}
+
+			for id in chain.into_iter().rev() {
+				let world = match self.nodes[id.index].parent {
+					Some(parent) => self.nodes[parent.index].world.compose(&self.nodes[id.index].local),
+					None => self.nodes[id.index].local.clone(),
+				};
+				let node = &mut self.nodes[id.index];
+				node.world = world;
+				node.dirty = false;
+			}
+		}
+		&self.nodes[id.index].world
+	}
+
+	/// Recompute world transforms of all dirty nodes
+	pub fn update(&mut self) {
+		for index in 0..self.nodes.len() {
+			if self.nodes[index].alive && self.nodes[index].dirty {
+				let id = NodeId {
+					index: index,
+					generation: self.nodes[index].generation,
+				};
+				self.world(id);
+			}
+		}
+	}
+
+	/// Move a point from the space of a node into world space
+	pub fn local_to_world(&mut self, id: NodeId, point: Vec2) -> Vec2 {
+		self.world(id).transform_point(point)
+	}
+
+	/// Move a point from world space into the space of a node
+	pub fn world_to_local(&mut self, id: NodeId, point: Vec2) -> Vec2 {
+		self.world(id).inverse_transform_point(point)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use graphics::angle::Angle;
+
+	const EPSILON: f32 = 1e-4;
+
+	fn close(a: Vec2, b: Vec2) -> bool {
+		(a - b).length() < EPSILON
+	}
+
+	fn at(x: f32, y: f32) -> Transform {
+		Transform::new(Vec2::new(x, y), Angle::zero(), Vec2::splat(1.0))
+	}
+
+	fn is_dirty(graph: &SceneGraph, id: NodeId) -> bool {
+		graph.node(id).dirty
+	}
+
+	#[test]
+	fn set_local_marks_descendants_dirty() {
+		let mut graph = SceneGraph::new();
+		let root = graph.add(at(0.0, 0.0), None);
+		let parent = graph.add(at(1.0, 0.0), Some(root));
+		let child = graph.add(at(1.0, 0.0), Some(parent));
+		let sibling = graph.add(at(0.0, 1.0), Some(root));
+		let other_root = graph.add(at(5.0, 5.0), None);
+		graph.update();
+		assert!([root, parent, child, sibling, other_root].iter().all(|id| !is_dirty(&graph, *id)));
+
+		graph.set_local(parent, at(2.0, 0.0));
+		assert!(is_dirty(&graph, parent) && is_dirty(&graph, child));
+		assert!(!is_dirty(&graph, root) && !is_dirty(&graph, sibling) && !is_dirty(&graph, other_root));
+
+		assert!(close(graph.world(child).translation, Vec2::new(3.0, 0.0)));
+		assert!(!is_dirty(&graph, parent) && !is_dirty(&graph, child));
+	}
+
+	#[test]
+	fn stale_ids() {
+		let mut graph = SceneGraph::new();
+		let parent = graph.add(at(0.0, 0.0), None);
+		let child = graph.add(at(1.0, 0.0), Some(parent));
+		graph.remove(parent);
+		assert!(!graph.contains(parent));
+		assert!(!graph.contains(child));
+
+		// Both slots are reused, the old ids must not see the new nodes
+		let first = graph.add(at(0.0, 0.0), None);
+		let second = graph.add(at(0.0, 0.0), None);
+		assert!(graph.contains(first) && graph.contains(second));
+		assert!(!graph.contains(parent));
+		assert!(!graph.contains(child));
+	}
+
+	#[test]
+	fn remove_detaches_from_parent() {
+		let mut graph = SceneGraph::new();
+		let parent = graph.add(at(0.0, 0.0), None);
+		let child = graph.add(at(1.0, 0.0), Some(parent));
+		let other = graph.add(at(2.0, 0.0), Some(parent));
+		graph.remove(child);
+		assert_eq!(graph.children(parent), &[other]);
+	}
+
+	#[test]
+	#[should_panic(expected = "own descendant")]
+	fn set_parent_rejects_descendants() {
+		let mut graph = SceneGraph::new();
+		let root = graph.add(at(0.0, 0.0), None);
+		let child = graph.add(at(1.0, 0.0), Some(root));
+		let grandchild = graph.add(at(1.0, 0.0), Some(child));
+		graph.set_parent(root, Some(grandchild));
+	}
+
+	#[test]
+	#[should_panic(expected = "own descendant")]
+	fn set_parent_rejects_itself() {
+		let mut graph = SceneGraph::new();
+		let node = graph.add(at(0.0, 0.0), None);
+		graph.set_parent(node, Some(node));
+	}
+
+	#[test]
+	fn set_parent_keeps_local_transform() {
+		let mut graph = SceneGraph::new();
+		let first = graph.add(at(1.0, 0.0), None);
+		let second = graph.add(at(0.0, 3.0), None);
+		let child = graph.add(at(1.0, 0.0), Some(first));
+		assert!(close(graph.world(child).translation, Vec2::new(2.0, 0.0)));
+
+		graph.set_parent(child, Some(second));
+		assert!(graph.children(first).is_empty());
+		assert_eq!(graph.parent(child), Some(second));
+		assert!(close(graph.world(child).translation, Vec2::new(1.0, 3.0)));
+	}
+
+	#[test]
+	fn local_world_round_trip() {
+		let mut graph = SceneGraph::new();
+		let parent = graph.add(Transform::new(Vec2::new(5.0, 0.0), Angle::from_degrees(90.0), Vec2::splat(2.0)), None);
+		let child = graph.add(Transform::new(Vec2::new(1.0, 0.0), Angle::from_degrees(30.0), Vec2::splat(0.5)), Some(parent));
+		assert!(close(graph.local_to_world(child, Vec2::zero()), Vec2::new(5.0, 2.0)));
+
+		for &point in [Vec2::zero(), Vec2::new(1.0, 2.0), Vec2::new(-3.0, 0.5)].iter() {
+			let world = graph.local_to_world(child, point);
+			assert!(close(graph.world_to_local(child, world), point), "{:?}", point);
+		}
+	}
+}
@@ -0,0 +1,348 @@
+// Shader loading, preprocessing and hot reloading
+//
+// Every file handed to the driver gets its own GLSL source string number via a #line directive,
+// this way compilation errors can be mapped back to the file and line that caused them.
+
+use config::Configuration;
+use SHADER_PREFIX;
+
+use super::{FRAGMENT_SHADER_EXTENSHION, VERTEX_SHADER_EXTENSHION};
+
+use glium::Program;
+
+use std::collections::HashMap as Map;
+use std::io::Error as IoError;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the watcher checks shader files for modifications
+const WATCH_INTERVAL_MS: u64 = 500;
+
+#[derive(Debug)]
+pub enum ShaderError {
+	Io(PathBuf, IoError), // Failed to read a shader file
+	Program(String),      // Failed to compile or link, holds the driver log with locations mapped to files
+}
+
+impl std::fmt::Display for ShaderError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			ShaderError::Io(path, error) => write!(f, "(IO) {}: {}", path.display(), error),
+			ShaderError::Program(log) => write!(f, "(Program) {}", log),
+		}
+	}
+}
+
+impl std::error::Error for ShaderError {
+	fn description(&self) -> &str {
+		"Failed to load a shader program."
+	}
+
+	fn cause(&self) -> Option<&std::error::Error> {
+		match self {
+			ShaderError::Io(_, error) => Some(error),
+			ShaderError::Program(_) => None,
+		}
+	}
+}
+
+/// Preprocessor definitions injected into every shader right after #version
+#[derive(Clone, Debug, Default)]
+pub struct ShaderDefines {
+	definitions: Vec<(String, String)>,
+}
+
+impl ShaderDefines {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Definitions derived from the configuration, including user-provided feature toggles
+	pub fn from_config(config: &Configuration) -> Self {
+		let mut defines = Self::new();
+		if config.debug_mode {
+			defines.define("DEBUG_MODE", "1");
+		}
+		for (name, value) in &config.shader_defines {
+			defines.define(name, value);
+		}
+		defines
+	}
+
+	/// Add a definition, replacing the old value if one is already defined
+	pub fn define(&mut self, name: &str, value: &str) {
+		match self.definitions.iter_mut().find(|definition| definition.0 == name) {
+			Some(definition) => definition.1 = String::from(value),
+			None => self.definitions.push((String::from(name), String::from(value))),
+		}
+	}
+}
+
+/// Resolves #include directives and injects definitions
+///
+/// Every file gets its own GLSL source string number, which is shared between stages of one program.
+struct Preprocessor<'a> {
+	defines: &'a ShaderDefines,
+	files: Vec<PathBuf>,  // All files read so far, source string number N refers to files[N - 1]
+	included: Vec<usize>, // Source string numbers already included into the current stage
+}
+
+impl<'a> Preprocessor<'a> {
+	fn new(defines: &'a ShaderDefines) -> Self {
+		Self {
+			defines,
+			files: Vec::new(),
+			included: Vec::new(),
+		}
+	}
+
+	/// Build source code of a single shader stage
+	fn stage(&mut self, path: &Path) -> Result<String, ShaderError> {
+		self.included.clear();
+		let mut code = String::new();
+		self.append(path, &mut code, true)?;
+		Ok(code)
+	}
+
+	/// Source string 0 is what the driver reports for untagged lines, so files start at 1
+	fn source_number(&mut self, path: &Path) -> usize {
+		match self.files.iter().position(|file| file == path) {
+			Some(index) => index + 1,
+			None => {
+				self.files.push(path.to_path_buf());
+				self.files.len()
+			}
+		}
+	}
+
+	fn append(&mut self, path: &Path, output: &mut String, top_level: bool) -> Result<(), ShaderError> {
+		let source_number = self.source_number(path);
+		// Every file is included at most once per stage, this also breaks include cycles
+		if self.included.contains(&source_number) {
+			return Ok(());
+		}
+		self.included.push(source_number);
+
+		let code = match std::fs::read_to_string(path) {
+			Ok(code) => code,
+			Err(error) => return Err(ShaderError::Io(path.to_path_buf(), error)),
+		};
+		let mut lines = code.lines().enumerate();
+		let mut line_number = 1;
+
+		// #version has to be the first statement, so definitions go right after it
+		if top_level {
+			if code.trim_start().starts_with("#version") {
+				for (index, line) in &mut lines {
+					output.push_str(line);
+					output.push('\n');
+					line_number = index + 2;
+					if line.trim_start().starts_with("#version") {
+						break;
+					}
+				}
+			}
+			for (name, value) in &self.defines.definitions {
+				output.push_str(&format!("#define {} {}\n", name, value));
+			}
+		}
+
+		output.push_str(&format!("#line {} {}\n", line_number, source_number));
+		for (index, line) in lines {
+			match parse_include(line) {
+				Some(include) => {
+					let include = PathBuf::from(String::from(SHADER_PREFIX) + include);
+					self.append(&include, output, false)?;
+					output.push_str(&format!("#line {} {}\n", index + 2, source_number));
+				}
+				None => {
+					output.push_str(line);
+					output.push('\n');
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Get the file name out of an '#include "file.glsl"' line
+fn parse_include(line: &str) -> Option<&str> {
+	let line = line.trim();
+	if !line.starts_with("#include") {
+		return None;
+	}
+
+	let name = line["#include".len()..].trim();
+	if name.len() > 2 && name.starts_with('"') && name.ends_with('"') {
+		Some(&name[1..name.len() - 1])
+	} else {
+		None
+	}
+}
+
+/// Path to a shader stage file
+pub fn shader_path(shader_name: &str, extension: &str) -> PathBuf {
+	let mut path = String::from(SHADER_PREFIX);
+	path.push_str(shader_name);
+	path.push_str(extension);
+	PathBuf::from(path)
+}
+
+/// Load, preprocess and compile a vertex-fragment shader pair
+///
+/// Returns the program alongside all the files that were used to build it, including the included ones.
+pub fn load_program<F>(
+	facade: &F,
+	shader_name: &str,
+	defines: &ShaderDefines,
+) -> Result<(Program, Vec<PathBuf>), ShaderError>
+where
+	F: glium::backend::Facade,
+{
+	let mut preprocessor = Preprocessor::new(defines);
+	let vertex_shader = preprocessor.stage(&shader_path(shader_name, VERTEX_SHADER_EXTENSHION))?;
+	let fragment_shader = preprocessor.stage(&shader_path(shader_name, FRAGMENT_SHADER_EXTENSHION))?;
+	let files = preprocessor.files;
+
+	match Program::from_source(facade, &vertex_shader, &fragment_shader, None) {
+		Ok(program) => Ok((program, files)),
+		Err(error) => Err(ShaderError::Program(map_error_log(&error.to_string(), &files))),
+	}
+}
+
+/// Replace "source:line" locations in a driver log with "file:line"
+///
+/// Drivers disagree on the format, Mesa and AMD use "0:12(5)" and "0:12:", NVIDIA uses "0(12)".
+fn map_error_log(log: &str, files: &[PathBuf]) -> String {
+	let mut mapped = String::with_capacity(log.len());
+
+	for line in log.lines() {
+		match find_location(line) {
+			Some((start, end, source_number, line_number)) if source_number > 0 && source_number <= files.len() => {
+				mapped.push_str(&line[..start]);
+				mapped.push_str(&format!("{}:{}", files[source_number - 1].display(), line_number));
+				mapped.push_str(&line[end..]);
+			}
+			_ => mapped.push_str(line),
+		}
+		mapped.push('\n');
+	}
+
+	mapped
+}
+
+/// Find the first "source:line" or "source(line)" pattern in a log line
+///
+/// Returns the byte range of the pattern, source string number and line number.
+fn find_location(line: &str) -> Option<(usize, usize, usize, usize)> {
+	let bytes = line.as_bytes();
+	let digits_end = |from: usize| {
+		let mut end = from;
+		while end < bytes.len() && bytes[end].is_ascii_digit() {
+			end += 1;
+		}
+		end
+	};
+
+	let mut start = 0;
+	while start < bytes.len() {
+		if !bytes[start].is_ascii_digit() || (start > 0 && bytes[start - 1].is_ascii_digit()) {
+			start += 1;
+			continue;
+		}
+
+		let source_end = digits_end(start);
+		if source_end + 1 < bytes.len() && (bytes[source_end] == b':' || bytes[source_end] == b'(') {
+			let line_end = digits_end(source_end + 1);
+			if line_end > source_end + 1 {
+				let mut end = line_end;
+				if bytes[source_end] == b'(' {
+					if line_end < bytes.len() && bytes[line_end] == b')' {
+						end += 1;
+					} else {
+						start = source_end;
+						continue;
+					}
+				}
+				let source_number = line[start..source_end].parse().ok()?;
+				let line_number = line[source_end + 1..line_end].parse().ok()?;
+				return Some((start, end, source_number, line_number));
+			}
+		}
+		start = source_end;
+	}
+
+	None
+}
+
+struct WatchedFile {
+	path: PathBuf,
+	modified: Option<SystemTime>,
+}
+
+impl WatchedFile {
+	fn new(path: PathBuf) -> Self {
+		let modified = modification_time(&path);
+		Self { path, modified }
+	}
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+	match std::fs::metadata(path) {
+		Ok(metadata) => metadata.modified().ok(),
+		Err(_) => None,
+	}
+}
+
+/// Polls shader files for modifications
+///
+/// Polling is cheap enough for the handful of files we have and avoids platform specific watchers.
+pub struct ShaderWatcher {
+	shaders: Map<String, Vec<WatchedFile>>,
+	last_poll: Instant,
+}
+
+impl ShaderWatcher {
+	pub fn new() -> Self {
+		Self {
+			shaders: Map::new(),
+			last_poll: Instant::now(),
+		}
+	}
+
+	/// Start (or restart) watching files of a given shader, remembering their current state
+	pub fn watch(&mut self, shader_name: &str, files: Vec<PathBuf>) {
+		let files = files.into_iter().map(WatchedFile::new).collect();
+		self.shaders.insert(String::from(shader_name), files);
+	}
+
+	/// Get names of all shaders that had any of their files modified since last poll
+	///
+	/// Does nothing if called more often than WATCH_INTERVAL_MS.
+	pub fn poll(&mut self) -> Vec<String> {
+		let mut changed = Vec::new();
+
+		let now = Instant::now();
+		if now.duration_since(self.last_poll) < Duration::from_millis(WATCH_INTERVAL_MS) {
+			return changed;
+		}
+		self.last_poll = now;
+
+		for (name, files) in self.shaders.iter_mut() {
+			let mut modified = false;
+			for file in files.iter_mut() {
+				let time = modification_time(&file.path);
+				if time != file.modified {
+					file.modified = time;
+					modified = true;
+				}
+			}
+			if modified {
+				changed.push(name.clone());
+			}
+		}
+
+		changed
+	}
+}
@@ -0,0 +1,218 @@
+// Bitmap text for debug overlays
+//
+// Printable ASCII glyphs are rasterized once at a fixed pixel size and packed into a single texture.
+
+use FONT_PREFIX;
+
+use super::math::Rect;
+use super::vector::Vec2;
+
+use rusttype::{point, Font, Scale};
+
+use glium::texture::{RawImage2d, Texture2d, TextureCreationError};
+
+use std::collections::HashMap as Map;
+use std::io::Error as IoError;
+
+/// Width of the atlas texture, glyphs are packed in rows
+const ATLAS_WIDTH: u32 = 512;
+/// Empty pixels around every glyph to avoid bleeding when sampling
+const GLYPH_PADDING: u32 = 1;
+/// Size of the opaque block used to draw solid rectangles
+const SOLID_SIZE: u32 = 2;
+
+/// Placement of a single rasterized glyph
+#[derive(Copy, Clone, Debug)]
+pub struct Glyph {
+	pub area: Rect,        // texture area within the atlas
+	pub size: Vec2,        // in pixels
+	pub offset: Vec2,      // from the pen position on the baseline to the top-left corner, y pointing down
+	pub advance: f32,      // horizontal distance to the next pen position
+}
+
+/// All printable ASCII glyphs of a font at a single size
+pub struct GlyphAtlas {
+	glyphs: Map<char, Glyph>,
+	solid: Rect,
+	pub texture: Texture2d,
+	pub ascent: f32,
+	pub line_height: f32,
+}
+
+#[derive(Debug)]
+pub enum TextError {
+	Io(IoError),                   // Something went wrong trying to read the font file
+	Font(rusttype::Error),         // The font file is not a valid font
+	Texture(TextureCreationError), // Failed to upload the atlas to the GPU
+}
+
+impl std::fmt::Display for TextError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			TextError::Io(error) => write!(f, "(Io){}", error),
+			TextError::Font(error) => write!(f, "(Font){}", error),
+			TextError::Texture(error) => write!(f, "(Texture){:?}", error),
+		}
+	}
+}
+
+impl std::error::Error for TextError {
+	fn description(&self) -> &str {
+		"Failed to create a glyph atlas."
+	}
+}
+
+impl From<IoError> for TextError {
+	fn from(error: IoError) -> Self {
+		TextError::Io(error)
+	}
+}
+
+impl From<rusttype::Error> for TextError {
+	fn from(error: rusttype::Error) -> Self {
+		TextError::Font(error)
+	}
+}
+
+impl From<TextureCreationError> for TextError {
+	fn from(error: TextureCreationError) -> Self {
+		TextError::Texture(error)
+	}
+}
+
+impl GlyphAtlas {
+	/// Rasterize a font from the font directory at the given pixel height
+	pub fn new<F>(facade: &F, font_name: &str, pixel_height: f32) -> Result<Self, TextError>
+	where
+		F: glium::backend::Facade,
+	{
+		let data = std::fs::read(String::from(FONT_PREFIX) + font_name)?;
+		let font = Font::from_bytes(data)?;
+		let scale = Scale::uniform(pixel_height);
+		let v_metrics = font.v_metrics(scale);
+
+		// Coverage of every glyph, packed left to right, top to bottom
+		struct Placed {
+			character: char,
+			position: (u32, u32),
+			size: (u32, u32),
+			offset: Vec2,
+			advance: f32,
+			coverage: Vec<u8>,
+		}
+
+		let mut placed = Vec::new();
+		let mut pen = (SOLID_SIZE + 2 * GLYPH_PADDING, GLYPH_PADDING);
+		let mut row_height = SOLID_SIZE;
+
+		for character in (32u8..127).map(|code| code as char) {
+			let glyph = font.glyph(character).scaled(scale);
+			let advance = glyph.h_metrics().advance_width;
+			let glyph = glyph.positioned(point(0.0, 0.0));
+
+			let (size, offset, coverage) = match glyph.pixel_bounding_box() {
+				Some(bounds) => {
+					let size = (bounds.width() as u32, bounds.height() as u32);
+					let mut coverage = vec![0u8; (size.0 * size.1) as usize];
+					glyph.draw(|x, y, value| {
+						coverage[(y * size.0 + x) as usize] = (value * 255.0).round() as u8;
+					});
+					(size, Vec2::new(bounds.min.x as f32, bounds.min.y as f32), coverage)
+				}
+				None => ((0, 0), Vec2::zero(), Vec::new()), // whitespace
+			};
+
+			if pen.0 + size.0 + GLYPH_PADDING > ATLAS_WIDTH {
+				pen = (GLYPH_PADDING, pen.1 + row_height + 2 * GLYPH_PADDING);
+				row_height = 0;
+			}
+			placed.push(Placed {
+				character: character,
+				position: pen,
+				size: size,
+				offset: offset,
+				advance: advance,
+				coverage: coverage,
+			});
+			pen.0 += size.0 + 2 * GLYPH_PADDING;
+			row_height = row_height.max(size.1);
+		}
+
+		let height = (pen.1 + row_height + GLYPH_PADDING).next_power_of_two();
+
+		// White everywhere, coverage goes into alpha so that the texture can be tinted freely
+		let mut pixels = vec![255u8; (ATLAS_WIDTH * height * 4) as usize];
+		for alpha in pixels.iter_mut().skip(3).step_by(4) {
+			*alpha = 0;
+		}
+		let mut set_alpha = |x: u32, y: u32, alpha: u8| pixels[((y * ATLAS_WIDTH + x) * 4 + 3) as usize] = alpha;
+
+		for y in 0..SOLID_SIZE {
+			for x in 0..SOLID_SIZE {
+				set_alpha(GLYPH_PADDING + x, GLYPH_PADDING + y, 255);
+			}
+		}
+
+		// Rows are uploaded as they are, so texture v grows with the row index
+		let atlas_size = Vec2::new(ATLAS_WIDTH as f32, height as f32);
+		let area = |position: (u32, u32), size: (u32, u32)| {
+			Rect::new(
+				Vec2::new(position.0 as f32, position.1 as f32) / atlas_size,
+				Vec2::new((position.0 + size.0) as f32, (position.1 + size.1) as f32) / atlas_size,
+			)
+		};
+
+		let mut glyphs = Map::with_capacity(placed.len());
+		for glyph in placed {
+			for y in 0..glyph.size.1 {
+				for x in 0..glyph.size.0 {
+					set_alpha(
+						glyph.position.0 + x,
+						glyph.position.1 + y,
+						glyph.coverage[(y * glyph.size.0 + x) as usize],
+					);
+				}
+			}
+
+			glyphs.insert(
+				glyph.character,
+				Glyph {
+					area: area(glyph.position, glyph.size),
+					size: Vec2::new(glyph.size.0 as f32, glyph.size.1 as f32),
+					offset: glyph.offset,
+					advance: glyph.advance,
+				},
+			);
+		}
+
+		// Sample the middle of the solid block so that filtering never reaches transparent pixels
+		let solid = area((GLYPH_PADDING + SOLID_SIZE / 2, GLYPH_PADDING + SOLID_SIZE / 2), (0, 0));
+
+		let image = RawImage2d::from_raw_rgba(pixels, (ATLAS_WIDTH, height));
+		Ok(Self {
+			glyphs: glyphs,
+			solid: solid,
+			texture: Texture2d::new(facade, image)?,
+			ascent: v_metrics.ascent,
+			line_height: (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap).ceil(),
+		})
+	}
+
+	/// Glyph of a character, characters outside of printable ASCII are not available
+	pub fn glyph(&self, character: char) -> Option<&Glyph> {
+		self.glyphs.get(&character)
+	}
+
+	/// Texture area that is fully opaque, used for solid rectangles
+	pub fn solid_area(&self) -> Rect {
+		self.solid
+	}
+
+	/// Width of a line of text in pixels
+	pub fn text_width(&self, text: &str) -> f32 {
+		text.chars()
+			.filter_map(|character| self.glyph(character))
+			.map(|glyph| glyph.advance)
+			.sum()
+	}
+}
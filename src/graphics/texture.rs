@@ -6,6 +6,7 @@ use TEXTURE_PREFIX;
 
 use super::Graphics;
 use super::math::Rect;
+use super::vector::Vec2;
 
 use image::{ImageError, DynamicImage, GenericImage};
 
@@ -23,7 +24,7 @@ const DEFAULT_FORMAT: GLTextureFormat = GLTextureFormat::S3tcDxt1Alpha;
 const USED_MIPMAP: glium::texture::CompressedMipmapsOption = glium::texture::CompressedMipmapsOption::NoMipmap;
 
 // A single texture
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Texture {
 	pub area: Rect,
 }
@@ -104,7 +105,7 @@ impl TextureCollection {
 
 		for (name, image) in texture_filenames.iter().zip(images.iter()) {
 			let (image_width, image_height) = image.dimensions();
-			let rect = Rect::new([pos_x as f32 / width, 1.0 - image_height as f32 / height], [(pos_x + image_width) as f32 / width, 1.0]);
+			let rect = Rect::new(Vec2::new(pos_x as f32 / width, 1.0 - image_height as f32 / height), Vec2::new((pos_x + image_width) as f32 / width, 1.0));
 			textures.insert(String::from(*name), Texture {area: rect});
 			texture.copy_from(image, pos_x, 0);
 			pos_x += image_width;
@@ -124,4 +125,9 @@ impl TextureCollection {
 			None => None
 		}
 	}
+
+	/// Find the id a texture was loaded with
+	pub fn find_id(&self, texture: &Texture) -> Option<&TextureID> {
+		self.textures.iter().find(|(_, other)| *other == texture).map(|(id, _)| id)
+	}
 }
\ No newline at end of file
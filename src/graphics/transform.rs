@@ -1,49 +1,114 @@
-use super::math::{Lerp, MAX_ROTATION};
+use super::angle::Angle;
+use super::math::{Boundable, Bounds, Lerp};
+use super::vector::{Mat3, Vec2};
 
 // Transformation of a drawable object
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Transform {
-	pub translation: [f32; 2],	// Position of the object in world space
-	pub rotation: f32,			// Rotation around the origin of the object in radians
-	pub scale: [f32; 2],		// Scaling of the object in world coordinate-space
+	pub translation: Vec2,		// Position of the object in world space
+	pub rotation: Angle,		// Rotation around the origin of the object
+	pub scale: Vec2,		// Scaling of the object in world coordinate-space
 }
 
 impl Default for Transform {
 	fn default() -> Self {
-		Self {translation: [0.0, 0.0], rotation: 0.0, scale: [1.0, 1.0]}
+		Self {translation: Vec2::zero(), rotation: Angle::zero(), scale: Vec2::splat(1.0)}
 	}
 }
 
 impl Transform {
-	pub fn new(translation: [f32; 2], rotation: f32, scale: [f32; 2]) -> Self {
-		Self {translation: translation, rotation: rotation, scale: scale}
+	pub fn new(translation: Vec2, rotation: Angle, scale: Vec2) -> Self {
+		Self {translation: translation, rotation: rotation.normalized(), scale: scale}
 	}
 
-	pub fn rotate(&mut self, angle: f32) {
-		self.rotation = (self.rotation + angle) % MAX_ROTATION;
+	/// Rotate further, the rotation stays normalized
+	pub fn rotate(&mut self, angle: Angle) {
+		self.rotation = (self.rotation + angle).normalized();
 	}
 
-	pub fn set_rotation(&mut self, angle: f32) {
-		debug_assert!(angle >= 0.0 && angle <= MAX_ROTATION, "Rotation angle out of bounds!");
-		self.rotation = angle;
+	pub fn set_rotation(&mut self, angle: Angle) {
+		self.rotation = angle.normalized();
 	}
 
-	pub fn translate(&mut self, delta: [f32; 2]) {
-		self.translation[0] += delta[0];
-		self.translation[1] += delta[1];
+	pub fn translate(&mut self, delta: Vec2) {
+		self.translation += delta;
 	}
 
-	pub fn set_position(&mut self, position: [f32; 2]) {
+	pub fn set_position(&mut self, position: Vec2) {
 		self.translation = position;
 	}
 
-	pub fn set_scale(&mut self, scale: [f32; 2]) {
+	pub fn set_scale(&mut self, scale: Vec2) {
 		self.scale = scale;
 	}
+
+	/// Move a point from object space into the space the transform is in
+	///
+	/// Matches instanced.vert: rotate around the origin, scale along the outer axes, then translate.
+	pub fn transform_point(&self, point: Vec2) -> Vec2 {
+		point.rotate(self.rotation) * self.scale + self.translation
+	}
+
+	/// Move a point from the space the transform is in into object space, inverse of transform_point()
+	pub fn inverse_transform_point(&self, point: Vec2) -> Vec2 {
+		((point - self.translation) / self.scale).rotate(-self.rotation)
+	}
+
+	/// Matrix doing the same as transform_point()
+	pub fn matrix(&self) -> Mat3 {
+		Mat3::from(self)
+	}
+
+	/// Transform of a child given in the space of this transform, expressed in the space this transform is in
+	///
+	/// Rotation and scale can only be combined exactly if the child's scale is uniform or this rotation is
+	/// a multiple of a half turn, otherwise the child gets scaled along the outer axes instead of being sheared.
+	pub fn compose(&self, child: &Transform) -> Transform {
+		Transform {
+			translation: self.transform_point(child.translation),
+			rotation: (self.rotation + child.rotation).normalized(),
+			scale: self.scale * child.scale,
+		}
+	}
+}
+
+/// Corners of the transformed unit quad, counter-clockwise starting at the bottom left
+///
+/// The corners are oriented with the rotation, use Rect::from_bounds() for an axis-aligned box around them.
+impl Boundable for Transform {
+	fn bounds(&self) -> Bounds {
+		[[-0.5, -0.5], [0.5, -0.5], [0.5, 0.5], [-0.5, 0.5]]
+			.iter()
+			.map(|&corner| self.transform_point(Vec2::from(corner)))
+			.collect()
+	}
 }
 
 impl Lerp for Transform {
 	fn lerp(a: &Self, b: &Self, t: f32) -> Self {
 		Self {translation: Lerp::lerp(&a.translation, &b.translation, t), rotation: Lerp::lerp(&a.rotation, &b.rotation, t), scale: Lerp::lerp(&a.scale, &b.scale, t)}
 	}
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn bounds_are_rotated_corners() {
+		let transform = Transform::new(Vec2::new(10.0, 0.0), Angle::from_degrees(90.0), Vec2::new(2.0, 1.0));
+		let corners = transform.bounds();
+		let expected = [[11.0, -0.5], [11.0, 0.5], [9.0, 0.5], [9.0, -0.5]];
+		assert_eq!(corners.len(), expected.len());
+		for (corner, expected) in corners.iter().zip(expected.iter()) {
+			assert!((*corner - Vec2::from(*expected)).length() < 1e-5, "{:?} {:?}", corners, expected);
+		}
+
+		// Counter-clockwise, starting at the corner that is bottom left in object space
+		let mut area = 0.0;
+		for (index, corner) in corners.iter().enumerate() {
+			area += corner.cross(corners[(index + 1) % corners.len()]);
+		}
+		assert!(area > 0.0);
+		assert_eq!(corners[0], transform.transform_point(Vec2::new(-0.5, -0.5)));
+	}
+}
@@ -0,0 +1,392 @@
+// 2D vectors and affine matrices
+//
+// Both convert into glium uniform values, so positions and transformations computed on the CPU are passed to
+// shaders as is. Matrices are stored column-major like GLSL expects them.
+
+use super::angle::Angle;
+use super::math::Lerp;
+use super::transform::Transform;
+
+use glium::uniforms::{AsUniformValue, UniformValue};
+
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// A point or a direction, serialized as [x, y]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "[f32; 2]", into = "[f32; 2]")]
+pub struct Vec2 {
+	pub x: f32,
+	pub y: f32,
+}
+
+impl Vec2 {
+	pub fn new(x: f32, y: f32) -> Self {
+		Self {x: x, y: y}
+	}
+
+	pub fn zero() -> Self {
+		Self {x: 0.0, y: 0.0}
+	}
+
+	/// Both components set to the same value
+	pub fn splat(value: f32) -> Self {
+		Self {x: value, y: value}
+	}
+
+	pub fn dot(self, other: Vec2) -> f32 {
+		self.x * other.x + self.y * other.y
+	}
+
+	/// Z component of the 3D cross product, positive if other is counter-clockwise from this vector
+	pub fn cross(self, other: Vec2) -> f32 {
+		self.x * other.y - self.y * other.x
+	}
+
+	pub fn length(self) -> f32 {
+		self.length_squared().sqrt()
+	}
+
+	pub fn length_squared(self) -> f32 {
+		self.dot(self)
+	}
+
+	pub fn distance(self, other: Vec2) -> f32 {
+		(other - self).length()
+	}
+
+	/// Vector of length 1 in the same direction, zero vectors stay zero
+	pub fn normalize(self) -> Self {
+		let length = self.length();
+		if length > 0.0 {
+			self / length
+		} else {
+			Vec2::zero()
+		}
+	}
+
+	/// Rotate counter-clockwise around the origin
+	pub fn rotate(self, angle: Angle) -> Self {
+		let (sin, cos) = angle.sin_cos();
+		Vec2::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+	}
+
+	/// Rotated a quarter turn counter-clockwise
+	pub fn perpendicular(self) -> Self {
+		Vec2::new(-self.y, self.x)
+	}
+
+	/// Angle from the x axis, within [-π, π]
+	pub fn angle(self) -> Angle {
+		Angle::from_radians(self.y.atan2(self.x))
+	}
+
+	pub fn min(self, other: Vec2) -> Self {
+		Vec2::new(self.x.min(other.x), self.y.min(other.y))
+	}
+
+	pub fn max(self, other: Vec2) -> Self {
+		Vec2::new(self.x.max(other.x), self.y.max(other.y))
+	}
+}
+
+impl From<[f32; 2]> for Vec2 {
+	fn from(array: [f32; 2]) -> Self {
+		Vec2::new(array[0], array[1])
+	}
+}
+
+impl From<Vec2> for [f32; 2] {
+	fn from(vector: Vec2) -> Self {
+		[vector.x, vector.y]
+	}
+}
+
+impl AsUniformValue for Vec2 {
+	fn as_uniform_value(&self) -> UniformValue {
+		UniformValue::Vec2([self.x, self.y])
+	}
+}
+
+impl Lerp for Vec2 {
+	fn lerp(a: &Self, b: &Self, t: f32) -> Self {
+		*a + (*b - *a) * t
+	}
+}
+
+impl Add for Vec2 {
+	type Output = Vec2;
+
+	fn add(self, other: Vec2) -> Vec2 {
+		Vec2::new(self.x + other.x, self.y + other.y)
+	}
+}
+
+impl Sub for Vec2 {
+	type Output = Vec2;
+
+	fn sub(self, other: Vec2) -> Vec2 {
+		Vec2::new(self.x - other.x, self.y - other.y)
+	}
+}
+
+impl Neg for Vec2 {
+	type Output = Vec2;
+
+	fn neg(self) -> Vec2 {
+		Vec2::new(-self.x, -self.y)
+	}
+}
+
+impl Mul<f32> for Vec2 {
+	type Output = Vec2;
+
+	fn mul(self, factor: f32) -> Vec2 {
+		Vec2::new(self.x * factor, self.y * factor)
+	}
+}
+
+/// Component-wise, for scaling along both axes
+impl Mul<Vec2> for Vec2 {
+	type Output = Vec2;
+
+	fn mul(self, other: Vec2) -> Vec2 {
+		Vec2::new(self.x * other.x, self.y * other.y)
+	}
+}
+
+impl Div<f32> for Vec2 {
+	type Output = Vec2;
+
+	fn div(self, divisor: f32) -> Vec2 {
+		Vec2::new(self.x / divisor, self.y / divisor)
+	}
+}
+
+/// Component-wise, inverse of scaling
+impl Div<Vec2> for Vec2 {
+	type Output = Vec2;
+
+	fn div(self, other: Vec2) -> Vec2 {
+		Vec2::new(self.x / other.x, self.y / other.y)
+	}
+}
+
+impl AddAssign for Vec2 {
+	fn add_assign(&mut self, other: Vec2) {
+		self.x += other.x;
+		self.y += other.y;
+	}
+}
+
+impl SubAssign for Vec2 {
+	fn sub_assign(&mut self, other: Vec2) {
+		self.x -= other.x;
+		self.y -= other.y;
+	}
+}
+
+impl MulAssign<f32> for Vec2 {
+	fn mul_assign(&mut self, factor: f32) {
+		self.x *= factor;
+		self.y *= factor;
+	}
+}
+
+impl DivAssign<f32> for Vec2 {
+	fn div_assign(&mut self, divisor: f32) {
+		self.x /= divisor;
+		self.y /= divisor;
+	}
+}
+
+/// Affine transformation of the plane, the bottom row is always [0, 0, 1]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat3 {
+	columns: [[f32; 3]; 3],
+}
+
+impl Default for Mat3 {
+	fn default() -> Self {
+		Mat3::identity()
+	}
+}
+
+impl Mat3 {
+	pub fn identity() -> Self {
+		Self::from_columns(Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0), Vec2::zero())
+	}
+
+	/// Matrix mapping x and y axes onto the given vectors, then moving by translation
+	pub fn from_columns(x: Vec2, y: Vec2, translation: Vec2) -> Self {
+		Self {
+			columns: [[x.x, x.y, 0.0], [y.x, y.y, 0.0], [translation.x, translation.y, 1.0]],
+		}
+	}
+
+	pub fn translation(translation: Vec2) -> Self {
+		Self::from_columns(Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0), translation)
+	}
+
+	pub fn rotation(angle: Angle) -> Self {
+		let (sin, cos) = angle.sin_cos();
+		Self::from_columns(Vec2::new(cos, sin), Vec2::new(-sin, cos), Vec2::zero())
+	}
+
+	pub fn scale(scale: Vec2) -> Self {
+		Self::from_columns(Vec2::new(scale.x, 0.0), Vec2::new(0.0, scale.y), Vec2::zero())
+	}
+
+	pub fn x_axis(&self) -> Vec2 {
+		Vec2::new(self.columns[0][0], self.columns[0][1])
+	}
+
+	pub fn y_axis(&self) -> Vec2 {
+		Vec2::new(self.columns[1][0], self.columns[1][1])
+	}
+
+	pub fn get_translation(&self) -> Vec2 {
+		Vec2::new(self.columns[2][0], self.columns[2][1])
+	}
+
+	/// Determinant of the linear part, zero if the matrix collapses the plane
+	pub fn determinant(&self) -> f32 {
+		self.x_axis().cross(self.y_axis())
+	}
+
+	/// The transformation undoing this one, None if it can't be undone
+	pub fn inverse(&self) -> Option<Mat3> {
+		let determinant = self.determinant();
+		if determinant == 0.0 || !determinant.is_finite() {
+			return None;
+		}
+
+		let (x, y) = (self.x_axis(), self.y_axis());
+		let inverse_x = Vec2::new(y.y, -x.y) / determinant;
+		let inverse_y = Vec2::new(-y.x, x.x) / determinant;
+		let translation = self.get_translation();
+		let inverse_translation = -(inverse_x * translation.x + inverse_y * translation.y);
+		Some(Self::from_columns(inverse_x, inverse_y, inverse_translation))
+	}
+
+	pub fn transform_point(&self, point: Vec2) -> Vec2 {
+		self.transform_vector(point) + self.get_translation()
+	}
+
+	/// Transform a direction, translation doesn't affect it
+	pub fn transform_vector(&self, vector: Vec2) -> Vec2 {
+		self.x_axis() * vector.x + self.y_axis() * vector.y
+	}
+
+	/// Column-major, as glium expects matrices
+	pub fn to_columns(&self) -> [[f32; 3]; 3] {
+		self.columns
+	}
+}
+
+/// Same order as instanced.vert: rotate around the origin, scale along the outer axes, then translate
+impl<'a> From<&'a Transform> for Mat3 {
+	fn from(transform: &Transform) -> Self {
+		Mat3::translation(transform.translation) * Mat3::scale(transform.scale) * Mat3::rotation(transform.rotation)
+	}
+}
+
+/// Composition, the right-hand side is applied first
+impl Mul for Mat3 {
+	type Output = Mat3;
+
+	fn mul(self, other: Mat3) -> Mat3 {
+		Mat3::from_columns(
+			self.transform_vector(other.x_axis()),
+			self.transform_vector(other.y_axis()),
+			self.transform_point(other.get_translation()),
+		)
+	}
+}
+
+impl MulAssign for Mat3 {
+	fn mul_assign(&mut self, other: Mat3) {
+		*self = *self * other;
+	}
+}
+
+impl AsUniformValue for Mat3 {
+	fn as_uniform_value(&self) -> UniformValue {
+		UniformValue::Mat3(self.columns)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const EPSILON: f32 = 1e-4;
+
+	fn close(a: Vec2, b: Vec2) -> bool {
+		(a - b).length() < EPSILON
+	}
+
+	fn close_matrix(a: Mat3, b: Mat3) -> bool {
+		close(a.x_axis(), b.x_axis()) && close(a.y_axis(), b.y_axis()) && close(a.get_translation(), b.get_translation())
+	}
+
+	fn transforms() -> Vec<Transform> {
+		vec![
+			Transform::new(Vec2::zero(), Angle::zero(), Vec2::splat(1.0)),
+			Transform::new(Vec2::new(3.0, -2.0), Angle::from_degrees(30.0), Vec2::splat(2.0)),
+			Transform::new(Vec2::new(-1.0, 4.0), Angle::from_degrees(135.0), Vec2::new(2.0, 0.5)),
+			Transform::new(Vec2::new(0.5, 0.5), Angle::from_degrees(-60.0), Vec2::new(-1.0, 3.0)),
+		]
+	}
+
+	const POINTS: [[f32; 2]; 4] = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [-2.5, 1.5]];
+
+	#[test]
+	fn matrix_matches_transform() {
+		for transform in transforms().iter() {
+			let matrix = Mat3::from(transform);
+			for &point in POINTS.iter() {
+				let point = Vec2::from(point);
+				assert!(close(matrix.transform_point(point), transform.transform_point(point)), "{:?} {:?}", transform, point);
+			}
+		}
+	}
+
+	#[test]
+	fn inverse_undoes_matrix() {
+		for transform in transforms().iter() {
+			let matrix = Mat3::from(transform);
+			let inverse = matrix.inverse().unwrap();
+			assert!(close_matrix(matrix * inverse, Mat3::identity()), "{:?}", transform);
+			assert!(close_matrix(inverse * matrix, Mat3::identity()), "{:?}", transform);
+			for &point in POINTS.iter() {
+				let point = Vec2::from(point);
+				assert!(close(inverse.transform_point(point), transform.inverse_transform_point(point)), "{:?}", transform);
+			}
+		}
+	}
+
+	#[test]
+	fn zero_scale_has_no_inverse() {
+		for &scale in [[0.0, 1.0], [1.0, 0.0], [0.0, 0.0]].iter() {
+			let transform = Transform::new(Vec2::new(1.0, 2.0), Angle::from_degrees(45.0), Vec2::from(scale));
+			assert_eq!(Mat3::from(&transform).inverse(), None, "{:?}", scale);
+		}
+	}
+
+	#[test]
+	fn right_hand_side_is_applied_first() {
+		let translation = Mat3::translation(Vec2::new(1.0, 0.0));
+		let scale = Mat3::scale(Vec2::splat(2.0));
+		let rotation = Mat3::rotation(Angle::from_degrees(90.0));
+		let point = Vec2::new(1.0, 0.0);
+
+		assert!(close((translation * scale).transform_point(point), Vec2::new(3.0, 0.0)));
+		assert!(close((scale * translation).transform_point(point), Vec2::new(4.0, 0.0)));
+		assert!(close((translation * rotation).transform_point(point), Vec2::new(1.0, 1.0)));
+		assert!(close((rotation * translation).transform_point(point), Vec2::new(0.0, 2.0)));
+
+		let mut combined = translation;
+		combined *= rotation;
+		assert_eq!(combined, translation * rotation);
+	}
+}
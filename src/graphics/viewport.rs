@@ -0,0 +1,117 @@
+// Mapping between the resolution the scene is rendered at and the window it is presented in
+
+use config::Configuration;
+
+use super::vector::Vec2;
+
+/// How the scene resolution relates to the window resolution
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderMode {
+	Native,       // Render at window resolution multiplied by the resolution scale
+	PixelPerfect, // Render at a fixed virtual resolution, upscale by a whole factor and letterbox the rest
+}
+
+/// Settings the viewport is computed from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+	pub mode: RenderMode,
+	pub virtual_resolution: (u32, u32),
+	pub resolution_scale: f32, // only used in Native mode, values below 1 trade sharpness for performance
+	pub max_render_size: (u32, u32), // Largest scene target the GPU supports, the resolution is clamped to it
+}
+
+impl RenderSettings {
+	pub fn from_config(config: &Configuration, max_render_size: (u32, u32)) -> Self {
+		Self {
+			mode: config.render_mode,
+			virtual_resolution: config.virtual_resolution,
+			resolution_scale: config.resolution_scale,
+			max_render_size: max_render_size,
+		}
+	}
+}
+
+/// Where and at which resolution the scene ends up in the window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+	pub render_size: (u32, u32),    // Resolution of the scene target
+	pub present_rect: glium::Rect,  // Area of the window the scene is presented into, in pixels from bottom-left
+	pub window_size: (u32, u32),
+	pub nearest_filtering: bool,    // Should the scene be upscaled without smoothing
+}
+
+impl Viewport {
+	pub fn new(settings: &RenderSettings, window_size: (u32, u32)) -> Self {
+		let window_size = (window_size.0.max(1), window_size.1.max(1));
+		let max_size = (settings.max_render_size.0.max(1), settings.max_render_size.1.max(1));
+
+		match settings.mode {
+			RenderMode::Native => {
+				// Scaled down further when the target wouldn't fit, keeping the aspect ratio of the window
+				let limit = (max_size.0 as f32 / window_size.0 as f32).min(max_size.1 as f32 / window_size.1 as f32);
+				let scale = settings.resolution_scale.max(0.01).min(limit);
+				Self {
+					render_size: (
+						((window_size.0 as f32 * scale).round() as u32).max(1).min(max_size.0),
+						((window_size.1 as f32 * scale).round() as u32).max(1).min(max_size.1),
+					),
+					present_rect: glium::Rect {
+						left: 0,
+						bottom: 0,
+						width: window_size.0,
+						height: window_size.1,
+					},
+					window_size: window_size,
+					nearest_filtering: false,
+				}
+			}
+			RenderMode::PixelPerfect => {
+				let virtual_size = (
+					settings.virtual_resolution.0.max(1).min(max_size.0),
+					settings.virtual_resolution.1.max(1).min(max_size.1),
+				);
+				let factor = (window_size.0 / virtual_size.0)
+					.min(window_size.1 / virtual_size.1)
+					.max(1);
+				let width = virtual_size.0 * factor;
+				let height = virtual_size.1 * factor;
+
+				// Window might be smaller than the virtual resolution, in which case the scene gets cropped
+				Self {
+					render_size: virtual_size,
+					present_rect: glium::Rect {
+						left: window_size.0.saturating_sub(width) / 2,
+						bottom: window_size.1.saturating_sub(height) / 2,
+						width: width,
+						height: height,
+					},
+					window_size: window_size,
+					nearest_filtering: true,
+				}
+			}
+		}
+	}
+
+	/// Is the scene rendered straight at window resolution, covering the whole window
+	pub fn is_native(&self) -> bool {
+		self.render_size == self.window_size
+			&& self.present_rect.left == 0
+			&& self.present_rect.bottom == 0
+			&& (self.present_rect.width, self.present_rect.height) == self.window_size
+	}
+
+	/// Convert normalized window coordinates (y pointing down) into normalized scene coordinates
+	///
+	/// Points on letterboxing bars end up outside of [0, 1].
+	pub fn window_to_scene(&self, normalized_window: Vec2) -> Vec2 {
+		let x = normalized_window.x * self.window_size.0 as f32;
+		let y = normalized_window.y * self.window_size.1 as f32;
+		let top = self.window_size.1 as f32 - (self.present_rect.bottom + self.present_rect.height) as f32;
+
+		Vec2::new(
+			(x - self.present_rect.left as f32) / self.present_rect.width as f32,
+			(y - top) / self.present_rect.height as f32,
+		)
+	}
+}
@@ -1,6 +1,8 @@
 // Input handler module
 pub use glium::glutin::ScanCode;
 
+use graphics::vector::Vec2;
+
 use std::collections::HashMap as Map;
 
 /// A bit-field for key modifiers
@@ -18,6 +20,10 @@ pub const MODIFIER_CTRL: KeyModifiers = 0x4;
 pub const MODIFIER_LOGO: KeyModifiers = 0x8;
 
 // Ordinary scancodes (will get added as necessary)
+pub const SCANCODE_F3: ScanCode = 0x3D;
+pub const SCANCODE_F4: ScanCode = 0x3E;
+pub const SCANCODE_F5: ScanCode = 0x3F;
+pub const SCANCODE_F9: ScanCode = 0x43;
 pub const SCANCODE_F11: ScanCode = 0x57;
 
 /// An action identifier that can be caused by Input
@@ -26,6 +32,10 @@ pub enum Action {
 	None, // Action invariant, no action actually needs to be performed
 
 	ToggleFullscreen,
+	ToggleProfiler, // Show or hide frame statistics
+	ExportTrace,    // Write recently profiled scopes into a trace file
+	QuickSave,      // Save the top scene into the quick save slot
+	QuickLoad,      // Replace the top scene with the quick save slot
 }
 
 /// Action executed on mouse wheel movement
@@ -200,6 +210,34 @@ impl Input {
 			},
 			Action::ToggleFullscreen,
 		);
+		input.set_on_key_up(
+			Key {
+				scancode: SCANCODE_F3,
+				modifiers: MODIFIER_NONE,
+			},
+			Action::ToggleProfiler,
+		);
+		input.set_on_key_up(
+			Key {
+				scancode: SCANCODE_F4,
+				modifiers: MODIFIER_NONE,
+			},
+			Action::ExportTrace,
+		);
+		input.set_on_key_up(
+			Key {
+				scancode: SCANCODE_F5,
+				modifiers: MODIFIER_NONE,
+			},
+			Action::QuickSave,
+		);
+		input.set_on_key_up(
+			Key {
+				scancode: SCANCODE_F9,
+				modifiers: MODIFIER_NONE,
+			},
+			Action::QuickLoad,
+		);
 
 		input.set_on_wheel_delta(MODIFIER_NONE, WheelAction::ChangeViewSize);
 		input.set_on_wheel_delta(MODIFIER_SHIFT, WheelAction::ChangeViewSharpness);
@@ -382,11 +420,11 @@ impl Input {
 	}
 
 	/// Get normalized mouse coordinates in window-space
-	pub fn relative_mouse_position(&self) -> [f32; 2] {
-		[
+	pub fn relative_mouse_position(&self) -> Vec2 {
+		Vec2::new(
 			self.mouse_position.0 / self.viewport_size.0,
 			self.mouse_position.1 / self.viewport_size.1,
-		]
+		)
 	}
 
 	/// Get mouse wheel coordinate
@@ -0,0 +1,743 @@
+// Levels loaded from scene files
+//
+// A scene file names every texture and material it uses instead of storing atlas areas or handles,
+// so files stay valid when the atlas layout or material load order changes.
+// Objects are either described in place or placed from prefabs.
+
+use SCENE_PREFIX;
+use TEXTURE_PREFIX;
+
+use ecs::components::{Parent, Sprite, Transform, Velocity};
+use ecs::systems::{self, HierarchySystem, MovementSystem};
+use ecs::{Entity, Schedule, World};
+use graphics::instance::{Instance, SpriteEffects};
+use graphics::material::{MaterialError, MaterialID, DEFAULT_MATERIAL};
+use prefab::{Placement, PrefabCollection, PrefabError, PrefabInstance};
+use random::Random;
+use save::{EntitySnapshot, ParentSnapshot, Snapshot, SAVE_VERSION};
+use graphics::math::Rect;
+use graphics::scene::{Background, BackgroundFit, ParallaxLayer, Scene};
+use graphics::texture::{GLTexture, Texture, TextureCollection, TextureCollectionCreationError, TextureID};
+use graphics::vector::Vec2;
+use graphics::Graphics;
+use input::WheelAction;
+use scene_stack::{GameScene, Transition};
+use timestep;
+use tween::Tweens;
+
+use std::collections::HashMap as Map;
+use std::io::Error as IoError;
+use std::path::Path;
+
+pub const SCENE_EXTENSION: &str = ".yml";
+
+/// Contents of a scene file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SceneFile {
+	pub textures: Vec<TextureID>, // Every texture used by objects, in atlas order
+	pub view_rect: Rect,
+	#[serde(default = "default_preserve_ratio")]
+	pub preserve_ratio: bool,
+	#[serde(default)]
+	pub lighting: Lighting,
+	#[serde(default)]
+	pub objects: Vec<ObjectDescription>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub placements: Vec<Placement>, // Textures used by prefabs are added to the atlas automatically
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub background: Option<BackgroundDescription>, // Textures of the background and parallax layers are added
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub parallax_layers: Vec<ParallaxLayerDescription>, // to the atlas automatically as well
+}
+
+/// Vision parameters of a scene, see Scene::view_origin()
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Lighting {
+	pub view_origin: Vec2,
+	pub view_distance: f32,
+	pub view_sharpness: f32,
+}
+
+impl Default for Lighting {
+	fn default() -> Self {
+		Self {
+			view_origin: Vec2::zero(),
+			view_distance: std::f32::INFINITY,
+			view_sharpness: 1.0,
+		}
+	}
+}
+
+/// A drawable object, the serializable form of an Instance
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ObjectDescription {
+	#[serde(default)]
+	pub transform: Transform,
+	#[serde(flatten)]
+	pub sprite: SpriteDescription,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub velocity: Option<Velocity>,
+}
+
+/// How an object is drawn, the serializable form of a Sprite
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpriteDescription {
+	pub texture_lit: TextureID,
+	pub texture_unlit: TextureID,
+	#[serde(default = "default_color")]
+	pub color_lit: [f32; 4],
+	#[serde(default = "default_color")]
+	pub color_unlit: [f32; 4],
+	#[serde(default = "default_material")]
+	pub material: MaterialID,
+	#[serde(default)]
+	pub effects: SpriteEffects,
+}
+
+impl SpriteDescription {
+	/// First texture that is not part of the collection
+	pub fn missing_texture(&self, textures: &TextureCollection) -> Option<&TextureID> {
+		[&self.texture_lit, &self.texture_unlit]
+			.iter()
+			.find(|texture| textures.get(texture).is_none())
+			.cloned()
+	}
+
+	/// Create the sprite, loading its material if needed
+	///
+	/// Panics if a texture is not part of the collection, see missing_texture().
+	pub fn build(&self, graphics: &mut Graphics, textures: &TextureCollection) -> Result<Sprite, MaterialError> {
+		let mut sprite = Sprite::new(
+			textures.get(&self.texture_lit).expect("sprite texture is not loaded!"),
+			textures.get(&self.texture_unlit).expect("sprite texture is not loaded!"),
+			self.color_lit,
+			self.color_unlit,
+		);
+		sprite.material = graphics.load_material(&self.material)?;
+		sprite.effects = self.effects;
+		Ok(sprite)
+	}
+}
+
+/// The backmost part of the scene, the serializable form of a Background
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackgroundDescription {
+	Color([f32; 4]),
+	VerticalGradient {
+		top: [f32; 4],
+		bottom: [f32; 4],
+	},
+	Texture {
+		texture: TextureID,
+		#[serde(default = "default_fit")]
+		fit: BackgroundFit,
+		#[serde(default = "default_color")]
+		color: [f32; 4],
+	},
+}
+
+impl BackgroundDescription {
+	pub fn texture(&self) -> Option<&TextureID> {
+		match self {
+			BackgroundDescription::Texture { texture, .. } => Some(texture),
+			_ => None,
+		}
+	}
+
+	/// Panics if the texture is not part of the collection
+	pub fn build(&self, textures: &TextureCollection) -> Background {
+		match self {
+			BackgroundDescription::Color(color) => Background::Color(*color),
+			BackgroundDescription::VerticalGradient { top, bottom } => Background::VerticalGradient {
+				top: *top,
+				bottom: *bottom,
+			},
+			BackgroundDescription::Texture { texture, fit, color } => Background::Texture {
+				texture: textures.get(texture).expect("background texture is not loaded!"),
+				fit: *fit,
+				color: *color,
+			},
+		}
+	}
+}
+
+/// A scrolling background layer, the serializable form of a ParallaxLayer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ParallaxLayerDescription {
+	pub texture: TextureID,
+	pub scroll_factor: Vec2,
+	#[serde(default)]
+	pub offset: Vec2,
+	pub size: Vec2,
+	#[serde(default = "default_repeat")]
+	pub repeat: [bool; 2],
+	#[serde(default = "default_color")]
+	pub color: [f32; 4],
+}
+
+impl ParallaxLayerDescription {
+	/// Panics if the texture is not part of the collection
+	pub fn build(&self, textures: &TextureCollection) -> ParallaxLayer {
+		ParallaxLayer {
+			texture: textures.get(&self.texture).expect("parallax layer texture is not loaded!"),
+			scroll_factor: self.scroll_factor,
+			offset: self.offset,
+			size: self.size,
+			repeat: self.repeat,
+			color: self.color,
+		}
+	}
+}
+
+/// Textures of the background and parallax layers
+fn backdrop_textures<'a>(
+	background: &'a Option<BackgroundDescription>,
+	parallax_layers: &'a [ParallaxLayerDescription],
+) -> Vec<&'a TextureID> {
+	let background = background.iter().filter_map(|background| background.texture());
+	background.chain(parallax_layers.iter().map(|layer| &layer.texture)).collect()
+}
+
+/// Add a texture to the atlas unless it is already there, checking that its file exists
+fn add_to_atlas(atlas: &mut Vec<TextureID>, texture: &TextureID) -> Result<(), SceneFileError> {
+	if !atlas.contains(texture) {
+		if !Path::new(&(String::from(TEXTURE_PREFIX) + texture)).is_file() {
+			return Err(SceneFileError::MissingTexture(texture.clone()));
+		}
+		atlas.push(texture.clone());
+	}
+	Ok(())
+}
+
+fn default_preserve_ratio() -> bool {
+	true
+}
+
+fn default_color() -> [f32; 4] {
+	[1.0, 1.0, 1.0, 1.0]
+}
+
+fn default_material() -> MaterialID {
+	String::from(DEFAULT_MATERIAL)
+}
+
+fn default_fit() -> BackgroundFit {
+	BackgroundFit::Stretch
+}
+
+fn default_repeat() -> [bool; 2] {
+	[true, true]
+}
+
+#[derive(Debug)]
+pub enum SceneFileError {
+	Io(IoError),                                             // Something went wrong trying to read or write the scene file
+	Yaml(serde_yaml::Error),                                 // Scene file is not a valid description
+	UndeclaredTexture { object: usize, texture: TextureID }, // Object uses a texture missing from the texture list
+	MissingTexture(TextureID),                               // Texture file doesn't exist
+	UnnamedTexture { object: usize },                        // Object uses a texture that doesn't belong to the scene
+	UnnamedMaterial { object: usize },                       // Object uses a material that wasn't loaded by name
+	Texture(TextureCollectionCreationError),                 // Failed to load the texture atlas
+	Material(MaterialError),                                 // Failed to load an object material
+	Prefab(PrefabError),                                     // Failed to load or place a prefab
+}
+
+impl std::fmt::Display for SceneFileError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			SceneFileError::Io(error) => write!(f, "(IO) {}", error),
+			SceneFileError::Yaml(error) => write!(f, "(Yaml) {}", error),
+			SceneFileError::UndeclaredTexture { object, texture } => {
+				write!(f, "object {} uses texture {} which is not in the texture list", object, texture)
+			}
+			SceneFileError::MissingTexture(texture) => write!(f, "texture {}{} doesn't exist", TEXTURE_PREFIX, texture),
+			SceneFileError::UnnamedTexture { object } => write!(f, "object {} uses a texture without a name", object),
+			SceneFileError::UnnamedMaterial { object } => write!(f, "object {} uses a material without a name", object),
+			SceneFileError::Texture(error) => write!(f, "(Texture) {:?}", error),
+			SceneFileError::Material(error) => write!(f, "(Material) {}", error),
+			SceneFileError::Prefab(error) => write!(f, "(Prefab) {}", error),
+		}
+	}
+}
+
+impl From<IoError> for SceneFileError {
+	fn from(error: IoError) -> Self {
+		SceneFileError::Io(error)
+	}
+}
+
+impl From<serde_yaml::Error> for SceneFileError {
+	fn from(error: serde_yaml::Error) -> Self {
+		SceneFileError::Yaml(error)
+	}
+}
+
+impl From<TextureCollectionCreationError> for SceneFileError {
+	fn from(error: TextureCollectionCreationError) -> Self {
+		SceneFileError::Texture(error)
+	}
+}
+
+impl From<MaterialError> for SceneFileError {
+	fn from(error: MaterialError) -> Self {
+		SceneFileError::Material(error)
+	}
+}
+
+impl From<PrefabError> for SceneFileError {
+	fn from(error: PrefabError) -> Self {
+		SceneFileError::Prefab(error)
+	}
+}
+
+fn scene_path(name: &str) -> String {
+	String::from(SCENE_PREFIX) + name + SCENE_EXTENSION
+}
+
+impl SceneFile {
+	/// Read a scene from SCENE_PREFIX, the name is given without extension
+	///
+	/// Textures are only checked once the scene is built, see validate().
+	pub fn load(name: &str) -> Result<Self, SceneFileError> {
+		let file = std::fs::File::open(Path::new(&scene_path(name)))?;
+		Ok(serde_yaml::from_reader(file)?)
+	}
+
+	pub fn save(&self, name: &str) -> Result<(), SceneFileError> {
+		std::fs::create_dir_all(SCENE_PREFIX)?;
+		std::fs::write(scene_path(name), serde_yaml::to_string(self)?)?;
+		Ok(())
+	}
+
+	/// Check that every texture is declared and exists on disk
+	pub fn validate(&self) -> Result<(), SceneFileError> {
+		for texture in self.textures.iter() {
+			if !Path::new(&(String::from(TEXTURE_PREFIX) + texture)).is_file() {
+				return Err(SceneFileError::MissingTexture(texture.clone()));
+			}
+		}
+
+		for (index, object) in self.objects.iter().enumerate() {
+			for texture in [&object.sprite.texture_lit, &object.sprite.texture_unlit].iter() {
+				if !self.textures.contains(texture) {
+					return Err(SceneFileError::UndeclaredTexture {
+						object: index,
+						texture: (*texture).clone(),
+					});
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+/// A playable scene built from a scene file
+pub struct LevelScene {
+	pub world: World,
+	schedule: Schedule,
+	objects: Vec<(Entity, Instance)>,
+	previous_objects: Vec<(Entity, Instance)>,
+	interpolated_objects: Vec<Instance>,
+
+	pub name: String,          // Scene file the level was loaded from
+	pub playtime: f64,         // Simulated seconds
+	pub random: Random,
+	pub tweens: Tweens<World>, // Advanced after the schedule, not stored in snapshots
+	textures: Vec<TextureID>,  // Declared by the scene file, prefab textures are not included
+	atlas: Vec<TextureID>,     // Every texture of the texture collection, in atlas order
+	texture_collection: TextureCollection,
+	prefabs: PrefabCollection,
+	pub view_rect: Rect,
+	pub preserve_ratio: bool,
+	pub lighting: Lighting,
+	background_description: Option<BackgroundDescription>, // As written to scene files and snapshots
+	parallax_descriptions: Vec<ParallaxLayerDescription>,
+	background: Background,
+	parallax_layers: Vec<ParallaxLayer>,
+}
+
+impl LevelScene {
+	/// Load a scene file together with its textures and materials
+	pub fn load(graphics: &mut Graphics, name: &str, random: Random) -> Result<Self, SceneFileError> {
+		let mut level = Self::from_file(graphics, SceneFile::load(name)?, random)?;
+		level.name = String::from(name);
+		Ok(level)
+	}
+
+	pub fn from_file(graphics: &mut Graphics, file: SceneFile, random: Random) -> Result<Self, SceneFileError> {
+		file.validate()?;
+
+		let mut prefabs = PrefabCollection::new();
+		let mut atlas = file.textures.clone();
+		for placement in file.placements.iter() {
+			prefabs.load(&placement.prefab)?;
+			for texture in prefabs.textures(placement) {
+				add_to_atlas(&mut atlas, &texture)?;
+			}
+		}
+		for texture in backdrop_textures(&file.background, &file.parallax_layers) {
+			add_to_atlas(&mut atlas, texture)?;
+		}
+
+		let names: Vec<&str> = atlas.iter().map(|name| name.as_str()).collect();
+		let texture_collection = TextureCollection::new(graphics, &names)?;
+
+		let mut world = Self::empty_world();
+		for object in file.objects {
+			let sprite = object.sprite.build(graphics, &texture_collection)?;
+			let entity = world.build().with(object.transform).with(sprite).entity();
+			if let Some(velocity) = object.velocity {
+				world.insert(entity, velocity);
+			}
+		}
+		for placement in file.placements.iter() {
+			prefabs.instantiate(&mut world, graphics, &texture_collection, placement)?;
+		}
+		let background = file.background.as_ref().map(|background| background.build(&texture_collection));
+		let parallax_layers = file.parallax_layers.iter().map(|layer| layer.build(&texture_collection)).collect();
+
+		let mut level = Self {
+			world: world,
+			schedule: Self::schedule(),
+			objects: Vec::new(),
+			previous_objects: Vec::new(),
+			interpolated_objects: Vec::new(),
+
+			name: String::new(),
+			playtime: 0.0,
+			random: random,
+			tweens: Tweens::new(),
+			textures: file.textures,
+			atlas: atlas,
+			texture_collection: texture_collection,
+			prefabs: prefabs,
+			view_rect: file.view_rect,
+			preserve_ratio: file.preserve_ratio,
+			lighting: file.lighting,
+			background_description: file.background,
+			parallax_descriptions: file.parallax_layers,
+			background: background.unwrap_or_default(),
+			parallax_layers: parallax_layers,
+		};
+		level.reset_objects();
+		Ok(level)
+	}
+
+	fn empty_world() -> World {
+		let mut world = World::new();
+		world.register::<Transform>();
+		world.register::<Sprite>();
+		world.register::<Velocity>();
+		world.register::<Parent>();
+		world.register::<PrefabInstance>();
+		world
+	}
+
+	fn schedule() -> Schedule {
+		Schedule::new().with(MovementSystem).with(HierarchySystem::new())
+	}
+
+	/// Extract objects from the world, there is no previous state to interpolate from
+	fn reset_objects(&mut self) {
+		systems::extract_instances(&self.world, &mut self.objects);
+		self.previous_objects.clone_from(&self.objects);
+		self.interpolated_objects = self.objects.iter().map(|&(_, ref instance)| instance.clone()).collect();
+	}
+
+	/// Spawn a prefab into the scene, its textures have to be part of the scene atlas
+	pub fn instantiate(&mut self, graphics: &mut Graphics, placement: &Placement) -> Result<Entity, PrefabError> {
+		self.prefabs.instantiate(&mut self.world, graphics, &self.texture_collection, placement)
+	}
+
+	/// Describe the current state of the scene, materials are named through graphics
+	///
+	/// Prefab instances are written back as placements, their children are not stored.
+	pub fn to_file(&self, graphics: &Graphics) -> Result<SceneFile, SceneFileError> {
+		let sprites = self.world.read::<Sprite>();
+		let transforms = self.world.read::<Transform>();
+		let velocities = self.world.read::<Velocity>();
+		let parents = self.world.read::<Parent>();
+		let instances = self.world.read::<PrefabInstance>();
+
+		let placements = instances
+			.join(&transforms)
+			.map(|(_, instance, transform)| instance.placement(transform))
+			.collect();
+
+		let mut objects = Vec::with_capacity(sprites.len());
+		for (entity, sprite) in sprites.iter() {
+			if parents.contains(entity) || instances.contains(entity) {
+				continue;
+			}
+			let transform = match transforms.get(entity) {
+				Some(transform) => transform.clone(),
+				None => continue,
+			};
+			// Errors name the position in the written objects list
+			let index = objects.len();
+			objects.push(ObjectDescription {
+				transform: transform,
+				sprite: self.describe_sprite(graphics, sprite, index)?,
+				velocity: velocities.get(entity).cloned(),
+			});
+		}
+
+		// Objects spawned at runtime may use textures the scene file didn't declare, such as prefab textures
+		let mut textures = self.textures.clone();
+		for object in objects.iter() {
+			for texture in [&object.sprite.texture_lit, &object.sprite.texture_unlit].iter() {
+				if !textures.contains(texture) {
+					textures.push((*texture).clone());
+				}
+			}
+		}
+
+		Ok(SceneFile {
+			textures: textures,
+			view_rect: self.view_rect,
+			preserve_ratio: self.preserve_ratio,
+			lighting: self.lighting.clone(),
+			objects: objects,
+			placements: placements,
+			background: self.background_description.clone(),
+			parallax_layers: self.parallax_descriptions.clone(),
+		})
+	}
+
+	/// Name textures and material of a sprite, index is used for errors
+	fn describe_sprite(&self, graphics: &Graphics, sprite: &Sprite, index: usize) -> Result<SpriteDescription, SceneFileError> {
+		let texture_id = |texture: &Texture| {
+			self.texture_collection
+				.find_id(texture)
+				.cloned()
+				.ok_or(SceneFileError::UnnamedTexture { object: index })
+		};
+
+		Ok(SpriteDescription {
+			texture_lit: texture_id(&sprite.texture_lit)?,
+			texture_unlit: texture_id(&sprite.texture_unlit)?,
+			color_lit: sprite.color_lit,
+			color_unlit: sprite.color_unlit,
+			material: match graphics.material_id(sprite.material) {
+				Some(id) => String::from(id),
+				None => return Err(SceneFileError::UnnamedMaterial { object: index }),
+			},
+			effects: sprite.effects,
+		})
+	}
+
+	/// Capture the full simulation state, materials are named through graphics
+	pub fn snapshot(&self, graphics: &Graphics) -> Result<Snapshot, SceneFileError> {
+		let entities: Vec<Entity> = self.world.entities().collect();
+		let indices: Map<Entity, usize> = entities.iter().enumerate().map(|(index, entity)| (*entity, index)).collect();
+
+		let transforms = self.world.read::<Transform>();
+		let sprites = self.world.read::<Sprite>();
+		let velocities = self.world.read::<Velocity>();
+		let parents = self.world.read::<Parent>();
+		let instances = self.world.read::<PrefabInstance>();
+
+		let mut snapshots = Vec::with_capacity(entities.len());
+		for (index, entity) in entities.iter().enumerate() {
+			let sprite = match sprites.get(*entity) {
+				Some(sprite) => Some(self.describe_sprite(graphics, sprite, index)?),
+				None => None,
+			};
+			snapshots.push(EntitySnapshot {
+				transform: transforms.get(*entity).cloned(),
+				sprite: sprite,
+				velocity: velocities.get(*entity).cloned(),
+				parent: parents.get(*entity).and_then(|parent| {
+					indices.get(&parent.entity).map(|index| ParentSnapshot {
+						index: *index,
+						local: parent.local.clone(),
+					})
+				}),
+				prefab: instances.get(*entity).cloned(),
+			});
+		}
+
+		Ok(Snapshot {
+			version: SAVE_VERSION,
+			scene: self.name.clone(),
+			textures: self.textures.clone(),
+			atlas: self.atlas.clone(),
+			view_rect: self.view_rect,
+			preserve_ratio: self.preserve_ratio,
+			lighting: self.lighting.clone(),
+			background: self.background_description.clone(),
+			parallax_layers: self.parallax_descriptions.clone(),
+			entities: snapshots,
+			playtime: self.playtime,
+			random: self.random.clone(),
+		})
+	}
+
+	/// Rebuild a level from a snapshot, entities are restored in the order they were captured
+	pub fn restore(graphics: &mut Graphics, snapshot: Snapshot) -> Result<Self, SceneFileError> {
+		for texture in snapshot.atlas.iter() {
+			if !Path::new(&(String::from(TEXTURE_PREFIX) + texture)).is_file() {
+				return Err(SceneFileError::MissingTexture(texture.clone()));
+			}
+		}
+		let mut atlas = snapshot.atlas;
+		for texture in backdrop_textures(&snapshot.background, &snapshot.parallax_layers) {
+			add_to_atlas(&mut atlas, texture)?;
+		}
+		let names: Vec<&str> = atlas.iter().map(|name| name.as_str()).collect();
+		let texture_collection = TextureCollection::new(graphics, &names)?;
+		let background = snapshot.background.as_ref().map(|background| background.build(&texture_collection));
+		let parallax_layers = snapshot.parallax_layers.iter().map(|layer| layer.build(&texture_collection)).collect();
+
+		let mut world = Self::empty_world();
+		let entities: Vec<Entity> = snapshot.entities.iter().map(|_| world.spawn()).collect();
+		for (index, (entity, components)) in entities.iter().zip(snapshot.entities).enumerate() {
+			if let Some(transform) = components.transform {
+				world.insert(*entity, transform);
+			}
+			if let Some(sprite) = components.sprite {
+				if let Some(texture) = sprite.missing_texture(&texture_collection) {
+					return Err(SceneFileError::UndeclaredTexture {
+						object: index,
+						texture: texture.clone(),
+					});
+				}
+				let sprite = sprite.build(graphics, &texture_collection)?;
+				world.insert(*entity, sprite);
+			}
+			if let Some(velocity) = components.velocity {
+				world.insert(*entity, velocity);
+			}
+			if let Some(parent) = components.parent {
+				if let Some(parent_entity) = entities.get(parent.index) {
+					world.insert(
+						*entity,
+						Parent {
+							entity: *parent_entity,
+							local: parent.local,
+						},
+					);
+				}
+			}
+			if let Some(instance) = components.prefab {
+				world.insert(*entity, instance);
+			}
+		}
+
+		let mut level = Self {
+			world: world,
+			schedule: Self::schedule(),
+			objects: Vec::new(),
+			previous_objects: Vec::new(),
+			interpolated_objects: Vec::new(),
+
+			name: snapshot.scene,
+			playtime: snapshot.playtime,
+			random: snapshot.random,
+			tweens: Tweens::new(),
+			textures: snapshot.textures,
+			atlas: atlas,
+			texture_collection: texture_collection,
+			prefabs: PrefabCollection::new(),
+			view_rect: snapshot.view_rect,
+			preserve_ratio: snapshot.preserve_ratio,
+			lighting: snapshot.lighting,
+			background_description: snapshot.background,
+			parallax_descriptions: snapshot.parallax_layers,
+			background: background.unwrap_or_default(),
+			parallax_layers: parallax_layers,
+		};
+		level.reset_objects();
+		Ok(level)
+	}
+
+	/// Write the current state of the scene into SCENE_PREFIX
+	pub fn save(&self, graphics: &Graphics, name: &str) -> Result<(), SceneFileError> {
+		self.to_file(graphics)?.save(name)
+	}
+
+	/// Advance the simulation by delta seconds
+	pub fn update(&mut self, delta: f32) {
+		std::mem::swap(&mut self.previous_objects, &mut self.objects);
+		self.schedule.run(&mut self.world, delta);
+		self.tweens.update(&mut self.world, delta);
+		systems::extract_instances(&self.world, &mut self.objects);
+		self.playtime += delta as f64;
+	}
+
+	/// Prepare objects for drawing, alpha is the position between the previous and the current state
+	pub fn interpolate(&mut self, alpha: f32) {
+		timestep::interpolate(&self.previous_objects, &self.objects, alpha, &mut self.interpolated_objects);
+	}
+}
+
+impl Scene for LevelScene {
+	fn object_instances(&self) -> &[Instance] {
+		&self.interpolated_objects
+	}
+
+	fn view_rect(&self) -> Rect {
+		self.view_rect
+	}
+
+	fn preserve_ratio(&self) -> bool {
+		self.preserve_ratio
+	}
+
+	fn view_origin(&self) -> Vec2 {
+		self.lighting.view_origin
+	}
+
+	fn view_distance(&self) -> f32 {
+		self.lighting.view_distance
+	}
+
+	fn view_sharpness(&self) -> f32 {
+		self.lighting.view_sharpness
+	}
+
+	fn background(&self) -> Background {
+		self.background.clone()
+	}
+
+	fn parallax_layers(&self) -> &[ParallaxLayer] {
+		&self.parallax_layers
+	}
+
+	fn texture(&self) -> &GLTexture {
+		&self.texture_collection.texture
+	}
+}
+
+impl GameScene for LevelScene {
+	fn as_scene(&self) -> &Scene {
+		self
+	}
+
+	fn update(&mut self, delta: f32) -> Transition {
+		LevelScene::update(self, delta);
+		Transition::none()
+	}
+
+	fn interpolate(&mut self, alpha: f32) {
+		LevelScene::interpolate(self, alpha);
+	}
+
+	fn handle_wheel_action(&mut self, action: WheelAction, delta: f32) {
+		match action {
+			WheelAction::ChangeViewSize => self.lighting.view_distance *= 1.0 + delta / 8.0,
+			WheelAction::ChangeViewSharpness => self.lighting.view_sharpness *= 1.0 + delta / 8.0,
+			_ => (),
+		};
+	}
+
+	fn handle_cursor(&mut self, position: Vec2) {
+		self.lighting.view_origin = position;
+	}
+
+	fn snapshot(&self, graphics: &Graphics) -> Option<Result<Snapshot, SceneFileError>> {
+		Some(LevelScene::snapshot(self, graphics))
+	}
+}
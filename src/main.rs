@@ -8,32 +8,58 @@ extern crate rusttype; // Rust TrueType library used for text drawing
 extern crate unicode_normalization; // Small Unicode utility that combines multiple code points (ex. diacritics).
 
 extern crate serde; // Serialize-deserialize rust library, used for configs and save files (probably)
+extern crate serde_json;
 extern crate serde_yaml;
 #[macro_use]
 extern crate serde_derive;
 
+extern crate flate2; // Save game compression
 extern crate image;
 extern crate rand; // For loading texture files
 
+mod bench;
+mod collision;
 mod config;
+mod ecs;
 mod graphics;
 mod input;
-
+mod level;
+mod prefab;
+mod profiler;
+mod random;
+mod save;
+mod scene_stack;
+mod timestep;
+mod tween;
+
+use bench::BenchSettings;
 use config::Configuration;
+use graphics::vector::Vec2;
 use graphics::{Graphics, TextureCollection};
 use input::Action as InputAction;
 use input::{Input, WheelAction};
+use level::LevelScene;
+use random::Random;
+use scene_stack::{SceneStack, Transition};
+use timestep::FixedTimestep;
 
 use glium::glutin;
 
 use std::io;
 
 const FONT_PREFIX: &str = "data/fonts/";
+const MATERIAL_PREFIX: &str = "data/materials/";
+const PREFAB_PREFIX: &str = "data/prefabs/";
+const SCENE_PREFIX: &str = "data/scenes/";
 const SHADER_PREFIX: &str = "data/shaders/";
 const TEXTURE_PREFIX: &str = "data/textures/";
+const TRACE_PREFIX: &str = "traces/";
 
 const CONFIG_NAME: &str = "config.yml";
 
+/// Seconds of the fade to black around loading a save
+const QUICK_LOAD_FADE: f32 = 0.5;
+
 const WINDOW_MIN_SIZE: (f64, f64) = (800.0, 600.0);
 const WINDOW_DEFAULT_SIZE: (f64, f64) = (800.0, 600.0);
 
@@ -44,14 +70,31 @@ struct WindowState {
 	last_pos: (f64, f64),
 	last_size: (f64, f64),
 	window_size: (f64, f64),
+	show_profiler: bool,
+	export_trace: bool,
 }
 
 fn main() {
 	let start_time = std::time::Instant::now();
 	let mut config = Configuration::load_or_default(std::path::Path::new(CONFIG_NAME));
+	let bench_settings = BenchSettings::from_args(std::env::args().skip(1));
+	let force_calibration = std::env::args().any(|arg| arg == "--calibrate");
+	let scene_name = std::env::args().find(|arg| arg.starts_with("--scene=")).map(|arg| String::from(&arg["--scene=".len()..]));
+	let seed_arg = std::env::args().find(|arg| arg.starts_with("--seed=")).and_then(|arg| {
+		let value = &arg["--seed=".len()..];
+		match value.parse() {
+			Ok(seed) => Some(seed),
+			Err(error) => {
+				println!("Ignoring --seed={}, expected a non-negative integer ({})", value, error);
+				None
+			}
+		}
+	});
+	let seed = seed_arg.or(config.seed).unwrap_or_else(random::time_seed);
 
 	if config.debug_mode {
 		println!("Loaded config: {:?}", config);
+		println!("Random seed: {} (replay with --seed={})", seed, seed);
 	}
 
 	// Use inside scope to close the window just before the program end.
@@ -64,6 +107,8 @@ fn main() {
 			last_pos: (0.0, 0.0),
 			window_size: WINDOW_DEFAULT_SIZE,
 			last_size: WINDOW_DEFAULT_SIZE,
+			show_profiler: false,
+			export_trace: false,
 		};
 
 		if let Some(size) = config.window_size {
@@ -75,7 +120,9 @@ fn main() {
 			.with_title("Rusty Game")
 			.with_min_dimensions(WINDOW_MIN_SIZE.into())
 			.with_dimensions(state.last_size.into());
-		let context_builder = glutin::ContextBuilder::new().with_vsync(config.vsync);
+		// Benchmarks measure how fast frames can be produced, so they never wait for vsync
+		let vsync = config.vsync && bench_settings.is_none();
+		let context_builder = glutin::ContextBuilder::new().with_vsync(vsync);
 		let display = glium::Display::new(window_builder, context_builder, &events_loop).unwrap();
 		let mut graphics = Graphics::new(display, &config).unwrap();
 
@@ -100,17 +147,46 @@ fn main() {
 
 		let texture_collection =
 			TextureCollection::new(&graphics, &vec!["test.png", "dark.png"]).unwrap();
+
+		if let Some(ref settings) = bench_settings {
+			let report = bench::run(settings, &mut graphics, &mut events_loop, texture_collection);
+			match report.save_as(&settings.output) {
+				Ok(_) => println!("Benchmark report written to {}", settings.output),
+				Err(error) => println!("Failed to write benchmark report to {}: {}", settings.output, error),
+			}
+			return;
+		}
+
 		let columns = count as u32 * 16;
 		let rows = count as u32 * 9;
 		let instance_count = columns * rows;
-		let mut scene = graphics::scene::TestScene::generate(
+		let scene = graphics::scene::TestScene::generate(
 			columns,
 			rows,
 			texture_collection,
 			String::from("test.png"),
 			String::from("dark.png"),
+			Random::new(seed),
 		);
 
+		// Batch size is measured again whenever the game runs on a different GPU or driver
+		let renderer = String::from(graphics.display.get_opengl_renderer_string());
+		let outdated = config.calibrated_renderer.as_ref() != Some(&renderer);
+		if force_calibration || (config.auto_batch_size && outdated) {
+			match graphics.calibrate_batch_size(&scene) {
+				Some(calibration) => {
+					if config.debug_mode {
+						for (batch_size, time) in calibration.timings.iter() {
+							println!("Batch size {:>5}: {:#?}", batch_size, time);
+						}
+						println!("Calibrated batch size for {}: {}", renderer, calibration.batch_size);
+					}
+					config.set_calibrated_batch_size(calibration.batch_size, renderer);
+				}
+				None => println!("Batch size calibration failed, keeping {}", config.batch_size),
+			}
+		}
+
 		if config.debug_mode {
 			println!(
 				"Loaded in {:#?}",
@@ -125,6 +201,28 @@ fn main() {
 			);
 		}
 
+		if config.profiler_csv.is_some() {
+			profiler::start_recording();
+		}
+		if let Some(budget) = config.trace_budget_ms {
+			profiler::set_hitch_budget(Some(std::time::Duration::from_micros((budget * 1000.0) as u64)));
+		}
+
+		let mut scenes = SceneStack::new();
+		let level = scene_name.and_then(|name| match LevelScene::load(&mut graphics, &name, Random::new(seed)) {
+			Ok(level) => Some(level),
+			Err(error) => {
+				println!("Failed to load scene {}: {}", name, error);
+				None
+			}
+		});
+		match level {
+			Some(level) => scenes.push(level),
+			None => scenes.push(scene),
+		}
+		let mut scene_actions = Vec::new();
+
+		let mut timestep = FixedTimestep::new(config.simulation_rate);
 		let begin = std::time::Instant::now();
 		let mut max_frametime = std::time::Duration::from_secs(0);
 		let mut min_frametime = std::time::Duration::from_secs(1000);
@@ -132,21 +230,66 @@ fn main() {
 
 		while !state.closed {
 			let frame_start = std::time::Instant::now();
+			profiler::begin_frame();
+			if profiler::take_hitch() || state.export_trace {
+				state.export_trace = false;
+				export_trace();
+			}
+
+			graphics.reload_changed_shaders();
+			{
+				let _scope = profiler::scope(profiler::SCOPE_UPDATE);
+				for _ in 0..timestep.advance() {
+					scenes.update(timestep.step_seconds());
+				}
+				scenes.interpolate(timestep.alpha());
+			}
+			if scenes.is_finished() {
+				break;
+			}
+
+			let cursor = graphics.screen_to_world(input.relative_mouse_position(), scenes.top().unwrap().as_scene());
+			scenes.handle_cursor(cursor);
 
-			scene.update();
-			scene.view_origin = graphics.screen_to_world(input.relative_mouse_position(), &scene);
-			graphics.draw(&scene);
-
-			events_loop.poll_events(|event| {
-				process_event(
-					&event,
-					&mut input,
-					&mut state,
-					|action, window_state| process_action(action, window_state, &graphics.window()),
-					|action, delta| process_wheel_action(action, delta, &mut scene),
-					config.debug_mode,
-				);
-			});
+			let fade = scenes.fade_opacity();
+			if fade > 0.0 {
+				let (width, height) = graphics.display.get_framebuffer_dimensions();
+				graphics
+					.overlay()
+					.rect(Vec2::zero(), Vec2::new(width as f32, height as f32), [0.0, 0.0, 0.0, fade]);
+			}
+			if state.show_profiler {
+				profiler::draw_overlay(graphics.overlay());
+			}
+			{
+				let _scope = profiler::scope(profiler::SCOPE_DRAW);
+				graphics.draw_scenes(&scenes.visible());
+			}
+
+			{
+				let _scope = profiler::scope(profiler::SCOPE_EVENTS);
+				events_loop.poll_events(|event| {
+					process_event(
+						&event,
+						&mut input,
+						&mut state,
+						|action, window_state| {
+							if let Some(action) = process_action(action, window_state, &graphics.window()) {
+								scene_actions.push(action);
+							}
+						},
+						|action, delta| scenes.handle_wheel_action(action, delta),
+						config.debug_mode,
+					);
+				});
+			}
+			for action in scene_actions.drain(..) {
+				match action {
+					InputAction::QuickSave => quick_save(&mut graphics, &scenes, config.compress_saves),
+					InputAction::QuickLoad => quick_load(&mut graphics, &mut scenes),
+					other => scenes.handle_action(other),
+				}
+			}
 
 			let frametime = std::time::Instant::now().duration_since(frame_start);
 			frames += 1;
@@ -176,6 +319,14 @@ fn main() {
 			);
 		}
 
+		if let Some(ref path) = config.profiler_csv {
+			// Finish the last frame so that it gets recorded
+			profiler::begin_frame();
+			if let Err(error) = profiler::with(|profiler| profiler.write_csv(path)) {
+				println!("Failed to write profiler statistics to {}: {}", path, error);
+			}
+		}
+
 		config.set_window_position(state.last_pos);
 		config.set_fullscreen(state.fullscreen);
 		config.set_window_size(state.last_size);
@@ -197,6 +348,52 @@ fn main() {
 	}
 }
 
+/// Write recently profiled scopes into a new file in the trace directory
+fn export_trace() {
+	let timestamp = match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+		Ok(time) => time.as_secs() * 1000 + time.subsec_millis() as u64,
+		Err(_) => 0,
+	};
+	let path = format!("{}trace_{}.json", TRACE_PREFIX, timestamp);
+
+	let result = std::fs::create_dir_all(TRACE_PREFIX).and_then(|_| profiler::with(|profiler| profiler.write_trace(&path)));
+	match result {
+		Ok(_) => println!("Exported trace to {}", path),
+		Err(error) => println!("Failed to export trace to {}: {}", path, error),
+	}
+}
+
+/// Save the top scene into the quick save slot, with a thumbnail of what is currently visible
+fn quick_save(graphics: &mut Graphics, scenes: &SceneStack, compress: bool) {
+	let snapshot = match scenes.top().and_then(|scene| scene.snapshot(graphics)) {
+		Some(Ok(snapshot)) => snapshot,
+		Some(Err(error)) => {
+			println!("Failed to capture the scene: {}", error);
+			return;
+		}
+		None => {
+			println!("The current scene can't be saved");
+			return;
+		}
+	};
+	let thumbnail = graphics.capture(&scenes.visible(), save::THUMBNAIL_SIZE);
+
+	match save::save(save::QUICKSAVE_SLOT, &snapshot, thumbnail.as_ref(), compress) {
+		Ok(_) => println!("Saved into {}", save::QUICKSAVE_SLOT),
+		Err(error) => println!("Failed to save into {}: {}", save::QUICKSAVE_SLOT, error),
+	}
+}
+
+/// Replace the top scene with the one stored in the quick save slot
+fn quick_load(graphics: &mut Graphics, scenes: &mut SceneStack) {
+	let result = save::load(save::QUICKSAVE_SLOT)
+		.and_then(|snapshot| LevelScene::restore(graphics, snapshot).map_err(save::SaveError::from));
+	match result {
+		Ok(level) => scenes.transition(Transition::replace(level).with_fade(QUICK_LOAD_FADE)),
+		Err(error) => println!("Failed to load {}: {}", save::QUICKSAVE_SLOT, error),
+	}
+}
+
 fn set_fullscreen(window: &glutin::GlWindow, fullscreen: bool, state: &mut WindowState) {
 	if fullscreen {
 		state.last_pos = match window.get_position() {
@@ -215,22 +412,19 @@ fn set_fullscreen(window: &glutin::GlWindow, fullscreen: bool, state: &mut Windo
 	state.fullscreen = fullscreen;
 }
 
-fn process_action(action: InputAction, window_state: &mut WindowState, window: &glutin::GlWindow) {
-	use InputAction::*;
+/// Handle actions that affect the window, returns actions meant for the scenes
+fn process_action(
+	action: InputAction,
+	window_state: &mut WindowState,
+	window: &glutin::GlWindow,
+) -> Option<InputAction> {
 	match action {
-		None => (),
-		ToggleFullscreen => set_fullscreen(window, !window_state.fullscreen, window_state),
+		InputAction::ToggleFullscreen => set_fullscreen(window, !window_state.fullscreen, window_state),
+		InputAction::ToggleProfiler => window_state.show_profiler = !window_state.show_profiler,
+		InputAction::ExportTrace => window_state.export_trace = true,
+		other => return Some(other),
 	}
-}
-
-fn process_wheel_action(action: WheelAction, delta: f32, scene: &mut graphics::scene::TestScene) {
-	use WheelAction::*;
-	match action {
-		None => (),
-		ChangeViewSize => scene.view_distance *= 1.0 + delta / 8.0,
-		ChangeViewSharpness => scene.sharpness *= 1.0 + delta / 8.0,
-		ChangeSceneSize => (),
-	};
+	None
 }
 
 // Process all window events
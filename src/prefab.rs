@@ -0,0 +1,304 @@
+// Reusable object definitions
+//
+// A prefab describes an object once, placements spawn copies of it with a few properties overridden.
+// Children are other prefabs attached with a transform relative to their parent, see HierarchySystem.
+
+use PREFAB_PREFIX;
+
+use ecs::components::{Parent, Sprite, Transform, Velocity};
+use ecs::{Entity, World};
+use graphics::instance::SpriteEffects;
+use graphics::material::{MaterialError, MaterialID};
+use graphics::texture::{TextureCollection, TextureID};
+use graphics::vector::Vec2;
+use graphics::Graphics;
+use level::SpriteDescription;
+
+use std::collections::HashMap as Map;
+use std::io::Error as IoError;
+use std::path::Path;
+
+pub type PrefabID = String;
+
+pub const PREFAB_EXTENSION: &str = ".yml";
+
+/// Contents of a prefab file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Prefab {
+	#[serde(default)]
+	pub sprite: Option<SpriteDescription>, // Prefabs without a sprite only group their children
+	#[serde(default = "default_scale")]
+	pub scale: Vec2,
+	#[serde(default)]
+	pub velocity: Option<Velocity>,
+	#[serde(default)]
+	pub children: Vec<Placement>, // Transforms of children are relative to the prefab
+}
+
+/// Properties of a single placement that differ from the prefab
+///
+/// Sprite properties are ignored for prefabs without a sprite.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrefabOverrides {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub texture_lit: Option<TextureID>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub texture_unlit: Option<TextureID>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub color_lit: Option<[f32; 4]>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub color_unlit: Option<[f32; 4]>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub material: Option<MaterialID>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub effects: Option<SpriteEffects>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub scale: Option<Vec2>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub velocity: Option<Velocity>,
+}
+
+impl PrefabOverrides {
+	pub fn is_empty(&self) -> bool {
+		self.texture_lit.is_none()
+			&& self.texture_unlit.is_none()
+			&& self.color_lit.is_none()
+			&& self.color_unlit.is_none()
+			&& self.material.is_none()
+			&& self.effects.is_none()
+			&& self.scale.is_none()
+			&& self.velocity.is_none()
+	}
+
+	fn apply(&self, prefab: &Prefab) -> Prefab {
+		let sprite = prefab.sprite.as_ref().map(|sprite| SpriteDescription {
+			texture_lit: self.texture_lit.clone().unwrap_or_else(|| sprite.texture_lit.clone()),
+			texture_unlit: self.texture_unlit.clone().unwrap_or_else(|| sprite.texture_unlit.clone()),
+			color_lit: self.color_lit.unwrap_or(sprite.color_lit),
+			color_unlit: self.color_unlit.unwrap_or(sprite.color_unlit),
+			material: self.material.clone().unwrap_or_else(|| sprite.material.clone()),
+			effects: self.effects.unwrap_or(sprite.effects),
+		});
+
+		Prefab {
+			sprite: sprite,
+			scale: self.scale.unwrap_or(prefab.scale),
+			velocity: self.velocity.or(prefab.velocity),
+			children: prefab.children.clone(),
+		}
+	}
+}
+
+/// A prefab placed in a scene file or attached to another prefab
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Placement {
+	pub prefab: PrefabID,
+	#[serde(default)]
+	pub transform: Transform, // Scale is multiplied by the prefab scale
+	#[serde(default, skip_serializing_if = "PrefabOverrides::is_empty")]
+	pub overrides: PrefabOverrides,
+}
+
+/// Root entity of an instantiated prefab, its children carry a Parent component instead
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PrefabInstance {
+	pub prefab: PrefabID,
+	pub overrides: PrefabOverrides,
+	pub scale: Vec2, // Prefab scale the placement transform was multiplied by
+}
+
+impl PrefabInstance {
+	/// The placement that would instantiate this prefab with the given transform
+	pub fn placement(&self, transform: &Transform) -> Placement {
+		let mut transform = transform.clone();
+		transform.scale = transform.scale / self.scale;
+		Placement {
+			prefab: self.prefab.clone(),
+			transform: transform,
+			overrides: self.overrides.clone(),
+		}
+	}
+}
+
+fn default_scale() -> Vec2 {
+	Vec2::splat(1.0)
+}
+
+#[derive(Debug)]
+pub enum PrefabError {
+	Io(PrefabID, IoError),                                   // Something went wrong trying to read the prefab file
+	Yaml(PrefabID, serde_yaml::Error),                       // Prefab file is not a valid description
+	Recursive(PrefabID),                                     // Prefab is its own descendant
+	MissingTexture { prefab: PrefabID, texture: TextureID }, // Texture is not part of the scene texture atlas
+	Material(MaterialError),                                 // Failed to load a sprite material
+}
+
+impl std::fmt::Display for PrefabError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			PrefabError::Io(prefab, error) => write!(f, "(IO) prefab {}: {}", prefab, error),
+			PrefabError::Yaml(prefab, error) => write!(f, "(Yaml) prefab {}: {}", prefab, error),
+			PrefabError::Recursive(prefab) => write!(f, "prefab {} contains itself", prefab),
+			PrefabError::MissingTexture { prefab, texture } => {
+				write!(f, "prefab {} uses texture {} which is not loaded by the scene", prefab, texture)
+			}
+			PrefabError::Material(error) => write!(f, "(Material) {}", error),
+		}
+	}
+}
+
+impl From<MaterialError> for PrefabError {
+	fn from(error: MaterialError) -> Self {
+		PrefabError::Material(error)
+	}
+}
+
+/// Prefabs loaded from PREFAB_PREFIX, each file is read once
+pub struct PrefabCollection {
+	prefabs: Map<PrefabID, Prefab>,
+}
+
+impl PrefabCollection {
+	pub fn new() -> Self {
+		Self { prefabs: Map::new() }
+	}
+
+	pub fn get(&self, id: &str) -> Option<&Prefab> {
+		self.prefabs.get(id)
+	}
+
+	/// Load a prefab together with all of its children, does nothing if it is already loaded
+	pub fn load(&mut self, id: &str) -> Result<&Prefab, PrefabError> {
+		self.load_recursive(id, &mut Vec::new())?;
+		Ok(&self.prefabs[id])
+	}
+
+	fn load_recursive(&mut self, id: &str, ancestors: &mut Vec<PrefabID>) -> Result<(), PrefabError> {
+		if ancestors.iter().any(|ancestor| ancestor == id) {
+			return Err(PrefabError::Recursive(String::from(id)));
+		}
+		if self.prefabs.contains_key(id) {
+			return Ok(());
+		}
+
+		let path = String::from(PREFAB_PREFIX) + id + PREFAB_EXTENSION;
+		let file = std::fs::File::open(Path::new(&path)).map_err(|error| PrefabError::Io(String::from(id), error))?;
+		let prefab: Prefab = serde_yaml::from_reader(file).map_err(|error| PrefabError::Yaml(String::from(id), error))?;
+
+		ancestors.push(String::from(id));
+		for child in prefab.children.iter() {
+			self.load_recursive(&child.prefab, ancestors)?;
+		}
+		ancestors.pop();
+
+		self.prefabs.insert(String::from(id), prefab);
+		Ok(())
+	}
+
+	/// Every texture a placement of a loaded prefab uses, including its children
+	pub fn textures(&self, placement: &Placement) -> Vec<TextureID> {
+		let mut textures = Vec::new();
+		self.collect_textures(placement, &mut textures);
+		textures
+	}
+
+	fn collect_textures(&self, placement: &Placement, textures: &mut Vec<TextureID>) {
+		let prefab = placement.overrides.apply(&self.prefabs[&placement.prefab]);
+		if let Some(sprite) = prefab.sprite {
+			for texture in vec![sprite.texture_lit, sprite.texture_unlit] {
+				if !textures.contains(&texture) {
+					textures.push(texture);
+				}
+			}
+		}
+		for child in prefab.children.iter() {
+			self.collect_textures(child, textures);
+		}
+	}
+
+	/// Spawn a prefab and its children into the world, returns the root entity
+	///
+	/// Textures have to be part of the scene texture atlas, materials are loaded when needed.
+	pub fn instantiate(
+		&mut self,
+		world: &mut World,
+		graphics: &mut Graphics,
+		textures: &TextureCollection,
+		placement: &Placement,
+	) -> Result<Entity, PrefabError> {
+		self.load(&placement.prefab)?;
+
+		let entity = self.spawn(world, graphics, textures, placement, None)?;
+		world.insert(
+			entity,
+			PrefabInstance {
+				prefab: placement.prefab.clone(),
+				overrides: placement.overrides.clone(),
+				scale: placement.overrides.scale.unwrap_or(self.prefabs[&placement.prefab].scale),
+			},
+		);
+		Ok(entity)
+	}
+
+	/// Spawn a loaded prefab, the placement transform is relative to the parent if there is one
+	fn spawn(
+		&self,
+		world: &mut World,
+		graphics: &mut Graphics,
+		textures: &TextureCollection,
+		placement: &Placement,
+		parent: Option<(Entity, &Transform)>,
+	) -> Result<Entity, PrefabError> {
+		let prefab = placement.overrides.apply(&self.prefabs[&placement.prefab]);
+		let sprite = match prefab.sprite {
+			Some(ref sprite) => Some(self.sprite(graphics, textures, &placement.prefab, sprite)?),
+			None => None,
+		};
+
+		let mut local = placement.transform.clone();
+		local.scale = local.scale * prefab.scale;
+		let transform = match parent {
+			Some((_, parent_transform)) => parent_transform.compose(&local),
+			None => local.clone(),
+		};
+
+		let entity = world.build().with(transform.clone()).entity();
+		if let Some(sprite) = sprite {
+			world.insert(entity, sprite);
+		}
+		if let Some(velocity) = prefab.velocity {
+			world.insert(entity, velocity);
+		}
+		if let Some((parent, _)) = parent {
+			world.insert(
+				entity,
+				Parent {
+					entity: parent,
+					local: local,
+				},
+			);
+		}
+
+		for child in prefab.children.iter() {
+			self.spawn(world, graphics, textures, child, Some((entity, &transform)))?;
+		}
+		Ok(entity)
+	}
+
+	fn sprite(
+		&self,
+		graphics: &mut Graphics,
+		textures: &TextureCollection,
+		id: &str,
+		sprite: &SpriteDescription,
+	) -> Result<Sprite, PrefabError> {
+		if let Some(texture) = sprite.missing_texture(textures) {
+			return Err(PrefabError::MissingTexture {
+				prefab: String::from(id),
+				texture: texture.clone(),
+			});
+		}
+		Ok(sprite.build(graphics, textures)?)
+	}
+}
@@ -0,0 +1,406 @@
+// Frame profiler
+//
+// Subsystems time their work with scope() guards and report counters, both are collected per frame.
+// Everything runs on the main thread, so the profiler is thread-local and needs no plumbing to be used.
+//
+// Individual scopes are also kept in a ring buffer that can be exported in Chrome tracing format
+// (chrome://tracing or ui.perfetto.dev) to inspect hitches.
+
+use graphics::overlay::Overlay;
+use graphics::vector::Vec2;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+// Scopes timed by the main loop and graphics
+pub const SCOPE_UPDATE: &str = "update";
+pub const SCOPE_UPLOAD: &str = "upload";
+pub const SCOPE_DRAW: &str = "draw";
+pub const SCOPE_EVENTS: &str = "events";
+
+// Counters reported by graphics
+pub const COUNTER_DRAW_CALLS: &str = "draw calls";
+pub const COUNTER_INSTANCES_SUBMITTED: &str = "instances submitted";
+pub const COUNTER_INSTANCES_CULLED: &str = "instances culled";
+pub const COUNTER_INSTANCE_BUFFER_USED: &str = "instance buffer used"; // peak bytes uploaded in a single batch
+pub const COUNTER_INSTANCE_BUFFER_SIZE: &str = "instance buffer size"; // bytes
+
+/// How many frames are kept for the overlay
+const HISTORY_LENGTH: usize = 240;
+/// How many scopes are kept for trace export, a few seconds worth of frames
+const TRACE_LENGTH: usize = 16384;
+/// Name of the event spanning a whole frame in traces
+const TRACE_FRAME: &str = "frame";
+/// Minimal time between two automatic trace exports, so that a slow stretch doesn't produce a trace every frame
+const HITCH_COOLDOWN_SECS: u64 = 2;
+
+/// Timings and counters of a single frame
+#[derive(Clone, Debug, Default)]
+pub struct FrameSample {
+	pub frametime: Duration,
+	pub scopes: Vec<(&'static str, Duration)>, // Time spent in each scope excluding nested scopes
+	pub counters: Vec<(&'static str, u64)>,
+}
+
+impl FrameSample {
+	pub fn scope(&self, name: &str) -> Duration {
+		match self.scopes.iter().find(|scope| scope.0 == name) {
+			Some(scope) => scope.1,
+			None => Duration::from_secs(0),
+		}
+	}
+
+	pub fn counter(&self, name: &str) -> u64 {
+		match self.counters.iter().find(|counter| counter.0 == name) {
+			Some(counter) => counter.1,
+			None => 0,
+		}
+	}
+
+	fn counter_mut(&mut self, name: &'static str) -> &mut u64 {
+		match self.counters.iter().position(|counter| counter.0 == name) {
+			Some(index) => &mut self.counters[index].1,
+			None => {
+				self.counters.push((name, 0));
+				&mut self.counters.last_mut().unwrap().1
+			}
+		}
+	}
+}
+
+/// A scope that hasn't finished yet
+struct OpenScope {
+	name: &'static str,
+	children: Duration, // Time spent in nested scopes
+}
+
+/// A finished scope as it appears in traces
+#[derive(Clone, Copy, Debug)]
+struct TraceEvent {
+	name: &'static str,
+	start: Duration, // since the profiler was created
+	duration: Duration,
+}
+
+pub struct Profiler {
+	frame_start: Option<Instant>,
+	current: FrameSample,
+	open_scopes: Vec<OpenScope>,
+	history: VecDeque<FrameSample>,
+	recording: Option<Vec<FrameSample>>, // Every frame since recording started, used for CSV export
+
+	epoch: Instant,
+	trace: VecDeque<TraceEvent>,
+	hitch_budget: Option<Duration>, // Frames longer than this are reported by take_hitch()
+	hitch: bool,
+	last_hitch: Option<Instant>,
+}
+
+thread_local! {
+	static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::new());
+}
+
+impl Profiler {
+	fn new() -> Self {
+		Self {
+			frame_start: None,
+			current: FrameSample::default(),
+			open_scopes: Vec::new(),
+			history: VecDeque::with_capacity(HISTORY_LENGTH),
+			recording: None,
+
+			epoch: Instant::now(),
+			trace: VecDeque::with_capacity(TRACE_LENGTH),
+			hitch_budget: None,
+			hitch: false,
+			last_hitch: None,
+		}
+	}
+
+	/// Most recent finished frames, oldest first
+	pub fn history(&self) -> &VecDeque<FrameSample> {
+		&self.history
+	}
+
+	/// Write all recorded frames as CSV, one row per frame with times in milliseconds
+	pub fn write_csv<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+		let frames = match self.recording {
+			Some(ref frames) => frames,
+			None => return Ok(()),
+		};
+
+		let scopes = [SCOPE_UPDATE, SCOPE_UPLOAD, SCOPE_DRAW, SCOPE_EVENTS];
+		let counters = [
+			COUNTER_DRAW_CALLS,
+			COUNTER_INSTANCES_SUBMITTED,
+			COUNTER_INSTANCES_CULLED,
+			COUNTER_INSTANCE_BUFFER_USED,
+			COUNTER_INSTANCE_BUFFER_SIZE,
+		];
+
+		let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+		write!(file, "frame,frametime")?;
+		for name in scopes.iter().chain(counters.iter()) {
+			write!(file, ",{}", name)?;
+		}
+		writeln!(file)?;
+
+		for (index, frame) in frames.iter().enumerate() {
+			write!(file, "{},{}", index, millis(frame.frametime))?;
+			for name in scopes.iter() {
+				write!(file, ",{}", millis(frame.scope(name)))?;
+			}
+			for name in counters.iter() {
+				write!(file, ",{}", frame.counter(name))?;
+			}
+			writeln!(file)?;
+		}
+
+		Ok(())
+	}
+
+	/// Write the buffered scopes as Chrome tracing JSON
+	pub fn write_trace<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+		// Timestamps are in microseconds
+		let micros = |duration: Duration| duration.as_secs() as f64 * 1000000.0 + duration.subsec_nanos() as f64 / 1000.0;
+
+		let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+		writeln!(file, "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[")?;
+		for (index, event) in self.trace.iter().enumerate() {
+			let separator = if index + 1 == self.trace.len() { "" } else { "," };
+			writeln!(
+				file,
+				"{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":1}}{}",
+				event.name,
+				if event.name == TRACE_FRAME { "frame" } else { "scope" },
+				micros(event.start),
+				micros(event.duration),
+				separator
+			)?;
+		}
+		writeln!(file, "]}}")?;
+
+		Ok(())
+	}
+
+	fn push_trace(&mut self, name: &'static str, start: Instant, duration: Duration) {
+		if self.trace.len() == TRACE_LENGTH {
+			self.trace.pop_front();
+		}
+		self.trace.push_back(TraceEvent {
+			name: name,
+			start: start.duration_since(self.epoch),
+			duration: duration,
+		});
+	}
+
+	fn begin_frame(&mut self) {
+		let now = Instant::now();
+		if let Some(start) = self.frame_start {
+			let mut frame = std::mem::replace(&mut self.current, FrameSample::default());
+			frame.frametime = now.duration_since(start);
+			self.push_trace(TRACE_FRAME, start, frame.frametime);
+
+			if let Some(budget) = self.hitch_budget {
+				let cooled_down = match self.last_hitch {
+					Some(last) => now.duration_since(last) >= Duration::from_secs(HITCH_COOLDOWN_SECS),
+					None => true,
+				};
+				if frame.frametime > budget && cooled_down {
+					self.hitch = true;
+					self.last_hitch = Some(now);
+				}
+			}
+
+			if let Some(ref mut frames) = self.recording {
+				frames.push(frame.clone());
+			}
+			if self.history.len() == HISTORY_LENGTH {
+				self.history.pop_front();
+			}
+			self.history.push_back(frame);
+		} else {
+			// Anything recorded before the first frame, like loading, doesn't belong to it
+			self.current = FrameSample::default();
+		}
+		self.frame_start = Some(now);
+	}
+
+	fn end_scope(&mut self, name: &'static str, start: Instant, duration: Duration) {
+		self.push_trace(name, start, duration);
+
+		let scope = self.open_scopes.pop().expect("profiler scopes ended out of order!");
+		debug_assert!(scope.name == name, "profiler scopes ended out of order!");
+
+		if let Some(parent) = self.open_scopes.last_mut() {
+			parent.children += duration;
+		}
+
+		let exclusive = duration - scope.children.min(duration);
+		match self.current.scopes.iter_mut().find(|scope| scope.0 == name) {
+			Some(scope) => scope.1 += exclusive,
+			None => self.current.scopes.push((name, exclusive)),
+		}
+	}
+}
+
+/// Times everything until dropped
+pub struct ScopeTimer {
+	name: &'static str,
+	start: Instant,
+}
+
+impl Drop for ScopeTimer {
+	fn drop(&mut self) {
+		let duration = Instant::now().duration_since(self.start);
+		PROFILER.with(|profiler| profiler.borrow_mut().end_scope(self.name, self.start, duration));
+	}
+}
+
+/// Start timing a scope, the time is recorded when the returned timer is dropped
+///
+/// Scopes can be nested, time spent in nested scopes is not counted towards the outer one.
+pub fn scope(name: &'static str) -> ScopeTimer {
+	PROFILER.with(|profiler| {
+		profiler.borrow_mut().open_scopes.push(OpenScope {
+			name: name,
+			children: Duration::from_secs(0),
+		})
+	});
+	ScopeTimer {
+		name: name,
+		start: Instant::now(),
+	}
+}
+
+/// Finish the previous frame and start a new one
+pub fn begin_frame() {
+	PROFILER.with(|profiler| profiler.borrow_mut().begin_frame());
+}
+
+/// Add to a counter of the current frame
+pub fn add_counter(name: &'static str, value: u64) {
+	PROFILER.with(|profiler| *profiler.borrow_mut().current.counter_mut(name) += value);
+}
+
+/// Raise a counter of the current frame to value if it is lower
+pub fn max_counter(name: &'static str, value: u64) {
+	PROFILER.with(|profiler| {
+		let mut profiler = profiler.borrow_mut();
+		let counter = profiler.current.counter_mut(name);
+		if *counter < value {
+			*counter = value;
+		}
+	});
+}
+
+/// Keep every frame from now on, so that they can be exported with write_csv()
+pub fn start_recording() {
+	PROFILER.with(|profiler| {
+		let mut profiler = profiler.borrow_mut();
+		if profiler.recording.is_none() {
+			profiler.recording = Some(Vec::new());
+		}
+	});
+}
+
+/// Report frames that take longer than budget through take_hitch()
+pub fn set_hitch_budget(budget: Option<Duration>) {
+	PROFILER.with(|profiler| profiler.borrow_mut().hitch_budget = budget);
+}
+
+/// Did a frame exceed the hitch budget since the last call
+pub fn take_hitch() -> bool {
+	PROFILER.with(|profiler| std::mem::replace(&mut profiler.borrow_mut().hitch, false))
+}
+
+/// Access the profiler of the current thread
+pub fn with<R, F: FnOnce(&Profiler) -> R>(function: F) -> R {
+	PROFILER.with(|profiler| function(&profiler.borrow()))
+}
+
+pub fn millis(duration: Duration) -> f32 {
+	duration.as_secs() as f32 * 1000.0 + duration.subsec_nanos() as f32 / 1000000.0
+}
+
+// Overlay layout in pixels
+const OVERLAY_MARGIN: f32 = 8.0;
+const OVERLAY_PADDING: f32 = 6.0;
+const OVERLAY_WIDTH: f32 = 2.0 * OVERLAY_PADDING + HISTORY_LENGTH as f32;
+const GRAPH_HEIGHT: f32 = 64.0;
+const GRAPH_MAX_MS: f32 = 50.0; // Frametime at the top of the graph
+const TARGET_MS: f32 = 1000.0 / 60.0;
+
+const COLOR_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.7];
+const COLOR_TEXT: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const COLOR_FAST: [f32; 4] = [0.2, 0.9, 0.2, 1.0];
+const COLOR_SLOW: [f32; 4] = [0.9, 0.8, 0.1, 1.0];
+const COLOR_HITCH: [f32; 4] = [0.9, 0.2, 0.2, 1.0];
+const COLOR_TARGET: [f32; 4] = [1.0, 1.0, 1.0, 0.4];
+
+/// Fill the overlay with statistics of the recent frames
+pub fn draw_overlay(overlay: &mut Overlay) {
+	with(|profiler| {
+		let history = profiler.history();
+		let last = match history.back() {
+			Some(last) => last,
+			None => return,
+		};
+
+		let total: Duration = history.iter().map(|frame| frame.frametime).sum();
+		let average = millis(total) / history.len() as f32;
+
+		let mut lines = vec![format!("FPS {:.1} ({:.2} ms)", 1000.0 / average, average)];
+		lines.push(String::new());
+		for name in [SCOPE_UPDATE, SCOPE_UPLOAD, SCOPE_DRAW, SCOPE_EVENTS].iter() {
+			lines.push(format!("{:<8} {:>7.3} ms", name, millis(last.scope(name))));
+		}
+		lines.push(String::new());
+		lines.push(format!("{} {}", COUNTER_DRAW_CALLS, last.counter(COUNTER_DRAW_CALLS)));
+		lines.push(format!(
+			"instances {} drawn, {} culled",
+			last.counter(COUNTER_INSTANCES_SUBMITTED),
+			last.counter(COUNTER_INSTANCES_CULLED)
+		));
+		lines.push(format!(
+			"instance buffer {} / {} KiB",
+			last.counter(COUNTER_INSTANCE_BUFFER_USED) / 1024,
+			last.counter(COUNTER_INSTANCE_BUFFER_SIZE) / 1024
+		));
+
+		let line_height = overlay.line_height();
+		let height = 3.0 * OVERLAY_PADDING + GRAPH_HEIGHT + line_height * lines.len() as f32;
+		overlay.rect(Vec2::splat(OVERLAY_MARGIN), Vec2::new(OVERLAY_WIDTH, height), COLOR_BACKGROUND);
+
+		// Frametime graph, newest frame on the right
+		let graph_left = OVERLAY_MARGIN + OVERLAY_PADDING;
+		let graph_bottom = OVERLAY_MARGIN + OVERLAY_PADDING + GRAPH_HEIGHT;
+		let offset = (HISTORY_LENGTH - history.len()) as f32;
+		for (index, frame) in history.iter().enumerate() {
+			let frametime = millis(frame.frametime);
+			let bar = (frametime / GRAPH_MAX_MS).min(1.0) * GRAPH_HEIGHT;
+			let color = if frametime <= TARGET_MS {
+				COLOR_FAST
+			} else if frametime <= TARGET_MS * 2.0 {
+				COLOR_SLOW
+			} else {
+				COLOR_HITCH
+			};
+			overlay.rect(Vec2::new(graph_left + offset + index as f32, graph_bottom - bar), Vec2::new(1.0, bar), color);
+		}
+		let target = TARGET_MS / GRAPH_MAX_MS * GRAPH_HEIGHT;
+		overlay.rect(
+			Vec2::new(graph_left, graph_bottom - target),
+			Vec2::new(HISTORY_LENGTH as f32, 1.0),
+			COLOR_TARGET,
+		);
+
+		let mut y = graph_bottom + OVERLAY_PADDING;
+		for line in lines {
+			overlay.text(Vec2::new(graph_left, y), &line, COLOR_TEXT);
+			y += line_height;
+		}
+	});
+}
@@ -0,0 +1,292 @@
+// Save games
+//
+// Every slot is a directory in the per-user save directory holding the snapshot, its metadata and a thumbnail.
+// Metadata is kept separately so slots can be listed without reading whole snapshots.
+// Snapshots are versioned, older versions are upgraded on load by running migrations on the raw YAML.
+
+use ecs::components::{Transform, Velocity};
+use graphics::math::Rect;
+use graphics::texture::TextureID;
+use level::{BackgroundDescription, Lighting, ParallaxLayerDescription, SceneFileError, SpriteDescription};
+use prefab::PrefabInstance;
+use random::Random;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use image::{ImageError, RgbaImage};
+
+use serde_yaml::Value;
+
+use std::io::Error as IoError;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+/// Version of snapshots written by this build
+pub const SAVE_VERSION: u32 = 2;
+
+/// MIGRATIONS[n] upgrades a snapshot of version n + 1 to version n + 2
+const MIGRATIONS: &[fn(&mut Value)] = &[add_random];
+
+/// Version 2 stores random state, older sessions continue with seed 0
+fn add_random(snapshot: &mut Value) {
+	if let Value::Mapping(ref mut mapping) = *snapshot {
+		let random = serde_yaml::to_value(Random::new(0)).expect("random state is always serializable!");
+		mapping.insert(Value::String(String::from("random")), random);
+	}
+}
+
+/// Slot written and read by quick save and quick load
+pub const QUICKSAVE_SLOT: &str = "quicksave";
+/// Size of slot thumbnails in pixels
+pub const THUMBNAIL_SIZE: (u32, u32) = (256, 144);
+
+const GAME_DIRECTORY: &str = "rusty_game";
+const SNAPSHOT_NAME: &str = "snapshot.yml";
+const COMPRESSED_SNAPSHOT_NAME: &str = "snapshot.yml.gz";
+const METADATA_NAME: &str = "metadata.yml";
+const THUMBNAIL_NAME: &str = "thumbnail.png";
+
+/// Full simulation state of a level
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+	pub version: u32,
+	pub scene: String,            // Name of the scene file the session started from
+	pub textures: Vec<TextureID>, // Declared by the scene file
+	pub atlas: Vec<TextureID>,    // Every texture of the scene atlas, including prefab textures
+	pub view_rect: Rect,
+	pub preserve_ratio: bool,
+	pub lighting: Lighting,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub background: Option<BackgroundDescription>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub parallax_layers: Vec<ParallaxLayerDescription>,
+	pub entities: Vec<EntitySnapshot>,
+	pub playtime: f64, // Simulated seconds
+	pub random: Random,
+}
+
+/// Components of a single entity
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntitySnapshot {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub transform: Option<Transform>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub sprite: Option<SpriteDescription>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub velocity: Option<Velocity>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub parent: Option<ParentSnapshot>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub prefab: Option<PrefabInstance>,
+}
+
+/// Parent component with the entity stored as an index into Snapshot::entities
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ParentSnapshot {
+	pub index: usize,
+	pub local: Transform,
+}
+
+/// Summary of a slot shown without loading it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveMetadata {
+	pub version: u32,
+	pub timestamp: u64, // Seconds since the Unix epoch
+	pub playtime: f64,  // Simulated seconds
+	pub scene: String,
+	pub compressed: bool,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+	Io(IoError),             // Something went wrong trying to read or write a slot file
+	Yaml(serde_yaml::Error), // Snapshot or metadata is not a valid description
+	Image(ImageError),       // Failed to write the thumbnail
+	UnsupportedVersion(u32), // Snapshot was written by a newer build or is corrupted
+	MissingVersion,          // Snapshot has no version field
+	Scene(SceneFileError),   // Failed to take the snapshot or to build a scene from it
+}
+
+impl std::fmt::Display for SaveError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			SaveError::Io(error) => write!(f, "(IO) {}", error),
+			SaveError::Yaml(error) => write!(f, "(Yaml) {}", error),
+			SaveError::Image(error) => write!(f, "(Image) {}", error),
+			SaveError::UnsupportedVersion(version) => {
+				write!(f, "snapshot version {} is not supported, expected 1 to {}", version, SAVE_VERSION)
+			}
+			SaveError::MissingVersion => write!(f, "snapshot has no version"),
+			SaveError::Scene(error) => write!(f, "(Scene) {}", error),
+		}
+	}
+}
+
+impl From<IoError> for SaveError {
+	fn from(error: IoError) -> Self {
+		SaveError::Io(error)
+	}
+}
+
+impl From<serde_yaml::Error> for SaveError {
+	fn from(error: serde_yaml::Error) -> Self {
+		SaveError::Yaml(error)
+	}
+}
+
+impl From<ImageError> for SaveError {
+	fn from(error: ImageError) -> Self {
+		SaveError::Image(error)
+	}
+}
+
+impl From<SceneFileError> for SaveError {
+	fn from(error: SceneFileError) -> Self {
+		SaveError::Scene(error)
+	}
+}
+
+/// Per-user directory all slots are stored in
+///
+/// %APPDATA% on Windows, $XDG_DATA_HOME or ~/.local/share elsewhere, the working directory as a last resort.
+pub fn save_directory() -> PathBuf {
+	let base = if cfg!(windows) {
+		std::env::var_os("APPDATA").map(PathBuf::from)
+	} else {
+		std::env::var_os("XDG_DATA_HOME")
+			.map(PathBuf::from)
+			.or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+	};
+	base.unwrap_or_else(PathBuf::new).join(GAME_DIRECTORY).join("saves")
+}
+
+fn slot_directory(slot: &str) -> PathBuf {
+	save_directory().join(slot)
+}
+
+/// Write a snapshot into a slot, replacing what was there
+pub fn save(slot: &str, snapshot: &Snapshot, thumbnail: Option<&RgbaImage>, compress: bool) -> Result<(), SaveError> {
+	let directory = slot_directory(slot);
+	std::fs::create_dir_all(&directory)?;
+
+	let yaml = serde_yaml::to_string(snapshot)?;
+	let (name, stale_name) = if compress {
+		(COMPRESSED_SNAPSHOT_NAME, SNAPSHOT_NAME)
+	} else {
+		(SNAPSHOT_NAME, COMPRESSED_SNAPSHOT_NAME)
+	};
+	let bytes = if compress {
+		let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+		encoder.write_all(yaml.as_bytes())?;
+		encoder.finish()?
+	} else {
+		yaml.into_bytes()
+	};
+
+	// Write next to the old snapshot first, so a failed save doesn't destroy it
+	let temporary = directory.join(String::from(name) + ".tmp");
+	std::fs::write(&temporary, bytes)?;
+	std::fs::rename(&temporary, directory.join(name))?;
+	if directory.join(stale_name).exists() {
+		std::fs::remove_file(directory.join(stale_name))?;
+	}
+
+	let timestamp = match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+		Ok(time) => time.as_secs(),
+		Err(_) => 0,
+	};
+	let metadata = SaveMetadata {
+		version: snapshot.version,
+		timestamp: timestamp,
+		playtime: snapshot.playtime,
+		scene: snapshot.scene.clone(),
+		compressed: compress,
+	};
+	std::fs::write(directory.join(METADATA_NAME), serde_yaml::to_string(&metadata)?)?;
+
+	match thumbnail {
+		Some(thumbnail) => thumbnail.save(directory.join(THUMBNAIL_NAME))?,
+		None => {
+			if directory.join(THUMBNAIL_NAME).exists() {
+				std::fs::remove_file(directory.join(THUMBNAIL_NAME))?;
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Read the snapshot of a slot, upgrading it to SAVE_VERSION
+pub fn load(slot: &str) -> Result<Snapshot, SaveError> {
+	let directory = slot_directory(slot);
+
+	let compressed = directory.join(COMPRESSED_SNAPSHOT_NAME);
+	let mut yaml = String::new();
+	if compressed.exists() {
+		GzDecoder::new(std::fs::File::open(compressed)?).read_to_string(&mut yaml)?;
+	} else {
+		std::fs::File::open(directory.join(SNAPSHOT_NAME))?.read_to_string(&mut yaml)?;
+	}
+
+	let mut value: Value = serde_yaml::from_str(&yaml)?;
+	let version = match value.get("version").and_then(|version| version.as_u64()) {
+		Some(version) => version as u32,
+		None => return Err(SaveError::MissingVersion),
+	};
+	if version == 0 || version > SAVE_VERSION {
+		return Err(SaveError::UnsupportedVersion(version));
+	}
+	for migration in MIGRATIONS[(version - 1) as usize..].iter() {
+		migration(&mut value);
+	}
+
+	let mut snapshot: Snapshot = serde_yaml::from_value(value)?;
+	snapshot.version = SAVE_VERSION;
+	Ok(snapshot)
+}
+
+pub fn metadata(slot: &str) -> Result<SaveMetadata, SaveError> {
+	let file = std::fs::File::open(slot_directory(slot).join(METADATA_NAME))?;
+	Ok(serde_yaml::from_reader(file)?)
+}
+
+/// Thumbnail image of a slot, if it was saved with one
+pub fn thumbnail_path(slot: &str) -> Option<PathBuf> {
+	let path = slot_directory(slot).join(THUMBNAIL_NAME);
+	if path.exists() {
+		Some(path)
+	} else {
+		None
+	}
+}
+
+/// Names and metadata of all slots, the most recent first
+///
+/// Directories without readable metadata are skipped.
+pub fn slots() -> Result<Vec<(String, SaveMetadata)>, SaveError> {
+	let directory = save_directory();
+	if !directory.exists() {
+		return Ok(Vec::new());
+	}
+
+	let mut slots = Vec::new();
+	for entry in std::fs::read_dir(directory)? {
+		let entry = entry?;
+		if !entry.file_type()?.is_dir() {
+			continue;
+		}
+		if let Some(name) = entry.file_name().to_str() {
+			if let Ok(metadata) = metadata(name) {
+				slots.push((String::from(name), metadata));
+			}
+		}
+	}
+	slots.sort_by(|a, b| b.1.timestamp.cmp(&a.1.timestamp));
+	Ok(slots)
+}
+
+pub fn delete(slot: &str) -> Result<(), SaveError> {
+	Ok(std::fs::remove_dir_all(slot_directory(slot))?)
+}
@@ -0,0 +1,265 @@
+// Stack of game scenes, such as a title screen, gameplay and a pause menu on top of it
+//
+// Only the top scene receives input. Scenes decide whether the ones below them keep updating and are drawn,
+// so a pause menu can show a frozen game behind it while a heads-up display lets it run.
+
+use graphics::vector::Vec2;
+use graphics::scene::Scene;
+use graphics::Graphics;
+use input::{Action, WheelAction};
+use level::SceneFileError;
+use save::Snapshot;
+
+/// A scene that can be managed by SceneStack
+pub trait GameScene: Scene {
+	/// The scene as a plain Scene for drawing, implemented as `self`
+	fn as_scene(&self) -> &Scene;
+
+	/// Called when the scene is added to the stack
+	fn on_enter(&mut self) {}
+	/// Called when the scene is removed from the stack
+	fn on_exit(&mut self) {}
+
+	/// Advance the simulation by delta seconds
+	fn update(&mut self, delta: f32) -> Transition;
+	/// Prepare objects for drawing, alpha is the position between the previous and the current update
+	fn interpolate(&mut self, _alpha: f32) {}
+
+	fn handle_action(&mut self, _action: Action) -> Transition {
+		Transition::none()
+	}
+	fn handle_wheel_action(&mut self, _action: WheelAction, _delta: f32) {}
+	/// Cursor moved, position is in world coordinates of this scene
+	fn handle_cursor(&mut self, _position: Vec2) {}
+
+	/// Should the scene below keep updating while this one is above it
+	fn updates_below(&self) -> bool {
+		false
+	}
+	/// Should the scene below be drawn behind this one
+	fn renders_below(&self) -> bool {
+		false
+	}
+
+	/// State stored by a save game, None if the scene can't be saved
+	fn snapshot(&self, _graphics: &Graphics) -> Option<Result<Snapshot, SceneFileError>> {
+		None
+	}
+}
+
+/// Change of the scene stack
+pub enum Change {
+	None,
+	Push(Box<GameScene>),
+	Pop,
+	Replace(Box<GameScene>), // Pop the top scene and push another one
+	Quit,
+}
+
+/// Change requested by a scene, optionally hidden behind a fade to black
+pub struct Transition {
+	pub change: Change,
+	pub fade: Option<f32>, // total duration in seconds, the change happens halfway through
+}
+
+impl Transition {
+	pub fn none() -> Self {
+		Self::from(Change::None)
+	}
+
+	pub fn push<T: GameScene + 'static>(scene: T) -> Self {
+		Self::from(Change::Push(Box::new(scene)))
+	}
+
+	pub fn pop() -> Self {
+		Self::from(Change::Pop)
+	}
+
+	pub fn replace<T: GameScene + 'static>(scene: T) -> Self {
+		Self::from(Change::Replace(Box::new(scene)))
+	}
+
+	pub fn quit() -> Self {
+		Self::from(Change::Quit)
+	}
+
+	pub fn with_fade(self, duration: f32) -> Self {
+		Self {
+			fade: Some(duration),
+			..self
+		}
+	}
+}
+
+impl From<Change> for Transition {
+	fn from(change: Change) -> Self {
+		Self {
+			change: change,
+			fade: None,
+		}
+	}
+}
+
+/// A fade to black and back, with a change applied when the screen is black
+struct Fade {
+	duration: f32,
+	elapsed: f32,
+	change: Option<Change>,
+}
+
+pub struct SceneStack {
+	scenes: Vec<Box<GameScene>>,
+	fade: Option<Fade>,
+	quit: bool,
+}
+
+impl SceneStack {
+	pub fn new() -> Self {
+		Self {
+			scenes: Vec::new(),
+			fade: None,
+			quit: false,
+		}
+	}
+
+	pub fn push<T: GameScene + 'static>(&mut self, scene: T) {
+		self.apply(Change::Push(Box::new(scene)));
+	}
+
+	/// Are there no scenes left or did a scene ask to quit
+	pub fn is_finished(&self) -> bool {
+		self.quit || self.scenes.is_empty()
+	}
+
+	pub fn top(&self) -> Option<&GameScene> {
+		self.scenes.last().map(|scene| &**scene)
+	}
+
+	/// Start a transition, ignored while another faded transition is in progress
+	pub fn transition(&mut self, transition: Transition) {
+		if let Change::None = transition.change {
+			return;
+		}
+		if self.fade.is_some() {
+			return;
+		}
+
+		match transition.fade {
+			Some(duration) if duration > 0.0 => {
+				self.fade = Some(Fade {
+					duration: duration,
+					elapsed: 0.0,
+					change: Some(transition.change),
+				})
+			}
+			_ => self.apply(transition.change),
+		}
+	}
+
+	fn apply(&mut self, change: Change) {
+		match change {
+			Change::None => (),
+			Change::Push(mut scene) => {
+				scene.on_enter();
+				self.scenes.push(scene);
+			}
+			Change::Pop => {
+				if let Some(mut scene) = self.scenes.pop() {
+					scene.on_exit();
+				}
+			}
+			Change::Replace(scene) => {
+				self.apply(Change::Pop);
+				self.apply(Change::Push(scene));
+			}
+			Change::Quit => self.quit = true,
+		}
+	}
+
+	/// Index of the lowest scene for which every scene above it lets the ones below do something
+	fn lowest_index<F: Fn(&GameScene) -> bool>(&self, passes_below: F) -> usize {
+		let mut index = self.scenes.len().saturating_sub(1);
+		while index > 0 && passes_below(&*self.scenes[index]) {
+			index -= 1;
+		}
+		index
+	}
+
+	/// Update the top scene and all scenes it lets update, then apply requested transitions
+	pub fn update(&mut self, delta: f32) {
+		let lowest = self.lowest_index(|scene| scene.updates_below());
+		let mut transitions = Vec::new();
+		for scene in self.scenes[lowest..].iter_mut() {
+			transitions.push(scene.update(delta));
+		}
+		for transition in transitions {
+			self.transition(transition);
+		}
+
+		let mut change = None;
+		let mut finished = false;
+		if let Some(ref mut fade) = self.fade {
+			fade.elapsed += delta;
+			if fade.elapsed >= fade.duration / 2.0 {
+				change = fade.change.take();
+			}
+			finished = fade.elapsed >= fade.duration;
+		}
+		if let Some(change) = change {
+			self.apply(change);
+		}
+		if finished {
+			self.fade = None;
+		}
+	}
+
+	/// Interpolate the scenes that are updated, frozen scenes below them are pinned to their latest state
+	pub fn interpolate(&mut self, alpha: f32) {
+		let lowest_rendered = self.lowest_index(|scene| scene.renders_below());
+		let lowest_updated = self.lowest_index(|scene| scene.updates_below());
+		for (index, scene) in self.scenes.iter_mut().enumerate().skip(lowest_rendered) {
+			scene.interpolate(if index >= lowest_updated { alpha } else { 1.0 });
+		}
+	}
+
+	pub fn handle_action(&mut self, action: Action) {
+		let transition = match self.scenes.last_mut() {
+			Some(scene) => scene.handle_action(action),
+			None => return,
+		};
+		self.transition(transition);
+	}
+
+	pub fn handle_wheel_action(&mut self, action: WheelAction, delta: f32) {
+		if let Some(scene) = self.scenes.last_mut() {
+			scene.handle_wheel_action(action, delta);
+		}
+	}
+
+	pub fn handle_cursor(&mut self, position: Vec2) {
+		if let Some(scene) = self.scenes.last_mut() {
+			scene.handle_cursor(position);
+		}
+	}
+
+	/// Scenes that should be drawn, from the bottom to the top
+	pub fn visible(&self) -> Vec<&Scene> {
+		if self.scenes.is_empty() {
+			return Vec::new();
+		}
+
+		let lowest = self.lowest_index(|scene| scene.renders_below());
+		self.scenes[lowest..].iter().map(|scene| scene.as_scene()).collect()
+	}
+
+	/// Opacity of the black fade over the screen
+	pub fn fade_opacity(&self) -> f32 {
+		match self.fade {
+			Some(ref fade) => {
+				let half = fade.duration / 2.0;
+				(1.0 - (fade.elapsed - half).abs() / half).max(0.0).min(1.0)
+			}
+			None => 0.0,
+		}
+	}
+}
@@ -0,0 +1,108 @@
+// Fixed-timestep simulation clock
+//
+// The simulation always advances in steps of the same length, independent of the frame rate.
+// Leftover time is kept in an accumulator and rendering interpolates between the last two simulated states.
+
+use graphics::math::Lerp;
+
+use std::collections::HashMap as Map;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Most steps simulated in one frame, if the simulation can't keep up the remaining time is dropped
+/// instead of making every following frame slower (the "spiral of death")
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+pub struct FixedTimestep {
+	step: Duration,
+	accumulator: Duration,
+	last_time: Instant,
+}
+
+impl FixedTimestep {
+	/// Simulate steps_per_second steps of equal length every second
+	pub fn new(steps_per_second: u32) -> Self {
+		let steps_per_second = steps_per_second.max(1);
+		Self {
+			step: Duration::from_secs(1) / steps_per_second,
+			accumulator: Duration::from_secs(0),
+			last_time: Instant::now(),
+		}
+	}
+
+	/// Length of a single step in seconds
+	pub fn step_seconds(&self) -> f32 {
+		self.step.as_secs() as f32 + self.step.subsec_nanos() as f32 / 1000000000.0
+	}
+
+	/// Account for the time passed since the last call, returns how many steps should be simulated
+	pub fn advance(&mut self) -> u32 {
+		let now = Instant::now();
+		self.accumulator += now.duration_since(self.last_time);
+		self.last_time = now;
+
+		let mut steps = 0;
+		while self.accumulator >= self.step {
+			self.accumulator -= self.step;
+			steps += 1;
+
+			if steps == MAX_STEPS_PER_FRAME {
+				// Fall behind real time rather than trying to catch up
+				self.accumulator = self.accumulator.min(self.step);
+				break;
+			}
+		}
+		steps
+	}
+
+	/// Where between the previous and the current simulated state the rendered frame is, in [0, 1]
+	pub fn alpha(&self) -> f32 {
+		let accumulated = self.accumulator.as_secs() as f32 + self.accumulator.subsec_nanos() as f32 / 1000000000.0;
+		(accumulated / self.step_seconds()).min(1.0)
+	}
+}
+
+/// Interpolate two snapshots of objects into output, in the order of the current snapshot
+///
+/// Objects are matched by their key, objects that have no previous state are output at their current state.
+pub fn interpolate<K, T>(previous: &[(K, T)], current: &[(K, T)], alpha: f32, output: &mut Vec<T>)
+where
+	K: Copy + Eq + Hash,
+	T: Lerp + Clone,
+{
+	// Objects usually keep their place, the lookup is only built once they don't
+	let mut by_key: Option<Map<K, &T>> = None;
+
+	output.clear();
+	for (index, &(key, ref state)) in current.iter().enumerate() {
+		let previous_state = match previous.get(index) {
+			Some(&(previous_key, ref previous_state)) if previous_key == key => Some(previous_state),
+			_ => by_key
+				.get_or_insert_with(|| previous.iter().map(|&(key, ref state)| (key, state)).collect())
+				.get(&key)
+				.cloned(),
+		};
+		output.push(match previous_state {
+			Some(previous_state) => Lerp::lerp(previous_state, state, alpha),
+			None => state.clone(),
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn matches_objects_by_key() {
+		let previous = [(1, 0.0), (2, 10.0), (3, 20.0)];
+		let mut output = Vec::new();
+
+		interpolate(&previous, &[(1, 2.0), (2, 12.0), (3, 22.0)], 0.5, &mut output);
+		assert_eq!(output, vec![1.0, 11.0, 21.0]);
+
+		// 1 was removed and swapped with the last object, 4 was spawned in its place
+		interpolate(&previous, &[(3, 22.0), (2, 12.0), (4, 50.0)], 0.5, &mut output);
+		assert_eq!(output, vec![21.0, 11.0, 50.0]);
+	}
+}
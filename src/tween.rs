@@ -0,0 +1,641 @@
+// Tweening, animating Lerp values over time
+//
+// Animations write into a context they are advanced with, usually the World of a scene.
+// Tweens can be chained into sequences and run side by side in parallel groups, which nest freely.
+
+use ecs::{Entity, World};
+use graphics::math::{Lerp, PI};
+
+/// Maps linear progress in [0, 1] to eased progress, which may overshoot for back and elastic curves
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+	Linear,
+	QuadIn,
+	QuadOut,
+	QuadInOut,
+	CubicIn,
+	CubicOut,
+	CubicInOut,
+	BackIn,
+	BackOut,
+	BackInOut,
+	ElasticIn,
+	ElasticOut,
+	ElasticInOut,
+	BounceIn,
+	BounceOut,
+	BounceInOut,
+	Bezier([f32; 4]), // Control points (x1, y1, x2, y2) of a cubic curve from (0, 0) to (1, 1), as in CSS
+}
+
+impl Default for Easing {
+	fn default() -> Self {
+		Easing::Linear
+	}
+}
+
+// Overshoot of back curves
+const BACK: f32 = 1.70158;
+const BACK_IN_OUT: f32 = BACK * 1.525;
+
+impl Easing {
+	pub fn apply(&self, t: f32) -> f32 {
+		match *self {
+			Easing::Linear => t,
+			Easing::QuadIn => t * t,
+			Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+			Easing::QuadInOut => {
+				if t < 0.5 {
+					2.0 * t * t
+				} else {
+					1.0 - (2.0 - 2.0 * t).powi(2) / 2.0
+				}
+			}
+			Easing::CubicIn => t * t * t,
+			Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+			Easing::CubicInOut => {
+				if t < 0.5 {
+					4.0 * t * t * t
+				} else {
+					1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+				}
+			}
+			Easing::BackIn => (BACK + 1.0) * t * t * t - BACK * t * t,
+			Easing::BackOut => 1.0 + (BACK + 1.0) * (t - 1.0).powi(3) + BACK * (t - 1.0).powi(2),
+			Easing::BackInOut => {
+				if t < 0.5 {
+					(2.0 * t).powi(2) * ((BACK_IN_OUT + 1.0) * 2.0 * t - BACK_IN_OUT) / 2.0
+				} else {
+					((2.0 * t - 2.0).powi(2) * ((BACK_IN_OUT + 1.0) * (2.0 * t - 2.0) + BACK_IN_OUT) + 2.0) / 2.0
+				}
+			}
+			Easing::ElasticIn => {
+				if t <= 0.0 || t >= 1.0 {
+					t.max(0.0).min(1.0)
+				} else {
+					-(2.0f32).powf(10.0 * t - 10.0) * ((10.0 * t - 10.75) * 2.0 * PI / 3.0).sin()
+				}
+			}
+			Easing::ElasticOut => {
+				if t <= 0.0 || t >= 1.0 {
+					t.max(0.0).min(1.0)
+				} else {
+					(2.0f32).powf(-10.0 * t) * ((10.0 * t - 0.75) * 2.0 * PI / 3.0).sin() + 1.0
+				}
+			}
+			Easing::ElasticInOut => {
+				if t <= 0.0 || t >= 1.0 {
+					t.max(0.0).min(1.0)
+				} else if t < 0.5 {
+					-(2.0f32).powf(20.0 * t - 10.0) * ((20.0 * t - 11.125) * 2.0 * PI / 4.5).sin() / 2.0
+				} else {
+					(2.0f32).powf(-20.0 * t + 10.0) * ((20.0 * t - 11.125) * 2.0 * PI / 4.5).sin() / 2.0 + 1.0
+				}
+			}
+			Easing::BounceIn => 1.0 - bounce_out(1.0 - t),
+			Easing::BounceOut => bounce_out(t),
+			Easing::BounceInOut => {
+				if t < 0.5 {
+					(1.0 - bounce_out(1.0 - 2.0 * t)) / 2.0
+				} else {
+					(1.0 + bounce_out(2.0 * t - 1.0)) / 2.0
+				}
+			}
+			Easing::Bezier([x1, y1, x2, y2]) => {
+				let s = solve_bezier(x1, x2, t);
+				bezier(y1, y2, s)
+			}
+		}
+	}
+}
+
+fn bounce_out(t: f32) -> f32 {
+	const N: f32 = 7.5625;
+	const D: f32 = 2.75;
+
+	if t < 1.0 / D {
+		N * t * t
+	} else if t < 2.0 / D {
+		let t = t - 1.5 / D;
+		N * t * t + 0.75
+	} else if t < 2.5 / D {
+		let t = t - 2.25 / D;
+		N * t * t + 0.9375
+	} else {
+		let t = t - 2.625 / D;
+		N * t * t + 0.984375
+	}
+}
+
+/// One coordinate of a cubic bezier from 0 to 1 with the given control points
+fn bezier(p1: f32, p2: f32, s: f32) -> f32 {
+	let inverse = 1.0 - s;
+	3.0 * inverse * inverse * s * p1 + 3.0 * inverse * s * s * p2 + s * s * s
+}
+
+/// Curve parameter at which the x coordinate equals x
+fn solve_bezier(x1: f32, x2: f32, x: f32) -> f32 {
+	let x = x.max(0.0).min(1.0);
+
+	// Newton's method converges in a few steps unless the slope is close to flat
+	let mut s = x;
+	for _ in 0..8 {
+		let error = bezier(x1, x2, s) - x;
+		if error.abs() < 1e-6 {
+			return s;
+		}
+		let inverse = 1.0 - s;
+		let slope = 3.0 * inverse * inverse * x1 + 6.0 * inverse * s * (x2 - x1) + 3.0 * s * s * (1.0 - x2);
+		if slope.abs() < 1e-6 {
+			break;
+		}
+		s = (s - error / slope).max(0.0).min(1.0);
+	}
+
+	// x grows monotonically with s for control points within [0, 1], so bisection always works
+	let (mut low, mut high) = (0.0, 1.0);
+	for _ in 0..32 {
+		s = (low + high) / 2.0;
+		if bezier(x1, x2, s) < x {
+			low = s;
+		} else {
+			high = s;
+		}
+	}
+	s
+}
+
+/// Something that plays over time, writing into a context of type C
+pub trait Animation<C> {
+	/// Advance by delta seconds, returns the part of delta left over after finishing
+	fn advance(&mut self, context: &mut C, delta: f32) -> f32;
+	/// Finished animations are not advanced any further, animations that repeat forever never finish
+	fn is_finished(&self) -> bool;
+}
+
+/// How often a tween plays after the first time
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Repeat {
+	Times(u32),
+	Forever,
+}
+
+/// Interpolates a value between two ends and hands it to a setter
+pub struct Tween<T: Lerp, C> {
+	from: T,
+	to: T,
+	duration: f32,
+	delay: f32, // only before the first play
+	easing: Easing,
+	repeat: Repeat,
+	yoyo: bool, // every other play runs backwards
+	elapsed: f32,
+	finished: bool,
+	set: Box<FnMut(&mut C, T)>,
+	on_complete: Option<Box<FnMut(&mut C)>>,
+}
+
+impl<T: Lerp + 'static, C: 'static> Tween<T, C> {
+	/// Linear tween played once, set receives the context and the current value
+	pub fn new<F: FnMut(&mut C, T) + 'static>(from: T, to: T, duration: f32, set: F) -> Self {
+		Self {
+			from: from,
+			to: to,
+			duration: duration.max(0.0),
+			delay: 0.0,
+			easing: Easing::Linear,
+			repeat: Repeat::Times(0),
+			yoyo: false,
+			elapsed: 0.0,
+			finished: false,
+			set: Box::new(set),
+			on_complete: None,
+		}
+	}
+
+	pub fn with_delay(mut self, delay: f32) -> Self {
+		self.delay = delay.max(0.0);
+		self
+	}
+
+	pub fn with_easing(mut self, easing: Easing) -> Self {
+		self.easing = easing;
+		self
+	}
+
+	pub fn with_repeat(mut self, repeat: Repeat) -> Self {
+		self.repeat = repeat;
+		self
+	}
+
+	/// Play every other repetition backwards
+	pub fn with_yoyo(mut self) -> Self {
+		self.yoyo = true;
+		self
+	}
+
+	/// Called once after the last value was set
+	pub fn on_complete<F: FnMut(&mut C) + 'static>(mut self, function: F) -> Self {
+		self.on_complete = Some(Box::new(function));
+		self
+	}
+
+	/// Value at a play index and linear progress within that play
+	fn value_at(&self, play: u32, progress: f32) -> T {
+		let progress = if self.yoyo && play % 2 == 1 { 1.0 - progress } else { progress };
+		T::lerp(&self.from, &self.to, self.easing.apply(progress))
+	}
+}
+
+impl<T: Lerp + 'static, C: 'static> Animation<C> for Tween<T, C> {
+	fn advance(&mut self, context: &mut C, delta: f32) -> f32 {
+		if self.finished {
+			return delta;
+		}
+		self.elapsed += delta;
+
+		let playing = self.elapsed - self.delay;
+		if playing < 0.0 {
+			return 0.0;
+		}
+
+		if let Repeat::Times(times) = self.repeat {
+			let total = self.delay + self.duration * (times + 1) as f32;
+			if self.elapsed >= total {
+				let value = self.value_at(times, 1.0);
+				(self.set)(context, value);
+				self.finished = true;
+				if let Some(ref mut on_complete) = self.on_complete {
+					on_complete(context);
+				}
+				return self.elapsed - total;
+			}
+		}
+
+		let value = if self.duration > 0.0 {
+			let play = (playing / self.duration) as u32;
+			self.value_at(play, (playing % self.duration) / self.duration)
+		} else {
+			// Zero length tweens that repeat forever just hold the end value
+			self.value_at(0, 1.0)
+		};
+		(self.set)(context, value);
+		0.0
+	}
+
+	fn is_finished(&self) -> bool {
+		self.finished
+	}
+}
+
+/// Animations played one after another
+pub struct Sequence<C> {
+	animations: Vec<Box<Animation<C>>>,
+	current: usize,
+}
+
+impl<C> Sequence<C> {
+	pub fn new() -> Self {
+		Self {
+			animations: Vec::new(),
+			current: 0,
+		}
+	}
+
+	pub fn then<A: Animation<C> + 'static>(mut self, animation: A) -> Self {
+		self.animations.push(Box::new(animation));
+		self
+	}
+
+	/// Wait before the next animation
+	pub fn wait(self, duration: f32) -> Self
+	where
+		C: 'static,
+	{
+		self.then(Wait::new(duration))
+	}
+
+	/// Call a function once the previous animations finished
+	pub fn call<F: FnMut(&mut C) + 'static>(self, function: F) -> Self
+	where
+		C: 'static,
+	{
+		self.then(Callback::new(function))
+	}
+}
+
+impl<C> Animation<C> for Sequence<C> {
+	fn advance(&mut self, context: &mut C, mut delta: f32) -> f32 {
+		while let Some(animation) = self.animations.get_mut(self.current) {
+			delta = animation.advance(context, delta);
+			if !animation.is_finished() {
+				return 0.0;
+			}
+			self.current += 1;
+		}
+		delta
+	}
+
+	fn is_finished(&self) -> bool {
+		self.current >= self.animations.len()
+	}
+}
+
+/// Animations played at the same time, finished when all of them are
+pub struct Parallel<C> {
+	animations: Vec<Box<Animation<C>>>,
+}
+
+impl<C> Parallel<C> {
+	pub fn new() -> Self {
+		Self { animations: Vec::new() }
+	}
+
+	pub fn with<A: Animation<C> + 'static>(mut self, animation: A) -> Self {
+		self.animations.push(Box::new(animation));
+		self
+	}
+}
+
+impl<C> Animation<C> for Parallel<C> {
+	fn advance(&mut self, context: &mut C, delta: f32) -> f32 {
+		// What is left over after the longest running animation
+		let mut left_over = delta;
+		for animation in self.animations.iter_mut() {
+			if !animation.is_finished() {
+				left_over = left_over.min(animation.advance(context, delta));
+			}
+		}
+
+		if self.is_finished() {
+			left_over
+		} else {
+			0.0
+		}
+	}
+
+	fn is_finished(&self) -> bool {
+		self.animations.iter().all(|animation| animation.is_finished())
+	}
+}
+
+/// Does nothing for a while, used as a gap in sequences
+pub struct Wait {
+	remaining: f32,
+}
+
+impl Wait {
+	pub fn new(duration: f32) -> Self {
+		Self { remaining: duration }
+	}
+}
+
+impl<C> Animation<C> for Wait {
+	fn advance(&mut self, _context: &mut C, delta: f32) -> f32 {
+		self.remaining -= delta;
+		if self.remaining <= 0.0 {
+			-self.remaining
+		} else {
+			0.0
+		}
+	}
+
+	fn is_finished(&self) -> bool {
+		self.remaining <= 0.0
+	}
+}
+
+/// Calls a function once, taking no time
+pub struct Callback<C> {
+	function: Box<FnMut(&mut C)>,
+	called: bool,
+}
+
+impl<C> Callback<C> {
+	pub fn new<F: FnMut(&mut C) + 'static>(function: F) -> Self {
+		Self {
+			function: Box::new(function),
+			called: false,
+		}
+	}
+}
+
+impl<C> Animation<C> for Callback<C> {
+	fn advance(&mut self, context: &mut C, delta: f32) -> f32 {
+		if !self.called {
+			(self.function)(context);
+			self.called = true;
+		}
+		delta
+	}
+
+	fn is_finished(&self) -> bool {
+		self.called
+	}
+}
+
+/// Handle of an animation played by Tweens
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TweenId(u64);
+
+/// Running animations of a context, finished ones are dropped
+pub struct Tweens<C> {
+	animations: Vec<(TweenId, Box<Animation<C>>)>,
+	next_id: u64,
+}
+
+impl<C> Tweens<C> {
+	pub fn new() -> Self {
+		Self {
+			animations: Vec::new(),
+			next_id: 0,
+		}
+	}
+
+	pub fn play<A: Animation<C> + 'static>(&mut self, animation: A) -> TweenId {
+		let id = TweenId(self.next_id);
+		self.next_id += 1;
+		self.animations.push((id, Box::new(animation)));
+		id
+	}
+
+	/// Stop an animation where it is, its completion callbacks are not called
+	pub fn stop(&mut self, id: TweenId) {
+		self.animations.retain(|(other, _)| *other != id);
+	}
+
+	pub fn is_playing(&self, id: TweenId) -> bool {
+		self.animations.iter().any(|(other, _)| *other == id)
+	}
+
+	pub fn len(&self) -> usize {
+		self.animations.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.animations.is_empty()
+	}
+
+	/// Advance all animations by delta seconds, in the order they were started
+	pub fn update(&mut self, context: &mut C, delta: f32) {
+		for (_, animation) in self.animations.iter_mut() {
+			animation.advance(context, delta);
+		}
+		self.animations.retain(|(_, animation)| !animation.is_finished());
+	}
+}
+
+/// Tween a component of an entity, does nothing once the entity or the component is gone
+pub fn component<T, U, F>(entity: Entity, from: T, to: T, duration: f32, mut set: F) -> Tween<T, World>
+where
+	T: Lerp + 'static,
+	U: 'static,
+	F: FnMut(&mut U, T) + 'static,
+{
+	Tween::new(from, to, duration, move |world: &mut World, value| {
+		if world.has::<U>(entity) {
+			if let Some(component) = world.write::<U>().get_mut(entity) {
+				set(component, value);
+			}
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const EPSILON: f32 = 1e-4;
+
+	fn close(a: f32, b: f32) -> bool {
+		(a - b).abs() < EPSILON
+	}
+
+	/// Tweens the first or second value of a pair from 0 to 10
+	fn first(duration: f32) -> Tween<f32, (f32, f32)> {
+		Tween::new(0.0, 10.0, duration, |context: &mut (f32, f32), value| context.0 = value)
+	}
+
+	fn second(duration: f32) -> Tween<f32, (f32, f32)> {
+		Tween::new(0.0, 10.0, duration, |context: &mut (f32, f32), value| context.1 = value)
+	}
+
+	#[test]
+	fn easings_start_at_0_and_end_at_1() {
+		let easings = [
+			Easing::Linear,
+			Easing::QuadIn,
+			Easing::QuadOut,
+			Easing::QuadInOut,
+			Easing::CubicIn,
+			Easing::CubicOut,
+			Easing::CubicInOut,
+			Easing::BackIn,
+			Easing::BackOut,
+			Easing::BackInOut,
+			Easing::ElasticIn,
+			Easing::ElasticOut,
+			Easing::ElasticInOut,
+			Easing::BounceIn,
+			Easing::BounceOut,
+			Easing::BounceInOut,
+			Easing::Bezier([0.25, 0.1, 0.25, 1.0]),
+			Easing::Bezier([0.68, -0.55, 0.27, 1.55]),
+		];
+		for easing in easings.iter() {
+			assert!(close(easing.apply(0.0), 0.0), "{:?} {}", easing, easing.apply(0.0));
+			assert!(close(easing.apply(1.0), 1.0), "{:?} {}", easing, easing.apply(1.0));
+		}
+	}
+
+	#[test]
+	fn linear_bezier() {
+		for step in 0..=20 {
+			let t = step as f32 / 20.0;
+			assert!(close(Easing::Bezier([0.0, 0.0, 1.0, 1.0]).apply(t), t), "{}", t);
+			assert!(close(bezier(0.0, 1.0, solve_bezier(0.0, 1.0, t)), t), "{}", t);
+			// Control points on the diagonal, evenly spaced, give a curve whose parameter is x itself
+			assert!(close(solve_bezier(1.0 / 3.0, 2.0 / 3.0, t), t), "{}", t);
+		}
+	}
+
+	#[test]
+	fn yoyo_ends_on_the_right_end() {
+		// An odd number of plays ends forwards, an even number backwards
+		for &(times, end) in [(0, 10.0), (1, 0.0), (2, 10.0), (3, 0.0)].iter() {
+			let mut value = -1.0;
+			let mut tween = Tween::new(0.0, 10.0, 1.0, |value: &mut f32, new| *value = new)
+				.with_repeat(Repeat::Times(times))
+				.with_yoyo();
+			let left_over = tween.advance(&mut value, times as f32 + 1.5);
+			assert!(tween.is_finished());
+			assert!(close(value, end), "{} repeats ended at {}", times, value);
+			assert!(close(left_over, 0.5), "{}", left_over);
+		}
+
+		let mut value = -1.0;
+		let mut tween = Tween::new(0.0, 10.0, 1.0, |value: &mut f32, new| *value = new)
+			.with_repeat(Repeat::Times(1))
+			.with_yoyo();
+		tween.advance(&mut value, 1.25);
+		assert!(close(value, 7.5), "{}", value);
+	}
+
+	#[test]
+	fn delay_and_repeat_without_yoyo() {
+		let mut value = -1.0;
+		let mut tween = Tween::new(0.0, 10.0, 1.0, |value: &mut f32, new| *value = new)
+			.with_delay(0.5)
+			.with_repeat(Repeat::Times(1));
+		assert_eq!(tween.advance(&mut value, 0.25), 0.0);
+		assert_eq!(value, -1.0);
+		tween.advance(&mut value, 1.5);
+		assert!(close(value, 2.5), "{}", value);
+		assert!(!tween.is_finished());
+		assert!(close(tween.advance(&mut value, 1.0), 0.25));
+		assert!(close(value, 10.0), "{}", value);
+	}
+
+	#[test]
+	fn sequence_carries_left_over_time() {
+		let mut context = (0.0, 0.0);
+		let mut sequence = Sequence::new().then(first(1.0)).wait(0.5).then(second(1.0));
+
+		assert_eq!(sequence.advance(&mut context, 1.25), 0.0);
+		assert!(close(context.0, 10.0) && close(context.1, 0.0), "{:?}", context);
+		assert_eq!(sequence.advance(&mut context, 0.75), 0.0);
+		assert!(close(context.1, 5.0), "{:?}", context);
+		assert!(close(sequence.advance(&mut context, 1.0), 0.5));
+		assert!(close(context.1, 10.0), "{:?}", context);
+		assert!(sequence.is_finished());
+	}
+
+	#[test]
+	fn parallel_carries_left_over_time() {
+		let mut context = (0.0, 0.0);
+		let mut parallel = Parallel::new().with(first(1.0)).with(second(2.0));
+
+		assert_eq!(parallel.advance(&mut context, 1.5), 0.0);
+		assert!(close(context.0, 10.0) && close(context.1, 7.5), "{:?}", context);
+		assert!(!parallel.is_finished());
+		assert!(close(parallel.advance(&mut context, 1.0), 0.5));
+		assert!(close(context.1, 10.0), "{:?}", context);
+		assert!(parallel.is_finished());
+	}
+
+	#[test]
+	fn nested_groups_carry_left_over_time() {
+		let mut context = (0.0, 0.0);
+		let mut sequence = Sequence::new()
+			.then(Parallel::new().with(first(1.0)).with(Wait::new(0.5)))
+			.then(second(1.0))
+			.call(|context: &mut (f32, f32)| context.0 = -1.0);
+
+		// The parallel group ends after one second, the rest goes to the second tween
+		sequence.advance(&mut context, 1.5);
+		assert!(close(context.0, 10.0) && close(context.1, 5.0), "{:?}", context);
+		assert!(close(sequence.advance(&mut context, 2.0), 1.5));
+		assert!(close(context.0, -1.0) && close(context.1, 10.0), "{:?}", context);
+	}
+}